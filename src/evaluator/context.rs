@@ -0,0 +1,318 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    io::{self, BufRead, Write},
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{modules::Modules, EvalError, EvalErrorKind};
+use crate::object::{gc, Environment};
+
+/// Where script output such as `puts` goes.
+pub enum Output {
+    Stdout,
+    /// Collects the output, so the host can read it back after evaluation.
+    Buffer(Rc<RefCell<Vec<u8>>>),
+    Writer(Box<dyn Write>),
+    /// Called once per line, without the trailing newline.
+    Callback(Box<dyn FnMut(&str)>),
+}
+
+impl Output {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        match self {
+            Output::Stdout => writeln!(io::stdout(), "{}", line),
+            Output::Buffer(buf) => writeln!(buf.borrow_mut(), "{}", line),
+            Output::Writer(w) => writeln!(w, "{}", line),
+            Output::Callback(f) => {
+                f(line);
+                Ok(())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout => io::stdout().flush(),
+            Output::Writer(w) => w.flush(),
+            Output::Buffer(_) | Output::Callback(_) => Ok(()),
+        }
+    }
+}
+
+/// Where scripts read input from.
+pub enum Input {
+    Stdin,
+    Reader(Box<dyn BufRead>),
+    /// Returns the next line, or `None` at the end of input.
+    Callback(Box<dyn FnMut() -> Option<String>>),
+}
+
+impl Input {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let n = match self {
+            Input::Stdin => io::stdin().lock().read_line(&mut line)?,
+            Input::Reader(r) => r.read_line(&mut line)?,
+            Input::Callback(f) => return Ok(f()),
+        };
+        if n == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+/// Resource limits for evaluating untrusted programs. They apply to each top-level `eval`; a
+/// limit of `None` is unbounded.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// Maximum number of statements and expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// Maximum depth of nested function calls.
+    pub max_depth: Option<usize>,
+    /// Maximum total size of the strings (in bytes), arrays and hashes (in elements) created.
+    pub max_memory: Option<usize>,
+    /// Maximum wall-clock time.
+    pub timeout: Option<Duration>,
+    /// Aborts evaluation once set to `true`, e.g. from another thread.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+// checking the clock on every step is measurably slow
+const CLOCK_INTERVAL: u64 = 256;
+
+// number of tracked environments before the first garbage collection
+const GC_THRESHOLD: usize = 1024;
+
+/// State shared by every environment of an evaluation: the host's I/O streams, the resource
+/// limits with their current usage, the environments tracked for garbage collection and the
+/// loaded modules.
+pub struct Context {
+    output: RefCell<Output>,
+    input: RefCell<Input>,
+    limits: RefCell<Limits>,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    memory: Cell<usize>,
+    started: Cell<Option<Instant>>,
+    envs: RefCell<Vec<Weak<RefCell<Environment>>>>,
+    prune_at: Cell<usize>,
+    // environments created since the last collection, and how many trigger the next one
+    created: Cell<usize>,
+    gc_threshold: Cell<usize>,
+    pub(super) modules: RefCell<Modules>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context").finish()
+    }
+}
+
+impl Context {
+    /// Creates a context connected to stdout and stdin.
+    pub fn new() -> Self {
+        Context {
+            output: RefCell::new(Output::Stdout),
+            input: RefCell::new(Input::Stdin),
+            limits: RefCell::new(Limits::default()),
+            steps: Cell::new(0),
+            depth: Cell::new(0),
+            memory: Cell::new(0),
+            started: Cell::new(None),
+            envs: RefCell::new(Vec::new()),
+            prune_at: Cell::new(GC_THRESHOLD),
+            created: Cell::new(0),
+            gc_threshold: Cell::new(GC_THRESHOLD),
+            modules: RefCell::new(Modules::default()),
+        }
+    }
+
+    pub fn set_output(&self, output: Output) {
+        *self.output.borrow_mut() = output;
+    }
+
+    pub fn set_input(&self, input: Input) {
+        *self.input.borrow_mut() = input;
+    }
+
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        self.output.borrow_mut().write_line(line)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.output.borrow_mut().flush()
+    }
+
+    /// Reads a line without its line ending, `None` means the input is exhausted.
+    pub fn read_line(&self) -> io::Result<Option<String>> {
+        self.input.borrow_mut().read_line()
+    }
+}
+
+impl Context {
+    pub fn set_limits(&self, limits: Limits) {
+        *self.limits.borrow_mut() = limits;
+    }
+
+    /// Sets new limits and returns the previous ones.
+    pub fn replace_limits(&self, limits: Limits) -> Limits {
+        self.limits.replace(limits)
+    }
+
+    /// Resets the usage counters at the start of a top-level evaluation. Nested evaluations,
+    /// e.g. from a native function, share the budget of the outer one.
+    pub fn begin(&self) {
+        if self.depth.get() == 0 {
+            self.steps.set(0);
+            self.memory.set(0);
+            self.started.set(Some(Instant::now()));
+        }
+    }
+
+    /// Accounts for one evaluation step and checks the step, time and cancellation limits.
+    pub fn step(&self) -> Result<(), EvalError> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+
+        let limits = self.limits.borrow();
+        if let Some(max) = limits.max_steps {
+            if steps > max {
+                return Err(limit_error(
+                    EvalErrorKind::StepLimit,
+                    format!("step limit exceeded: {} steps", max),
+                ));
+            }
+        }
+        if let Some(cancel) = &limits.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(limit_error(
+                    EvalErrorKind::Cancelled,
+                    "evaluation cancelled".to_string(),
+                ));
+            }
+        }
+        if let (Some(timeout), Some(started)) = (limits.timeout, self.started.get()) {
+            if steps % CLOCK_INTERVAL == 1 && started.elapsed() > timeout {
+                return Err(limit_error(
+                    EvalErrorKind::Timeout,
+                    format!("evaluation timed out after {:?}", timeout),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn enter_call(&self) -> Result<(), EvalError> {
+        let depth = self.depth.get() + 1;
+        if let Some(max) = self.limits.borrow().max_depth {
+            if depth > max {
+                return Err(limit_error(
+                    EvalErrorKind::DepthLimit,
+                    format!("maximum call depth exceeded: {}", max),
+                ));
+            }
+        }
+        self.depth.set(depth);
+        Ok(())
+    }
+
+    pub fn exit_call(&self) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+
+    /// Charges `size` against the memory limit.
+    pub fn allocate(&self, size: usize) -> Result<(), EvalError> {
+        self.memory.set(self.memory.get().saturating_add(size));
+        self.check_allocation(0)
+    }
+
+    /// Fails like `allocate(size)` would, without charging anything, so that something of
+    /// `size` is only built when it fits.
+    pub(crate) fn check_allocation(&self, size: usize) -> Result<(), EvalError> {
+        let memory = self.memory.get().saturating_add(size);
+        if let Some(max) = self.limits.borrow().max_memory {
+            if memory > max {
+                return Err(limit_error(
+                    EvalErrorKind::MemoryLimit,
+                    format!("memory limit exceeded: {}", max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn limit_error(kind: EvalErrorKind, message: String) -> EvalError {
+    EvalError {
+        kind,
+        message,
+        span: None,
+    }
+}
+
+impl Context {
+    /// Registers an environment with the garbage collector, which frees it once it is only kept
+    /// alive by closures stored in it.
+    pub fn track(&self, env: &Rc<RefCell<Environment>>) {
+        if env.borrow().tracked.replace(true) {
+            return;
+        }
+        let mut envs = self.envs.borrow_mut();
+        // a `Weak` keeps the allocation around, so forget the freed environments now and then
+        if envs.len() >= self.prune_at.get() {
+            envs.retain(|env| env.strong_count() > 0);
+            self.prune_at.set((envs.len() * 2).max(GC_THRESHOLD));
+        }
+        envs.push(Rc::downgrade(env));
+        self.created.set(self.created.get() + 1);
+    }
+
+    /// Frees the tracked environments that are unreachable except through reference cycles,
+    /// returning how many were freed.
+    pub fn collect_garbage(&self) -> usize {
+        let envs: Vec<_> = self
+            .envs
+            .borrow_mut()
+            .drain(..)
+            .filter_map(|env| env.upgrade())
+            .collect();
+        let freed = gc::collect(&envs);
+        let weak: Vec<_> = envs.iter().map(Rc::downgrade).collect();
+        drop(envs);
+
+        let mut tracked = self.envs.borrow_mut();
+        tracked.extend(weak.into_iter().filter(|env| env.strong_count() > 0));
+        self.prune_at.set((tracked.len() * 2).max(GC_THRESHOLD));
+        self.created.set(0);
+        // keeps the collections' cost proportional to the work done in between
+        self.gc_threshold.set(tracked.len().max(GC_THRESHOLD));
+        freed
+    }
+
+    /// Collects garbage at the end of a top-level evaluation when enough environments were
+    /// created since the last collection.
+    pub(crate) fn finish(&self) {
+        if self.depth.get() == 0 && self.created.get() >= self.gc_threshold.get() {
+            self.collect_garbage();
+        }
+    }
+}
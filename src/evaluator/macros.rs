@@ -0,0 +1,180 @@
+//! Quoting and macro expansion.
+//!
+//! Expansion runs over a program before it is evaluated: `define_macros` takes the top-level
+//! `let` statements bound to macro literals out of the program and binds them in the
+//! environment, then `expand_macros` replaces every call of such a binding by the quote the macro
+//! returns when applied to its quoted arguments.
+
+use std::{cell::RefCell, mem, rc::Rc};
+
+use super::{apply_function, eval, eval_expression, EvalError, EvalResult};
+use crate::{
+    lexer::token::Span,
+    object::{Environment, Function, Object},
+    parser::{
+        node::{
+            BooleanLiteral, CallExpression, Expression, IntegerLiteral, Node, Program,
+            QuoteExpression, Statement, StringLiteral,
+        },
+        visitor::{walk_expression_mut, VisitorMut},
+    },
+};
+
+/// Evaluates `quote`, replacing the `unquote`s of the quoted expression by their values.
+pub(super) fn eval_quote(quote: &QuoteExpression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let mut expression = quote.expression.clone();
+    let mut unquoter = Unquoter { env, error: None };
+    unquoter.visit_expression_mut(&mut expression);
+    match unquoter.error {
+        Some(err) => Err(err),
+        None => Ok(Rc::new(Object::Quote(Rc::new(expression)))),
+    }
+}
+
+struct Unquoter {
+    env: Rc<RefCell<Environment>>,
+    error: Option<EvalError>,
+}
+
+impl VisitorMut for Unquoter {
+    fn visit_expression_mut(&mut self, exp: &mut Expression) {
+        if self.error.is_some() {
+            return;
+        }
+        match exp {
+            Expression::Unquote(unquote) => {
+                let span = unquote.span;
+                let result = eval_expression(&unquote.expression, Rc::clone(&self.env))
+                    .and_then(|obj| splice(&obj, span).ok_or_else(|| cannot_unquote(&obj)));
+                match result {
+                    Ok(spliced) => *exp = spliced,
+                    Err(err) => self.error = Some(err.at(span)),
+                }
+            }
+            // a nested quote keeps its unquotes for when it is evaluated itself
+            Expression::Quote(_) => {}
+            _ => walk_expression_mut(self, exp),
+        }
+    }
+}
+
+/// The expression standing for `obj` in quoted code.
+fn splice(obj: &Object, span: Span) -> Option<Expression> {
+    match obj {
+        Object::Int(value) => Some(Expression::Integer(IntegerLiteral {
+            value: *value,
+            span,
+        })),
+        Object::Bool(value) => Some(Expression::Boolean(BooleanLiteral {
+            value: *value,
+            span,
+        })),
+        Object::String(value) => Some(Expression::String(StringLiteral {
+            value: value.clone(),
+            span,
+        })),
+        Object::Quote(exp) => Some((**exp).clone()),
+        _ => None,
+    }
+}
+
+fn cannot_unquote(obj: &Object) -> EvalError {
+    EvalError::new(format!(
+        "cannot unquote {}: only integers, booleans, strings and quotes can be",
+        obj.inspect()
+    ))
+}
+
+/// Removes the macro definitions from the top level of `program` and binds them in `env`.
+pub fn define_macros(
+    program: &mut Program,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), EvalError> {
+    let (definitions, body) = mem::take(&mut program.body)
+        .into_iter()
+        .partition(is_macro_definition);
+    program.body = body;
+    for stmt in definitions {
+        eval(&Node::Statement(Box::new(stmt)), Rc::clone(env))?;
+    }
+    Ok(())
+}
+
+fn is_macro_definition(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Let(stmt) => matches!(stmt.value, Expression::Macro(_)),
+        _ => false,
+    }
+}
+
+/// Replaces the calls of the macros bound in `env` by their expansion, innermost calls first.
+pub fn expand_macros(
+    program: &mut Program,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), EvalError> {
+    let ctx = env.borrow().context();
+    ctx.begin();
+    let mut expander = Expander { env, error: None };
+    expander.visit_program_mut(program);
+    ctx.finish();
+    match expander.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+struct Expander<'a> {
+    env: &'a Rc<RefCell<Environment>>,
+    error: Option<EvalError>,
+}
+
+impl Expander<'_> {
+    /// The macro `call` calls, if its function is the name of one.
+    fn macro_called(&self, call: &CallExpression) -> Option<Rc<Function>> {
+        let name = match &call.function {
+            Expression::Identifier(ident) => &ident.name,
+            _ => return None,
+        };
+        match self.env.borrow().get(name).as_deref() {
+            Some(Object::Macro(m)) => Some(Rc::clone(m)),
+            _ => None,
+        }
+    }
+
+    fn expand(&self, m: Rc<Function>, call: &CallExpression) -> Result<Expression, EvalError> {
+        let args: Vec<Rc<Object>> = call
+            .arguments
+            .iter()
+            .map(|arg| Rc::new(Object::Quote(Rc::new(arg.clone()))))
+            .collect();
+        let ctx = self.env.borrow().context();
+        match &*apply_function(&Object::Function(m), &args, &ctx)? {
+            Object::Quote(exp) => Ok((**exp).clone()),
+            obj => Err(EvalError::new(format!(
+                "macro {} returned {}, not a quote",
+                call.function,
+                obj.inspect()
+            ))),
+        }
+    }
+}
+
+impl VisitorMut for Expander<'_> {
+    fn visit_expression_mut(&mut self, exp: &mut Expression) {
+        if self.error.is_some() {
+            return;
+        }
+        walk_expression_mut(self, exp);
+        if self.error.is_some() {
+            return;
+        }
+        if let Expression::Call(call) = exp {
+            if let Some(m) = self.macro_called(call) {
+                match self.expand(m, call) {
+                    Ok(expanded) => *exp = expanded,
+                    Err(err) => self.error = Some(err.at(call.span)),
+                }
+            }
+        }
+    }
+}
@@ -1,20 +1,76 @@
 use core::fmt;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
+
+use indexmap::IndexMap;
 
 use crate::{
-    lexer::{punctuator::Punctuator, token::TokenKind},
-    object::{self, Builtin, Environment, Function, MonkeyHash, Object},
-    parser::node::{BlockStatement, Expression, HashLiteral, Node, Program, Statement},
+    lexer::{
+        punctuator::Punctuator,
+        token::{Span, TokenKind},
+    },
+    object::{self, Builtin, Environment, Function, HashKey, MonkeyHash, Object},
+    parser::node::{
+        Alternative, Binding, BlockStatement, Expression, HashLiteral, IdentifierExpression,
+        IfExpression, MatchExpression, Node, Pattern, Program, SliceExpression, Statement,
+    },
+    resolver::Resolver,
+};
+
+pub use self::{
+    context::{Context, Input, Limits, Output},
+    macros::{define_macros, expand_macros},
 };
 
+mod context;
+mod macros;
+mod modules;
 #[cfg(test)]
 mod tests;
 
 pub type EvalResult = Result<Rc<Object>, EvalError>;
 
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 2 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EvalErrorKind {
+    /// An error in the program itself, e.g. a type mismatch.
+    Runtime,
+    // The following are raised when a `Limits` of the context is exceeded.
+    StepLimit,
+    DepthLimit,
+    MemoryLimit,
+    Timeout,
+    Cancelled,
+}
+
 #[derive(Debug)]
 pub struct EvalError {
+    pub kind: EvalErrorKind,
     pub message: String,
+    /// The innermost expression being evaluated when the error was raised, if any.
+    pub span: Option<Span>,
+}
+
+impl EvalError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        EvalError {
+            kind: EvalErrorKind::Runtime,
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Points the error at `span` unless it already points somewhere more precise.
+    pub(crate) fn at(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Whether evaluation was aborted by a resource limit rather than by the program.
+    pub fn is_limit(&self) -> bool {
+        self.kind != EvalErrorKind::Runtime
+    }
 }
 
 impl fmt::Display for EvalError {
@@ -24,11 +80,34 @@ impl fmt::Display for EvalError {
 }
 
 pub fn eval(node: &Node, env: Rc<RefCell<Environment>>) -> EvalResult {
-    match node {
+    let ctx = env.borrow().context();
+    // the caller's own environment can end up in a cycle with its closures too
+    ctx.track(&env);
+    ctx.begin();
+    let result = match resolve(node) {
         Node::Program(ref p) => eval_program(p, env),
         Node::Expression(ref exp) => eval_expression(exp, env),
         Node::Statement(ref stmt) => eval_statement(stmt, env),
-    }
+    };
+    ctx.finish();
+    result
+}
+
+/// Resolves the identifiers of a copy of `node`. Undefined names are only reported once read,
+/// since the host or a later evaluation may define them as globals first.
+fn resolve(node: &Node) -> Node {
+    let mut node = node.clone();
+    Resolver::new().resolve_node(&mut node);
+    node
+}
+
+/// Evaluates `node` under `limits`, restoring the context's own limits afterwards.
+pub fn eval_with_limits(node: &Node, env: Rc<RefCell<Environment>>, limits: Limits) -> EvalResult {
+    let ctx = env.borrow().context();
+    let previous = ctx.replace_limits(limits);
+    let result = eval(node, env);
+    ctx.replace_limits(previous);
+    result
 }
 
 fn eval_program(p: &Program, env: Rc<RefCell<Environment>>) -> EvalResult {
@@ -45,10 +124,19 @@ fn eval_program(p: &Program, env: Rc<RefCell<Environment>>) -> EvalResult {
 }
 
 fn eval_expression(exp: &Expression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    eval_expression_inner(exp, env).map_err(|e| e.at(exp.span()))
+}
+
+fn eval_expression_inner(exp: &Expression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let ctx = env.borrow().context();
+    ctx.step()?;
     match exp {
-        Expression::Integer(num) => Ok(Rc::new(Object::Int(*num))),
-        Expression::Boolean(b) => Ok(Rc::new(Object::Bool(*b))),
-        Expression::String(s) => Ok(Rc::new(Object::String(s.clone()))),
+        Expression::Integer(lit) => Ok(Rc::new(Object::Int(lit.value))),
+        Expression::Boolean(lit) => Ok(Rc::new(Object::Bool(lit.value))),
+        Expression::String(lit) => {
+            ctx.allocate(lit.value.len())?;
+            Ok(Rc::new(Object::String(lit.value.clone())))
+        }
         Expression::Prefix(exp) => {
             let right = eval_expression(&exp.right, env)?;
             eval_prefix_expression(&exp.operator, right)
@@ -56,16 +144,16 @@ fn eval_expression(exp: &Expression, env: Rc<RefCell<Environment>>) -> EvalResul
         Expression::Infix(exp) => {
             let left = eval_expression(&exp.left, Rc::clone(&env))?;
             let right = eval_expression(&exp.right, env)?;
-            eval_infix_expression(&exp.operator, left, right)
+            let result = eval_infix_expression(&exp.operator, left, right)?;
+            ctx.allocate(allocation_size(&result))?;
+            Ok(result)
         }
-        Expression::If(if_exp) => {
-            let evaluated = eval_expression(&if_exp.condition, Rc::clone(&env))?;
+        Expression::If(if_exp) => eval_if_expression(if_exp, env),
+        Expression::Ternary(exp) => {
+            let evaluated = eval_expression(&exp.condition, Rc::clone(&env))?;
             match is_truthy(&evaluated) {
-                true => eval_block_statement(&if_exp.consequence, env),
-                false => match &if_exp.alternative {
-                    Some(alt) => eval_block_statement(alt, env),
-                    None => Ok(Rc::new(Object::Null)),
-                },
+                true => eval_expression(&exp.consequence, env),
+                false => eval_expression(&exp.alternative, env),
             }
         }
         Expression::Identifier(ident) => eval_identifier(ident, env),
@@ -80,10 +168,11 @@ fn eval_expression(exp: &Expression, env: Rc<RefCell<Environment>>) -> EvalResul
         Expression::Call(exp) => {
             let function = eval_expression(&exp.function, Rc::clone(&env))?;
             let args = eval_expressions(&exp.arguments, env)?;
-            apply_function(&function, &args)
+            apply_function(&function, &args, &ctx)
         }
         Expression::Array(a) => {
             let elements = eval_expressions(&a.elements, Rc::clone(&env))?;
+            ctx.allocate(elements.len())?;
             Ok(Rc::new(Object::Array(Rc::new(object::Array { elements }))))
         }
         Expression::Index(i) => {
@@ -91,23 +180,41 @@ fn eval_expression(exp: &Expression, env: Rc<RefCell<Environment>>) -> EvalResul
             let index = eval_expression(&i.index, env)?;
             eval_index_expression(left, index)
         }
-        Expression::Hash(h) => eval_hash_literal(&h, Rc::clone(&env)),
-        _ => Err(EvalError {
-            message: "unimplement eval expression".to_string(),
-        }),
+        Expression::Slice(slice) => eval_slice_expression(slice, env),
+        Expression::Match(exp) => eval_match_expression(exp, env),
+        Expression::Hash(h) => eval_hash_literal(h, Rc::clone(&env)),
+        Expression::Quote(quote) => macros::eval_quote(quote, env),
+        Expression::Unquote(_) => Err(EvalError::new("unquote outside of quote")),
+        Expression::Macro(m) => {
+            let m = Function {
+                parameters: m.parameters.clone(),
+                body: m.body.clone(),
+                env: Rc::clone(&env),
+            };
+            Ok(Rc::new(Object::Macro(Rc::new(m))))
+        }
+        Expression::Import(import) => modules::eval_import(import, &env),
+        Expression::Interpolation(exp) => {
+            let mut s = String::new();
+            for part in &exp.parts {
+                s.push_str(&eval_expression(part, Rc::clone(&env))?.inspect());
+            }
+            ctx.allocate(s.len())?;
+            Ok(Rc::new(Object::String(s)))
+        }
     }
 }
 
-fn eval_identifier(ident: &str, env: Rc<RefCell<Environment>>) -> Result<Rc<Object>, EvalError> {
-    match env.borrow().get(ident) {
-        Some(obj) => Ok(obj),
-        None => match Builtin::lookup(ident) {
-            Some(obj) => Ok(Rc::new(obj)),
-            None => Err(EvalError {
-                message: format!("identifier not found: {}", ident),
-            }),
-        },
-    }
+fn eval_identifier(ident: &IdentifierExpression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let obj = match ident.binding {
+        Some(Binding::Local { depth, slot }) => env.borrow().get_slot(depth, slot),
+        _ => env
+            .borrow()
+            .get(&ident.name)
+            .or_else(|| Builtin::lookup(&ident.name).map(Rc::new)),
+    };
+    // a local read before its `let` ran
+    obj.ok_or_else(|| EvalError::new(format!("identifier not found: {}", ident.name)))
 }
 
 fn eval_block_statement(
@@ -126,15 +233,11 @@ fn eval_block_statement(
 
     Ok(result)
 }
-fn is_truthy(obj: &Object) -> bool {
-    match obj {
-        Object::Null => false,
-        Object::Bool(false) => false,
-        _ => true,
-    }
+pub(crate) fn is_truthy(obj: &Object) -> bool {
+    !matches!(obj, Object::Null | Object::Bool(false))
 }
 
-fn eval_infix_expression(
+pub(crate) fn eval_infix_expression(
     operator: &TokenKind,
     left: Rc<Object>,
     right: Rc<Object>,
@@ -143,11 +246,21 @@ fn eval_infix_expression(
         (Object::Int(l), Object::Int(r)) => eval_integer_infix_expression(operator, *l, *r),
         (Object::Bool(l), Object::Bool(r)) => eval_bool_infix_expression(operator, *l, *r),
         (Object::String(l), Object::String(r)) => {
-            eval_string_infix_expression(operator, l.clone(), &*r)
+            eval_string_infix_expression(operator, l.clone(), r)
         }
-        _ => Err(EvalError {
-            message: format!("type mismatch: {:?} {} {:?}", left, operator, right),
-        }),
+        // arrays and hashes compare structurally, functions by identity
+        (l, r) if std::mem::discriminant(l) == std::mem::discriminant(r) => match operator {
+            TokenKind::Punctuator(Punctuator::Eq) => Ok(Rc::new(Object::Bool(left == right))),
+            TokenKind::Punctuator(Punctuator::NotEq) => Ok(Rc::new(Object::Bool(left != right))),
+            _ => Err(EvalError::new(format!(
+                "unknown operator: {:?} {} {:?}",
+                left, operator, right
+            ))),
+        },
+        _ => Err(EvalError::new(format!(
+            "type mismatch: {:?} {} {:?}",
+            left, operator, right
+        ))),
     }
 }
 
@@ -159,11 +272,10 @@ fn eval_bool_infix_expression(
     match operator {
         TokenKind::Punctuator(Punctuator::Eq) => Ok(Rc::new(Object::Bool(l == r))),
         TokenKind::Punctuator(Punctuator::NotEq) => Ok(Rc::new(Object::Bool(l != r))),
-        _ => {
-            return Err(EvalError {
-                message: format!("unknown operator: {} {} {}", l, operator, r),
-            })
-        }
+        _ => Err(EvalError::new(format!(
+            "unknown operator: {} {} {}",
+            l, operator, r
+        ))),
     }
 }
 
@@ -172,42 +284,55 @@ fn eval_integer_infix_expression(
     l: i64,
     r: i64,
 ) -> Result<Rc<Object>, EvalError> {
+    let overflow = || EvalError::new(format!("integer overflow: {} {} {}", l, operator, r));
     match operator {
-        TokenKind::Punctuator(Punctuator::Add) => Ok(Rc::new(Object::Int(l + r))),
-        TokenKind::Punctuator(Punctuator::Sub) => Ok(Rc::new(Object::Int(l - r))),
-        TokenKind::Punctuator(Punctuator::Mul) => Ok(Rc::new(Object::Int(l * r))),
-        TokenKind::Punctuator(Punctuator::Div) => Ok(Rc::new(Object::Int(l / r))),
+        TokenKind::Punctuator(Punctuator::Add) => {
+            Ok(Rc::new(Object::Int(l.checked_add(r).ok_or_else(overflow)?)))
+        }
+        TokenKind::Punctuator(Punctuator::Sub) => {
+            Ok(Rc::new(Object::Int(l.checked_sub(r).ok_or_else(overflow)?)))
+        }
+        TokenKind::Punctuator(Punctuator::Mul) => {
+            Ok(Rc::new(Object::Int(l.checked_mul(r).ok_or_else(overflow)?)))
+        }
+        TokenKind::Punctuator(Punctuator::Div) => {
+            if r == 0 {
+                return Err(EvalError::new(format!("division by zero: {} / {}", l, r)));
+            }
+            Ok(Rc::new(Object::Int(l.checked_div(r).ok_or_else(overflow)?)))
+        }
         TokenKind::Punctuator(Punctuator::GreaterThan) => Ok(Rc::new(Object::Bool(l > r))),
         TokenKind::Punctuator(Punctuator::LessThan) => Ok(Rc::new(Object::Bool(l < r))),
         TokenKind::Punctuator(Punctuator::Eq) => Ok(Rc::new(Object::Bool(l == r))),
         TokenKind::Punctuator(Punctuator::NotEq) => Ok(Rc::new(Object::Bool(l != r))),
-        _ => {
-            return Err(EvalError {
-                message: format!("unknown operator: {} {} {}", l, operator, r),
-            })
-        }
+        _ => Err(EvalError::new(format!(
+            "unknown operator: {} {} {}",
+            l, operator, r
+        ))),
     }
 }
 
-fn eval_prefix_expression(
+pub(crate) fn eval_prefix_expression(
     operator: &TokenKind,
     right: Rc<Object>,
 ) -> Result<Rc<Object>, EvalError> {
     match operator {
         TokenKind::Punctuator(Punctuator::Not) => eval_bang_operator_expression(right),
         TokenKind::Punctuator(Punctuator::Sub) => eval_minus_prefix_operator_expression(right),
-        _ => Err(EvalError {
-            message: format!("unknown prefix operator {}", operator),
-        }),
+        _ => Err(EvalError::new(format!(
+            "unknown prefix operator {}",
+            operator
+        ))),
     }
 }
 
 fn eval_minus_prefix_operator_expression(right: Rc<Object>) -> Result<Rc<Object>, EvalError> {
     match *right {
-        Object::Int(num) => Ok(Rc::new(Object::Int(-num))),
-        _ => Err(EvalError {
-            message: format!("unknown operator: -{:?}", right),
-        }),
+        Object::Int(num) => match num.checked_neg() {
+            Some(n) => Ok(Rc::new(Object::Int(n))),
+            None => Err(EvalError::new(format!("integer overflow: -{}", num))),
+        },
+        _ => Err(EvalError::new(format!("unknown operator: -{:?}", right))),
     }
 }
 
@@ -223,11 +348,11 @@ fn eval_bang_operator_expression(right: Rc<Object>) -> Result<Rc<Object>, EvalEr
 }
 
 fn eval_statement(stmt: &Statement, env: Rc<RefCell<Environment>>) -> EvalResult {
+    env.borrow().context().step()?;
     match stmt {
         Statement::Let(stmt) => {
             let exp = eval_expression(&stmt.value, Rc::clone(&env))?;
-            let obj = Rc::clone(&exp);
-            env.borrow_mut().set(stmt.name.clone(), obj);
+            bind_pattern(&stmt.pattern, Rc::clone(&exp), &env)?;
             Ok(exp)
         }
         Statement::Return(ret) => {
@@ -238,6 +363,105 @@ fn eval_statement(stmt: &Statement, env: Rc<RefCell<Environment>>) -> EvalResult
     }
 }
 
+/// Binds the names of `pattern` to the parts of `obj` it destructures, or fails if it does not
+/// match.
+fn bind_pattern(
+    pattern: &Pattern,
+    obj: Rc<Object>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), EvalError> {
+    let mut bindings = Vec::new();
+    destructure(pattern, obj, env, &mut bindings)??;
+    for (ident, obj) in bindings {
+        bind(ident, obj, env);
+    }
+    Ok(())
+}
+
+/// Matches `obj` against `pattern`, collecting the value of every name it binds. The inner
+/// result tells why the pattern does not match, the outer one is an error evaluating it.
+fn destructure<'p>(
+    pattern: &'p Pattern,
+    obj: Rc<Object>,
+    env: &Rc<RefCell<Environment>>,
+    bindings: &mut Vec<(&'p IdentifierExpression, Rc<Object>)>,
+) -> Result<Result<(), EvalError>, EvalError> {
+    let mismatch = |message: String| Ok(Err(EvalError::new(message).at(pattern.span())));
+    match (pattern, &*obj) {
+        (Pattern::Identifier(ident), _) => bindings.push((ident, obj)),
+        (Pattern::Wildcard { .. }, _) => {}
+        (Pattern::Literal(lit), _) => {
+            let value = eval_expression(lit, Rc::clone(env))?;
+            if value != obj {
+                return mismatch(format!(
+                    "{} does not match {}",
+                    obj.inspect(),
+                    value.inspect()
+                ));
+            }
+        }
+        (Pattern::Array(array), Object::Array(a)) => {
+            let (len, want) = (a.elements.len(), array.elements.len());
+            if len < want || (len > want && array.rest.is_none()) {
+                let at_least = if array.rest.is_some() {
+                    "at least "
+                } else {
+                    ""
+                };
+                return mismatch(format!(
+                    "wrong number of elements to destructure: want={}{}, got={}",
+                    at_least, want, len
+                ));
+            }
+            for (pattern, el) in array.elements.iter().zip(&a.elements) {
+                if let Err(err) = destructure(pattern, Rc::clone(el), env, bindings)? {
+                    return Ok(Err(err));
+                }
+            }
+            if let Some(rest) = &array.rest {
+                let elements = a.elements[want..].to_vec();
+                env.borrow().context().allocate(elements.len())?;
+                let rest_obj = Rc::new(Object::Array(Rc::new(object::Array { elements })));
+                bindings.push((rest, rest_obj));
+            }
+        }
+        (Pattern::Hash(hash), Object::Hash(h)) => {
+            for (key_exp, pattern) in &hash.pairs {
+                let key = eval_expression(key_exp, Rc::clone(env))?;
+                let key =
+                    HashKey::try_from(&*key).map_err(|e| EvalError::new(e).at(key_exp.span()))?;
+                let value = match h.pairs.get(&key) {
+                    Some(value) => Rc::clone(value),
+                    None => {
+                        return Ok(Err(EvalError::new(format!(
+                            "cannot destructure hash without key {}",
+                            key.to_object().inspect()
+                        ))
+                        .at(key_exp.span())))
+                    }
+                };
+                if let Err(err) = destructure(pattern, value, env, bindings)? {
+                    return Ok(Err(err));
+                }
+            }
+        }
+        (Pattern::Array(_), obj) => {
+            return mismatch(format!("cannot destructure {} as an array", obj.inspect()))
+        }
+        (Pattern::Hash(_), obj) => {
+            return mismatch(format!("cannot destructure {} as a hash", obj.inspect()))
+        }
+    }
+    Ok(Ok(()))
+}
+
+fn bind(ident: &IdentifierExpression, obj: Rc<Object>, env: &Rc<RefCell<Environment>>) {
+    match ident.binding {
+        Some(Binding::Local { slot, .. }) => env.borrow_mut().set_slot(slot, obj),
+        _ => env.borrow_mut().set(ident.name.clone(), obj),
+    }
+}
+
 fn eval_expressions(
     exps: &Vec<Expression>,
     env: Rc<RefCell<Environment>>,
@@ -245,44 +469,84 @@ fn eval_expressions(
     let mut objs = Vec::with_capacity(exps.len());
 
     for e in exps {
-        let res = eval_expression(&e, Rc::clone(&env))?;
+        let res = eval_expression(e, Rc::clone(&env))?;
         objs.push(res);
     }
 
     Ok(objs)
 }
 
-fn apply_function(func: &Object, args: &Vec<Rc<Object>>) -> EvalResult {
+pub fn apply_function(func: &Object, args: &Vec<Rc<Object>>, ctx: &Context) -> EvalResult {
     match func {
         Object::Function(f) => {
-            let extended_env = extend_function_env(f, args);
-            let evaluated = eval_block_statement(&f.body, extended_env)?;
-            Ok(unwrap_return_value(evaluated))
+            if f.parameters.len() != args.len() {
+                return Err(EvalError::new(format!(
+                    "wrong number of arguments: want={}, got={}",
+                    f.parameters.len(),
+                    args.len()
+                )));
+            }
+            let extended_env = extend_function_env(f, args)?;
+            ctx.enter_call()?;
+            // grow the stack on the heap for deep recursion, `Limits::max_depth` bounds it
+            let evaluated = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+                eval_block_statement(&f.body, extended_env)
+            });
+            ctx.exit_call();
+            Ok(unwrap_return_value(evaluated?))
         }
-        Object::Builtin(b) => match b.apply(args) {
-            Ok(obj) => Ok(obj),
-            Err(err) => Err(EvalError { message: err }),
-        },
-        f => Err(EvalError {
-            message: format!("{:?} is not a function", f),
-        }),
+        Object::Builtin(b) => {
+            let obj = b.apply(ctx, args)?;
+            ctx.allocate(allocation_size(&obj))?;
+            Ok(obj)
+        }
+        Object::Native(n) => {
+            if let Some(arity) = n.arity {
+                if arity != args.len() {
+                    return Err(EvalError::new(format!(
+                        "wrong number of arguments to {}: want={}, got={}",
+                        n.name,
+                        arity,
+                        args.len()
+                    )));
+                }
+            }
+            let obj = (n.func)(args)?;
+            ctx.allocate(allocation_size(&obj))?;
+            Ok(obj)
+        }
+        Object::Macro(_) => Err(EvalError::new(
+            "macros are expanded before evaluation and cannot be called at runtime",
+        )),
+        f => Err(EvalError::new(format!("{:?} is not a function", f))),
+    }
+}
+
+/// The size charged against `Limits::max_memory` for a newly created object: bytes of a
+/// string, elements of an array or pairs of a hash.
+fn allocation_size(obj: &Object) -> usize {
+    match obj {
+        Object::String(s) => s.len(),
+        Object::Array(a) => a.elements.len(),
+        Object::Hash(h) => h.pairs.len(),
+        _ => 0,
     }
 }
 
-fn extend_function_env(func: &Function, args: &Vec<Rc<Object>>) -> Rc<RefCell<Environment>> {
+fn extend_function_env(
+    func: &Function,
+    args: &[Rc<Object>],
+) -> Result<Rc<RefCell<Environment>>, EvalError> {
     let env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(
         &func.env,
     ))));
+    env.borrow().context().track(&env);
 
-    let mut args_iter = args.into_iter();
-
-    for param in &func.parameters {
-        let arg = args_iter.next().unwrap();
-
-        env.borrow_mut().set(param.name.clone(), Rc::clone(arg))
+    for (param, arg) in func.parameters.iter().zip(args) {
+        bind_pattern(param, Rc::clone(arg), &env)?;
     }
 
-    env
+    Ok(env)
 }
 
 fn unwrap_return_value(obj: Rc<Object>) -> Rc<Object> {
@@ -295,41 +559,154 @@ fn unwrap_return_value(obj: Rc<Object>) -> Rc<Object> {
 fn eval_string_infix_expression(operator: &TokenKind, left: String, right: &str) -> EvalResult {
     match operator {
         TokenKind::Punctuator(Punctuator::Add) => Ok(Rc::new(Object::String(left + right))),
-        _ => Err(EvalError {
-            message: format!("unknown operator: {} {} {}", left, operator, right),
-        }),
+        TokenKind::Punctuator(Punctuator::Eq) => Ok(Rc::new(Object::Bool(left == right))),
+        TokenKind::Punctuator(Punctuator::NotEq) => Ok(Rc::new(Object::Bool(left != right))),
+        _ => Err(EvalError::new(format!(
+            "unknown operator: {} {} {}",
+            left, operator, right
+        ))),
+    }
+}
+
+/// The position `i` counts from the end of something of length `len` when negative, as in
+/// Python. `None` when it is out of range.
+fn index_position(i: i64, len: usize) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let i = if i < 0 { i.checked_add(len)? } else { i };
+    if (0..len).contains(&i) {
+        Some(i as usize)
+    } else {
+        None
     }
 }
 
 fn eval_index_expression(left: Rc<Object>, index: Rc<Object>) -> EvalResult {
     match (&*left, &*index) {
-        (Object::Array(a), Object::Int(i)) => match a.elements.get(*i as usize) {
-            Some(el) => Ok(Rc::clone(el)),
-            None => Ok(Rc::new(Object::Null)),
-        },
-        (Object::Hash(h), _object) => match &*index {
-            Object::String(_) | Object::Int(_) | Object::Bool(_) => match h.pairs.get(&*index) {
+        (Object::Array(a), Object::Int(i)) => {
+            match index_position(*i, a.elements.len()).map(|i| &a.elements[i]) {
+                Some(el) => Ok(Rc::clone(el)),
+                None => Ok(Rc::new(Object::Null)),
+            }
+        }
+        (Object::String(s), Object::Int(i)) => {
+            let c = index_position(*i, s.chars().count()).and_then(|i| s.chars().nth(i));
+            match c {
+                Some(c) => Ok(Rc::new(Object::String(c.to_string()))),
+                None => Ok(Rc::new(Object::Null)),
+            }
+        }
+        (Object::Hash(h), _) => {
+            let key = HashKey::try_from(&*index).map_err(EvalError::new)?;
+            match h.pairs.get(&key) {
                 Some(obj) => Ok(Rc::clone(obj)),
                 None => Ok(Rc::new(Object::Null)),
-            },
-            _ => Err(EvalError {
-                message: format!("unusable as hash key: {}", index),
-            }),
+            }
+        }
+        _ => Err(EvalError::new(format!(
+            "index operator not supported {}",
+            index
+        ))),
+    }
+}
+
+fn eval_if_expression(if_exp: &IfExpression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let evaluated = eval_expression(&if_exp.condition, Rc::clone(&env))?;
+    match is_truthy(&evaluated) {
+        true => eval_block_statement(&if_exp.consequence, env),
+        false => match &if_exp.alternative {
+            Some(Alternative::Block(alt)) => eval_block_statement(alt, env),
+            Some(Alternative::If(alt)) => eval_if_expression(alt, env),
+            None => Ok(Rc::new(Object::Null)),
         },
-        _ => Err(EvalError {
-            message: format!("index operator not supported {}", index),
-        }),
+    }
+}
+
+fn eval_match_expression(exp: &MatchExpression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let value = eval_expression(&exp.value, Rc::clone(&env))?;
+    for arm in &exp.arms {
+        let mut bindings = Vec::new();
+        if destructure(&arm.pattern, Rc::clone(&value), &env, &mut bindings)?.is_err() {
+            continue;
+        }
+        // the names bound by an arm only live in its guard and body
+        let arm_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
+        env.borrow().context().track(&arm_env);
+        for (ident, obj) in bindings {
+            bind(ident, obj, &arm_env);
+        }
+        if let Some(guard) = &arm.guard {
+            if !is_truthy(&*eval_expression(guard, Rc::clone(&arm_env))?) {
+                continue;
+            }
+        }
+        return eval_expression(&arm.body, arm_env);
+    }
+    Err(EvalError::new(format!(
+        "non-exhaustive match: no arm matches {}",
+        value.inspect()
+    )))
+}
+
+fn eval_slice_expression(slice: &SliceExpression, env: Rc<RefCell<Environment>>) -> EvalResult {
+    let left = eval_expression(&slice.left, Rc::clone(&env))?;
+    let start = eval_slice_bound(&slice.start, Rc::clone(&env))?;
+    let end = eval_slice_bound(&slice.end, Rc::clone(&env))?;
+    // bounds out of range are clamped to it, as in Python
+    let range = |len: usize| {
+        let clamp = |i: i64| {
+            let len = i64::try_from(len).unwrap_or(i64::MAX);
+            let i = if i < 0 { len.saturating_add(i) } else { i };
+            i.clamp(0, len) as usize
+        };
+        let start = start.map_or(0, clamp);
+        start..end.map_or(len, clamp).max(start)
+    };
+    let ctx = env.borrow().context();
+    match &*left {
+        Object::Array(a) => {
+            let elements = a.elements[range(a.elements.len())].to_vec();
+            ctx.allocate(elements.len())?;
+            Ok(Rc::new(Object::Array(Rc::new(object::Array { elements }))))
+        }
+        Object::String(s) => {
+            let range = range(s.chars().count());
+            let sliced: String = s.chars().skip(range.start).take(range.len()).collect();
+            ctx.allocate(sliced.len())?;
+            Ok(Rc::new(Object::String(sliced)))
+        }
+        obj => Err(EvalError::new(format!(
+            "slice operator not supported {}",
+            obj
+        ))),
+    }
+}
+
+fn eval_slice_bound(
+    bound: &Option<Expression>,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Option<i64>, EvalError> {
+    let exp = match bound {
+        Some(exp) => exp,
+        None => return Ok(None),
+    };
+    match &*eval_expression(exp, env)? {
+        Object::Int(i) => Ok(Some(*i)),
+        obj => Err(
+            EvalError::new(format!("slice bound must be an integer, got {}", obj)).at(exp.span()),
+        ),
     }
 }
 
 fn eval_hash_literal(h: &HashLiteral, env: Rc<RefCell<Environment>>) -> EvalResult {
-    let mut pairs = HashMap::new();
+    let mut pairs = IndexMap::new();
 
     for (key_exp, val_exp) in &h.pairs {
         let key = eval_expression(key_exp, Rc::clone(&env))?;
+        let key = HashKey::try_from(&*key).map_err(EvalError::new)?;
         let value = eval_expression(val_exp, Rc::clone(&env))?;
         pairs.insert(key, value);
     }
+    env.borrow().context().allocate(pairs.len())?;
 
     Ok(Rc::new(Object::Hash(Rc::new(MonkeyHash { pairs }))))
 }
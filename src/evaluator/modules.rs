@@ -0,0 +1,155 @@
+//! Modules: source files loaded with `import "path"`.
+//!
+//! A module is evaluated in an environment of its own, and importing it gives a hash of the
+//! bindings its top level declares with `export let`. Each file is evaluated once per context;
+//! later imports of it get the same hash back.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs, iter,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use indexmap::IndexMap;
+
+use super::{define_macros, eval, expand_macros, Context, EvalError, EvalResult};
+use crate::{
+    lexer::Lexer,
+    object::{Environment, HashKey, MonkeyHash, Object},
+    parser::{
+        node::{ImportExpression, Node, Statement},
+        Parser,
+    },
+};
+
+#[derive(Debug, Default)]
+pub(super) struct Modules {
+    search_path: Vec<PathBuf>,
+    // the exports of the modules evaluated so far, by canonical path
+    loaded: HashMap<PathBuf, Rc<Object>>,
+    // the files being evaluated, the importing one last
+    loading: Vec<PathBuf>,
+}
+
+impl Context {
+    /// Sets the directories searched, in order, for modules not found next to the importing
+    /// file.
+    pub fn set_search_path(&self, dirs: Vec<PathBuf>) {
+        self.modules.borrow_mut().search_path = dirs;
+    }
+
+    pub fn search_path(&self) -> Vec<PathBuf> {
+        self.modules.borrow().search_path.clone()
+    }
+
+    /// Runs `f` as the evaluation of the source file `path`: its imports are looked up relative
+    /// to the file's directory, and importing the file itself is a cycle.
+    pub(crate) fn in_file<T, F: FnOnce() -> T>(&self, path: &Path, f: F) -> T {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.modules.borrow_mut().loading.push(path);
+        let result = f();
+        self.modules.borrow_mut().loading.pop();
+        result
+    }
+
+    /// Forgets the loaded modules, so the environments their exports hold can be collected.
+    pub(crate) fn forget_modules(&self) {
+        self.modules.borrow_mut().loaded.clear();
+    }
+
+    fn find_module(&self, name: &str) -> Result<PathBuf, EvalError> {
+        let modules = self.modules.borrow();
+        // the working directory when not importing from a file
+        let base = modules
+            .loading
+            .last()
+            .and_then(|file| file.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        iter::once(base)
+            .chain(modules.search_path.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+            .ok_or_else(|| EvalError::new(format!("module not found: {}", name)))
+    }
+
+    fn check_cycle(&self, path: &Path) -> Result<(), EvalError> {
+        let modules = self.modules.borrow();
+        match modules.loading.iter().position(|file| file == path) {
+            Some(i) => {
+                let chain: Vec<String> = modules.loading[i..]
+                    .iter()
+                    .chain(iter::once(&path.to_path_buf()))
+                    .map(|file| file.display().to_string())
+                    .collect();
+                Err(EvalError::new(format!(
+                    "import cycle: {}",
+                    chain.join(" -> ")
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+pub(super) fn eval_import(import: &ImportExpression, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let ctx = env.borrow().context();
+    let path = ctx.find_module(&import.path)?;
+    if let Some(exports) = ctx.modules.borrow().loaded.get(&path) {
+        return Ok(Rc::clone(exports));
+    }
+    ctx.check_cycle(&path)?;
+    let source = fs::read_to_string(&path)
+        .map_err(|err| EvalError::new(format!("cannot read module {}: {}", path.display(), err)))?;
+
+    // counted as a call, so the module shares the budget of the importing evaluation
+    ctx.enter_call()?;
+    let exports = ctx.in_file(&path, || load(&source, &ctx));
+    ctx.exit_call();
+    let exports = exports.map_err(|err| EvalError {
+        message: format!("in module {}: {}", path.display(), err.message),
+        span: None,
+        ..err
+    })?;
+    ctx.modules
+        .borrow_mut()
+        .loaded
+        .insert(path, Rc::clone(&exports));
+    Ok(exports)
+}
+
+fn load(source: &str, ctx: &Rc<Context>) -> EvalResult {
+    let mut program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            EvalError::new(format!("parse errors: {}", errors.join("; ")))
+        })?;
+    let names: Vec<String> = program
+        .body
+        .iter()
+        .flat_map(|stmt| match stmt {
+            Statement::Let(stmt) if stmt.exported => stmt.pattern.identifiers(),
+            _ => Vec::new(),
+        })
+        .map(|ident| ident.name.clone())
+        .collect();
+
+    let env = Rc::new(RefCell::new(Environment::with_context(Rc::clone(ctx))));
+    ctx.track(&env);
+    define_macros(&mut program, &env)?;
+    expand_macros(&mut program, &env)?;
+    eval(&Node::Program(Box::new(program)), Rc::clone(&env))?;
+
+    let pairs: IndexMap<HashKey, Rc<Object>> = names
+        .into_iter()
+        .filter_map(|name| {
+            let value = env.borrow().get(&name)?;
+            Some((HashKey::String(name), value))
+        })
+        .collect();
+    Ok(Rc::new(Object::Hash(Rc::new(MonkeyHash { pairs }))))
+}
@@ -1,4 +1,9 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
 use super::*;
 use crate::{
@@ -118,6 +123,18 @@ fn test_if_else_expression() {
         ("if (1 > 2) { 10 }", 0),
         ("if (1 > 2) { 10 } else { 20 }", 20),
         ("if (1 < 2) { 10 } else { 20 }", 10),
+        ("if 1 > 2 { 10 } else { 20 }", 20),
+        ("if 1 > 2 { 10 } else if 2 > 1 { 20 } else { 30 }", 20),
+        ("if 1 > 2 { 10 } else if 2 > 3 { 20 } else { 30 }", 30),
+        ("if 1 > 2 { 10 } else if 2 > 3 { 20 }", 0),
+        (
+            "let x = 5; if x < 0 { 1 } else if x < 3 { 2 } else if x < 6 { 3 }",
+            3,
+        ),
+        ("true ? 10 : 20", 10),
+        ("1 > 2 ? 10 : 20", 20),
+        ("let x = 2; x == 1 ? 10 : x == 2 ? 20 : 30", 20),
+        ("false ? 10 : if false { 1 }", 0),
     ];
     for t in tests {
         let evaluated = test_eval(t.0);
@@ -126,6 +143,12 @@ fn test_if_else_expression() {
             _ => test_integer_object(&evaluated, t.1),
         }
     }
+    // only the branch taken is evaluated
+    assert_eq!(test_eval("true ? 1 : 1 / 0").to_string(), "1");
+    assert_eq!(
+        test_eval("let f = fn(n) { if n == 0 { return 1; } else if n < 0 { return 0; } n * f(n - 1) }; f(5)").to_string(),
+        "120"
+    );
 }
 
 #[test]
@@ -203,10 +226,21 @@ fn test_error_handling() {
         ),
         ("foobar", "identifier not found: foobar"),
         (r#""Hello" - "World""#, "unknown operator: Hello - World"),
-        // (
-        //     r#" {"name": "Monkey"}[fn(x) { x }]; "#,
-        //     "unusable as hash key: fn(x) {\nx\n}",
-        // ),
+        (
+            r#" {"name": "Monkey"}[fn(x) { x }]; "#,
+            "unusable as hash key: fn(x) {\nx\n}",
+        ),
+        ("{[1]: 2}", "unusable as hash key: [1]"),
+        (r#"{"a": 1}[{"b": 2}]"#, "unusable as hash key: {b: 2}"),
+        ("fn(x) { x }()", "wrong number of arguments: want=1, got=0"),
+        ("fn() { 1 }(1, 2)", "wrong number of arguments: want=0, got=2"),
+        ("10 / (5 - 5)", "division by zero: 10 / 0"),
+        (
+            "9223372036854775807 + 1",
+            "integer overflow: 9223372036854775807 + 1",
+        ),
+        ("[1] < [2]", "unknown operator: Array(Array { elements: [Int(1)] }) < Array(Array { elements: [Int(2)] })"),
+        ("[1] == 1", "type mismatch: Array(Array { elements: [Int(1)] }) == Int(1)"),
     ];
     for t in tests {
         let env = Rc::new(RefCell::new(Environment::new()));
@@ -297,7 +331,7 @@ fn test_array_literals() {
     let obj = test_eval(input);
     match &*obj {
         Object::Array(a) => {
-            test_integer_object(a.elements.get(0).unwrap(), 1);
+            test_integer_object(a.elements.first().unwrap(), 1);
             test_integer_object(a.elements.get(1).unwrap(), 4);
             test_integer_object(a.elements.get(2).unwrap(), 6);
         }
@@ -319,8 +353,15 @@ fn test_array_index_expressions() {
             6,
         ),
         ("let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]", 2),
+        ("[1, 2, 3][-1]", 3),
+        ("[1, 2, 3][-3]", 1),
+    ];
+    let null_tests = [
+        ("[1, 2, 3][3]", "null"),
+        ("[1, 2, 3][-4]", "null"),
+        ("[][-1]", "null"),
+        ("[1][-9223372036854775807 - 1]", "null"),
     ];
-    let null_tests = [("[1, 2, 3][3]", "null"), ("[1, 2, 3][-1]", "null")];
     for t in tests {
         test_integer_object(&test_eval(t.0), t.1)
     }
@@ -329,6 +370,185 @@ fn test_array_index_expressions() {
     }
 }
 
+#[test]
+fn test_slice_expressions() {
+    let tests = [
+        ("[1, 2, 3, 4][1:3]", "[2, 3]"),
+        ("[1, 2, 3, 4][:2]", "[1, 2]"),
+        ("[1, 2, 3, 4][2:]", "[3, 4]"),
+        ("[1, 2, 3, 4][:]", "[1, 2, 3, 4]"),
+        ("[1, 2, 3, 4][-2:]", "[3, 4]"),
+        ("[1, 2, 3, 4][:-1]", "[1, 2, 3]"),
+        ("[1, 2, 3, 4][3:1]", "[]"),
+        ("[1, 2, 3, 4][-10:10]", "[1, 2, 3, 4]"),
+        ("let a = [1, 2, 3]; let i = 1; a[i:i + 1]", "[2]"),
+        (r#""héllo"[2:]"#, "llo"),
+        (r#""héllo"[1:-2]"#, "él"),
+        (r#""héllo"[-1]"#, "o"),
+        (r#""héllo"[-6]"#, "null"),
+        (r#""abc"[5:]"#, ""),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+    let errors = [
+        (r#"[1, 2]["a":]"#, "slice bound must be an integer, got a"),
+        ("{1: 2}[1:]", "slice operator not supported {1: 2}"),
+    ];
+    for t in errors {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+}
+
+#[test]
+fn test_destructuring() {
+    let tests = [
+        (
+            "let [a, b, ...rest] = [1, 2, 3, 4]; [a, b, rest]",
+            "[1, 2, [3, 4]]",
+        ),
+        ("let [a, ...rest] = [1]; rest", "[]"),
+        ("let [[a, b], c] = [[1, 2], 3]; a + b + c", "6"),
+        (
+            r#"let {"x": x, "y": y} = {"x": 1, "y": 2, "z": 3}; x * 10 + y"#,
+            "12",
+        ),
+        (r#"let {"p": [x, y]} = {"p": [4, 5]}; y"#, "5"),
+        (r#"let k = "x"; let {k: v} = {"x": 7}; v"#, "7"),
+        ("let [a, b] = [1, 2]", "[1, 2]"),
+        ("let f = fn([a, b], c) { a + b + c }; f([1, 2], 3)", "6"),
+        (
+            r#"let norm = fn({"x": x, "y": y}) { x * x + y * y }; norm({"x": 3, "y": 4})"#,
+            "25",
+        ),
+        (
+            "let f = fn([x, ...xs]) { if (len(xs) == 0) { x } else { x + f(xs) } }; f([1, 2, 3])",
+            "6",
+        ),
+        (
+            "let g = fn() { let [a, b] = [1, 2]; fn() { a + b } }; g()()",
+            "3",
+        ),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+
+    let errors = [
+        (
+            "let [a, b] = [1];",
+            "wrong number of elements to destructure: want=2, got=1",
+        ),
+        (
+            "let [a, b] = [1, 2, 3];",
+            "wrong number of elements to destructure: want=2, got=3",
+        ),
+        (
+            "let [a, b, ...c] = [1];",
+            "wrong number of elements to destructure: want=at least 2, got=1",
+        ),
+        ("let [a] = 1;", "cannot destructure 1 as an array"),
+        (r#"let {"a": a} = [1];"#, "cannot destructure [1] as a hash"),
+        (
+            r#"let {"a": a} = {"b": 1};"#,
+            "cannot destructure hash without key a",
+        ),
+        ("let {[]: a} = {};", "unusable as hash key: []"),
+        (
+            "let f = fn([a, b]) { a }; f(1)",
+            "cannot destructure 1 as an array",
+        ),
+    ];
+    for t in errors {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1, "{}", t.0),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+}
+
+#[test]
+fn test_match_expressions() {
+    let classify = r#"let classify = fn(v) {
+        match (v) {
+            0 => "zero",
+            -1 => "minus one",
+            "hi" => "greeting",
+            true => "yes",
+            [] => "empty",
+            [x] => "one: " + to_string(x),
+            [x, ...rest] if x > 10 => "big head of " + to_string(len(rest) + 1),
+            [_, _, ...rest] => "many",
+            {"name": name, "tags": [tag, ..._rest]} => name + " #" + tag,
+            {"name": name} => name,
+            n if n > 100 => "large",
+            other => "other " + to_string(other),
+        }
+    };"#;
+    let tests = [
+        ("classify(0)", "zero"),
+        ("classify(-1)", "minus one"),
+        (r#"classify("hi")"#, "greeting"),
+        ("classify(true)", "yes"),
+        ("classify([])", "empty"),
+        ("classify([7])", "one: 7"),
+        ("classify([11, 2, 3])", "big head of 3"),
+        ("classify([1, 2, 3])", "many"),
+        (r#"classify({"name": "a", "tags": ["b", "c"]})"#, "a #b"),
+        (r#"classify({"name": "a", "tags": []})"#, "a"),
+        ("classify(101)", "large"),
+        ("classify(5)", "other 5"),
+    ];
+    for t in tests {
+        let input = format!("{} {}", classify, t.0);
+        assert_eq!(test_eval(&input).to_string(), t.1, "{}", t.0);
+    }
+
+    let tests = [
+        ("match (1 + 1) { 2 => 20, _ => 0 }", "20"),
+        ("let x = 1; match (5) { x => x }; x", "1"),
+        ("let x = 5; match (3) { x => x }", "3"),
+        ("let f = fn(x) { match (1) { x => x }; x }; f(5)", "5"),
+        ("let y = 10; match (1) { 2 => 0, y => y }; y", "10"),
+        ("let x = 7; match (1) { x if x > 5 => 0, _ => x }", "7"),
+        ("match ([1, 2]) { [a, b] if a > b => a, [a, b] => b }", "2"),
+        ("let _ = 1; let [_, b] = [1, 2]; b", "2"),
+        ("match (3) { n => fn() { n * 2 } }()", "6"),
+        ("let v = [1, 2]; match v { [a, b] => a + b }", "3"),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+
+    let errors = [
+        (
+            "match (3) { 1 => 1, 2 => 2 }",
+            "non-exhaustive match: no arm matches 3",
+        ),
+        (
+            "match ([1]) { [a] if a > 1 => a }",
+            "non-exhaustive match: no arm matches [1]",
+        ),
+        ("match (1) { x if y => x }", "identifier not found: y"),
+        (
+            "match ([1, 2]) { [a, b] if a > 5 => 0, _ => 1 }; a",
+            "identifier not found: a",
+        ),
+    ];
+    for t in errors {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1, "{}", t.0),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+}
+
 #[test]
 fn test_hash_index_expressions() {
     let tests = [
@@ -348,3 +568,680 @@ fn test_hash_index_expressions() {
         assert_eq!(test_eval(t.0).to_string(), t.1);
     }
 }
+
+#[test]
+fn test_json_builtins() {
+    let tests = [
+        (
+            r#"json_stringify([1, "two", true, json_parse("null")])"#,
+            r#"[1,"two",true,null]"#,
+        ),
+        (
+            r#"json_stringify({"b": [1], "a": {"c": false}})"#,
+            r#"{"b":[1],"a":{"c":false}}"#,
+        ),
+        (r#"json_stringify({"a": 1}, 2)"#, "{\n  \"a\": 1\n}"),
+        (
+            r#"json_stringify(json_parse(json_stringify({"x": [1, 2, {"y": false}]})))"#,
+            r#"{"x":[1,2,{"y":false}]}"#,
+        ),
+        (r#"json_parse("[1, 2, 3]")[1]"#, "2"),
+        (
+            r#"json_parse(json_stringify({"name": "monkey"}))["name"]"#,
+            "monkey",
+        ),
+        (r#"json_parse("null")"#, "null"),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1);
+    }
+}
+
+#[test]
+fn test_json_builtin_errors() {
+    let tests = [
+        (
+            r#"json_stringify(fn(x) { x })"#,
+            "json_stringify: cannot convert function fn(x) {\nx\n} to JSON",
+        ),
+        (
+            r#"json_stringify({1: 2})"#,
+            "json_stringify: hash key 1 is not a string",
+        ),
+        (
+            r#"json_stringify([1], 100000000000000)"#,
+            "indent 100000000000000 for json_stringify must be an integer from 0 to 10",
+        ),
+        (
+            r#"json_stringify([1], -1)"#,
+            "indent -1 for json_stringify must be an integer from 0 to 10",
+        ),
+        (
+            r#"json_parse("1.5")"#,
+            "json_parse: number 1.5 is not an integer",
+        ),
+        (
+            r#"json_parse("[1,")"#,
+            "json_parse: EOF while parsing a value at line 1 column 3",
+        ),
+    ];
+    for t in tests {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match parser::parse(t.0) {
+            Ok(node) => match eval(&node, env) {
+                Err(e) => assert_eq!(e.message, t.1),
+                n => panic!("expected error {} but got {:?}", t.1, n),
+            },
+            Err(e) => panic!("error {:?} on input {}", e, t.0),
+        }
+    }
+}
+
+#[test]
+fn test_array_builtins() {
+    let tests = [
+        ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+        ("map([[1], [], [2, 3]], len)", "[1, 0, 2]"),
+        (
+            "filter(range(10), fn(x) { x / 3 * 3 == x })",
+            "[0, 3, 6, 9]",
+        ),
+        ("reduce([1, 2, 3, 4], fn(acc, x) { acc + x })", "10"),
+        ("reduce([], fn(acc, x) { acc + x }, 0)", "0"),
+        ("reduce([1, 2], fn(acc, x) { push(acc, x) }, [])", "[1, 2]"),
+        ("sort([3, 1, 2])", "[1, 2, 3]"),
+        (r#"sort(["pear", "apple", "fig"])"#, "[apple, fig, pear]"),
+        ("sort([1, 3, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+        // stable
+        (
+            "sort([[2, 1], [1, 2], [2, 3], [1, 4]], fn(a, b) { a[0] - b[0] })",
+            "[[1, 2], [1, 4], [2, 1], [2, 3]]",
+        ),
+        // a comparator that is not a total order still gives some order
+        ("sort([3, 1, 2], fn(a, b) { 1 })", "[2, 1, 3]"),
+        ("reverse([1, 2, 3])", "[3, 2, 1]"),
+        ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+        ("slice([1, 2, 3, 4], -2)", "[3, 4]"),
+        ("slice([1, 2, 3, 4], 3, 1)", "[]"),
+        ("slice([1, 2], 0, 10)", "[1, 2]"),
+        ("concat([1], [], [2, 3])", "[1, 2, 3]"),
+        ("contains([1, [2]], [2])", "true"),
+        (r#"contains([1, 2], "1")"#, "false"),
+        (r#"index_of(["a", "b"], "b")"#, "1"),
+        ("index_of([1, 2], 3)", "-1"),
+        ("zip([1, 2, 3], [true, false])", "[[1, true], [2, false]]"),
+        ("range(3)", "[0, 1, 2]"),
+        ("range(2, 5)", "[2, 3, 4]"),
+        ("range(10, 0, -3)", "[10, 7, 4, 1]"),
+        ("range(5, 2)", "[]"),
+        ("join([1, \"b\", true], \", \")", "1, b, true"),
+        ("join(map(range(3), fn(i) { i }))", "012"),
+        // deep enough to overflow a recursive map written in Monkey
+        ("len(map(range(100000), fn(x) { x + 1 }))", "100000"),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+}
+
+#[test]
+fn test_array_builtin_errors() {
+    let tests = [
+        (
+            "map([1], fn(a, b) { a })",
+            "wrong number of arguments: want=2, got=1",
+        ),
+        ("map(1, len)", "argument to map must be an array, got 1"),
+        (
+            "map([1])",
+            "wrong number of arguments to map: want=2, got=1",
+        ),
+        ("filter([1], 2)", "Int(2) is not a function"),
+        (
+            "reduce([], fn(a, b) { a })",
+            "reduce of an empty array with no initial value",
+        ),
+        (r#"sort([1, "a"])"#, "sort cannot compare 1 and a"),
+        (
+            "sort([1, 2], fn(a, b) { true })",
+            "sort comparator must return an integer, got true",
+        ),
+        (
+            "slice([1], 0, 1, 2)",
+            "wrong number of arguments to slice: want=2 to 3, got=4",
+        ),
+        ("range(0, 10, 0)", "range step must not be 0"),
+        (
+            r#"range("3")"#,
+            "argument to range must be an integer, got 3",
+        ),
+        ("join([1], 2)", "separator for join must be a string, got 2"),
+        (
+            "concat([1], 2)",
+            "argument to concat must be an array, got 2",
+        ),
+    ];
+    for t in tests {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+
+    // limits hit inside a callback stay limit errors
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+    let err = test_eval_limits("map(range(1000), fn(x) { x * x })", limits).unwrap_err();
+    assert_eq!(err.kind, EvalErrorKind::StepLimit);
+    let limits = Limits {
+        max_memory: Some(1000),
+        ..Limits::default()
+    };
+    let err = test_eval_limits("range(1000000000000)", limits).unwrap_err();
+    assert_eq!(err.kind, EvalErrorKind::MemoryLimit);
+}
+
+#[test]
+fn test_hash_builtins() {
+    let tests = [
+        (r#"keys({"a": 1, 2: "b", true: 3})"#, "[a, 2, true]"),
+        (r#"values({"a": 1, "b": [2]})"#, "[1, [2]]"),
+        (r#"entries({"a": 1, 2: false})"#, "[[a, 1], [2, false]]"),
+        (r#"has({"a": 1}, "a")"#, "true"),
+        (r#"has({"a": 1}, 1)"#, "false"),
+        (r#"delete({"a": 1, "b": 2, "c": 3}, "b")"#, "{a: 1, c: 3}"),
+        (r#"delete({"a": 1}, "z")"#, "{a: 1}"),
+        // the hash deleted from is left alone
+        (r#"let h = {"a": 1}; delete(h, "a"); h"#, "{a: 1}"),
+        (
+            r#"merge({"a": 1, "b": 2}, {"b": 3, "c": 4})"#,
+            "{a: 1, b: 3, c: 4}",
+        ),
+        (r#"from_entries([["a", 1], [2, "b"]])"#, "{a: 1, 2: b}"),
+        (
+            r#"from_entries(entries({"x": [1], "y": {}}))"#,
+            "{x: [1], y: {}}",
+        ),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+}
+
+#[test]
+fn test_hash_builtin_errors() {
+    let tests = [
+        (r#"has({}, [1])"#, "unusable as hash key: [1]"),
+        (r#"delete({}, {})"#, "unusable as hash key: {}"),
+        (
+            r#"from_entries([[fn() {}, 1]])"#,
+            "unusable as hash key: fn() {\n\n}",
+        ),
+        (r#"keys([1])"#, "argument to keys must be a hash, got [1]"),
+        (r#"merge({}, 1)"#, "argument to merge must be a hash, got 1"),
+        (
+            r#"from_entries([["a"]])"#,
+            "entry for from_entries must be a [key, value] array, got [a]",
+        ),
+        (
+            r#"has({})"#,
+            "wrong number of arguments to has: want=2, got=1",
+        ),
+    ];
+    for t in tests {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+}
+
+#[test]
+fn test_string_builtins() {
+    let tests = [
+        (r#"len("héllo 🐒")"#, "7"),
+        (r#"split("a,b,,c", ",")"#, "[a, b, , c]"),
+        ("split(\"  one two\n three \")", "[one, two, three]"),
+        (r#"split("añb", "")"#, "[a, ñ, b]"),
+        ("trim(\"  hi\n\")", "hi"),
+        (r#"upper("straße")"#, "STRASSE"),
+        (r#"lower("ÀB")"#, "àb"),
+        (r#"replace("a-b-c", "-", "+")"#, "a+b+c"),
+        (r#"starts_with("monkey", "mon")"#, "true"),
+        (r#"ends_with("monkey", "mon")"#, "false"),
+        (r#"contains("monkey", "ke")"#, "true"),
+        (r#"contains(["monkey"], "ke")"#, "false"),
+        (r#"substr("héllo", 1, 3)"#, "éll"),
+        (r#"substr("héllo", -2)"#, "lo"),
+        (r#"substr("héllo", 3, 10)"#, "lo"),
+        (r#"chars("ü🐒")"#, "[ü, 🐒]"),
+        (r#"repeat("ab", 3)"#, "ababab"),
+        (r#"repeat("ab", 0)"#, ""),
+        (r#"to_int(" -42 ")"#, "-42"),
+        (r#"to_int("4x")"#, "null"),
+        (r#"to_int(7)"#, "7"),
+        (r#"to_string([1, "a"])"#, "[1, a]"),
+        (r#"to_string(12) + "3""#, "123"),
+        (r#"format("{} is {}", "x", 1 + 1)"#, "x is 2"),
+        (r#"format("{{{}}}", [])"#, "{[]}"),
+        (r#""héllo"[1]"#, "é"),
+        (r#""héllo"[5]"#, "null"),
+        (r#"join(reverse(chars("añb")))"#, "bña"),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+}
+
+#[test]
+fn test_string_builtin_errors() {
+    let tests = [
+        (r#"upper(1)"#, "argument to upper must be a string, got 1"),
+        (
+            r#"split("a", 1)"#,
+            "argument to split must be a string, got 1",
+        ),
+        (
+            r#"repeat("a", -1)"#,
+            "count for repeat must not be negative, got -1",
+        ),
+        (
+            r#"format("{} and {}", 1)"#,
+            "format string has 2 placeholders but got 1 values",
+        ),
+        (r#"format("{x}", 1)"#, "unmatched { in format string"),
+        (
+            r#"format()"#,
+            "wrong number of arguments to format: want=1 or more, got=0",
+        ),
+        (
+            r#"replace("a", "b")"#,
+            "wrong number of arguments to replace: want=3, got=2",
+        ),
+    ];
+    for t in tests {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match eval(&parser::parse(t.0).unwrap(), env) {
+            Err(e) => assert_eq!(e.message, t.1),
+            n => panic!("expected error {} but got {:?}", t.1, n),
+        }
+    }
+    let limits = Limits {
+        max_memory: Some(1000),
+        ..Limits::default()
+    };
+    let err = test_eval_limits(r#"repeat("ab", 1000000000000)"#, limits).unwrap_err();
+    assert_eq!(err.kind, EvalErrorKind::MemoryLimit);
+}
+
+#[test]
+fn test_interpolation() {
+    let tests = [
+        (
+            r#"let name = "ann"; let items = [1, 2]; "hello ${name}, you have ${len(items)} items""#,
+            "hello ann, you have 2 items",
+        ),
+        (r#""${ {"a": [1, "}"]}["a"] }""#, "[1, }]"),
+        (r#""${"in" + "ner"}${true}${"x"}""#, "innertruex"),
+        (r#"let f = fn(x) { "<${x}>" }; f(f(1))"#, "<<1>>"),
+        (r#""no ${"$"}{} here""#, "no ${} here"),
+        (
+            r#"let name = "ann"; "\${name} is ${name}""#,
+            "${name} is ann",
+        ),
+        (r#""$x {y}""#, "$x {y}"),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1, "{}", t.0);
+    }
+    let env = Rc::new(RefCell::new(Environment::new()));
+    match eval(&parser::parse(r#""a ${missing} b""#).unwrap(), env) {
+        Err(e) => assert_eq!(e.message, "identifier not found: missing"),
+        n => panic!("expected error but got {:?}", n),
+    }
+}
+
+#[test]
+fn test_hash_insertion_order() {
+    let tests = [
+        (r#"{"b": 1, "a": 2, "c": 3}"#, "{b: 1, a: 2, c: 3}"),
+        (r#"{3: "x", 1: "y", 2: "z"}"#, "{3: x, 1: y, 2: z}"),
+        (r#"{"a": 1, "b": 2, "a": 3}"#, "{a: 3, b: 2}"),
+        (
+            r#"json_stringify(json_parse(json_stringify({"z": 1, "y": 2})))"#,
+            r#"{"z":1,"y":2}"#,
+        ),
+    ];
+    for t in tests {
+        assert_eq!(test_eval(t.0).to_string(), t.1);
+    }
+}
+
+#[test]
+fn test_equality() {
+    let tests = [
+        (r#""a" == "a""#, true),
+        (r#""a" != "b""#, true),
+        ("[1, [2, 3]] == [1, [2, 3]]", true),
+        ("[1, 2] == [2, 1]", false),
+        ("[] != []", false),
+        (r#"{"a": 1, "b": [2]} == {"b": [2], "a": 1}"#, true),
+        (r#"{"a": 1} == {"a": 2}"#, false),
+        ("let f = fn(x) { x }; f == f", true),
+        ("fn(x) { x } == fn(x) { x }", false),
+        ("let f = fn(x) { x }; [f] == [f]", true),
+        ("len == len", true),
+        ("len == first", false),
+    ];
+    for t in tests {
+        test_bool_object(&test_eval(t.0), t.1);
+    }
+}
+
+fn test_eval_limits(input: &str, limits: Limits) -> EvalResult {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let node = parser::parse(input).expect(input);
+    eval_with_limits(&node, env, limits)
+}
+
+#[test]
+fn test_limits() {
+    let tests = [
+        (
+            "let f = fn() { f() }; f()",
+            Limits {
+                max_depth: Some(100),
+                ..Limits::default()
+            },
+            EvalErrorKind::DepthLimit,
+        ),
+        (
+            "let loop = fn(n) { if (n > 0) { loop(n - 1) } }; loop(100000)",
+            Limits {
+                max_steps: Some(1000),
+                ..Limits::default()
+            },
+            EvalErrorKind::StepLimit,
+        ),
+        (
+            "let grow = fn(s) { grow(s + s) }; grow(\"ab\")",
+            Limits {
+                max_memory: Some(1 << 20),
+                ..Limits::default()
+            },
+            EvalErrorKind::MemoryLimit,
+        ),
+        (
+            "let grow = fn(a) { grow(push(a, a)) }; grow([])",
+            Limits {
+                max_memory: Some(1000),
+                ..Limits::default()
+            },
+            EvalErrorKind::MemoryLimit,
+        ),
+        (
+            "let f = fn() { f() }; f()",
+            Limits {
+                timeout: Some(Duration::from_millis(10)),
+                ..Limits::default()
+            },
+            EvalErrorKind::Timeout,
+        ),
+        (
+            "1 + 1",
+            Limits {
+                cancel: Some(Arc::new(AtomicBool::new(true))),
+                ..Limits::default()
+            },
+            EvalErrorKind::Cancelled,
+        ),
+    ];
+
+    for (input, limits, expected) in tests.iter() {
+        match test_eval_limits(input, limits.clone()) {
+            Err(err) => {
+                assert_eq!(&err.kind, expected, "{}", input);
+                assert!(err.is_limit());
+            }
+            Ok(obj) => panic!("expected {:?} for {}, got {}", expected, input, obj),
+        }
+    }
+}
+
+#[test]
+fn test_builtin_memory_limits() {
+    // each result is far larger than its arguments, so it must be refused before it is built
+    let tests = [
+        r#"let a = range(500000); join(a, repeat("x", 500000))"#,
+        r#"replace(repeat("a", 500000), "a", repeat("b", 500000))"#,
+        "let a = range(500000); concat(a, a, a)",
+        r#"let s = repeat("x", 500000); format("{}{}{}", s, s, s)"#,
+        "json_stringify([[[[[[[[[[range(100000)]]]]]]]]]], 10)",
+    ];
+    for input in tests.iter() {
+        let limits = Limits {
+            max_memory: Some(1 << 20),
+            ..Limits::default()
+        };
+        match test_eval_limits(input, limits) {
+            Err(err) => assert_eq!(err.kind, EvalErrorKind::MemoryLimit, "{}", input),
+            Ok(obj) => panic!("expected a memory limit error for {}, got {}", input, obj),
+        }
+    }
+}
+
+#[test]
+fn test_limits_allow_deep_recursion() {
+    let input = "let count = fn(n) { if (n == 0) { 0 } else { 1 + count(n - 1) } }; count(5000)";
+    let limits = Limits {
+        max_depth: Some(10000),
+        ..Limits::default()
+    };
+    let evaluated = test_eval_limits(input, limits).unwrap();
+    test_integer_object(&evaluated, 5000);
+}
+
+#[test]
+fn test_limits_restored_after_eval() {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let input = "let f = fn(n) { if (n > 0) { f(n - 1) } else { n } }; f(50)";
+    let node = parser::parse(input).unwrap();
+    let limits = Limits {
+        max_depth: Some(10),
+        ..Limits::default()
+    };
+    assert!(eval_with_limits(&node, Rc::clone(&env), limits).is_err());
+    // the budget is per evaluation, and the previous unbounded limits are back
+    test_integer_object(&eval(&node, env).unwrap(), 0);
+}
+
+#[test]
+fn test_closures_and_scopes() {
+    let tests = [
+        ("let x = 1; let f = fn(x) { x * 10 }; f(2) + x", 21),
+        (
+            "let f = fn() { let a = 1; if (true) { let a = a + 1; } a }; f()",
+            2,
+        ),
+        (
+            "let adder = fn(a) { fn(b) { fn(c) { a + b + c } } }; adder(1)(2)(3)",
+            6,
+        ),
+        (
+            "let f = fn() { let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } };
+             let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; even(10) };
+             if (f()) { 1 } else { 0 }",
+            1,
+        ),
+        (
+            "let g = 5; let f = fn() { let h = g; let g = 2; h + g }; f()",
+            7,
+        ),
+    ];
+
+    for (input, expected) in tests.iter() {
+        test_integer_object(&test_eval(input), *expected);
+    }
+}
+
+#[test]
+fn test_undefined_reported_when_read() {
+    let ctx = Context::new();
+    let buf = Rc::new(RefCell::new(Vec::new()));
+    ctx.set_output(Output::Buffer(Rc::clone(&buf)));
+    let env = Rc::new(RefCell::new(Environment::with_context(Rc::new(ctx))));
+
+    let node = parser::parse("puts(1); let f = fn() { missing }; f()").unwrap();
+    let err = eval(&node, Rc::clone(&env)).unwrap_err();
+    assert_eq!(err.message, "identifier not found: missing");
+    assert_eq!(String::from_utf8(buf.borrow().clone()).unwrap(), "1\n");
+
+    // a global defined by a later evaluation is found by functions made before it
+    let node = parser::parse("let missing = 2; f()").unwrap();
+    test_integer_object(&eval(&node, env).unwrap(), 2);
+
+    // a local read before its `let` ran is still a runtime error
+    let node = parser::parse("let f = fn() { let g = fn() { x }; g(); let x = 1; }; f()").unwrap();
+    let err = eval(&node, Rc::new(RefCell::new(Environment::new()))).unwrap_err();
+    assert_eq!(err.message, "identifier not found: x");
+}
+
+#[test]
+fn test_caller_environment_collected() {
+    let ctx = Rc::new(Context::new());
+    let env = Rc::new(RefCell::new(Environment::with_context(Rc::clone(&ctx))));
+    let node = parser::parse("let f = fn() { f };").unwrap();
+    eval(&node, Rc::clone(&env)).unwrap();
+    eval(&node, Rc::clone(&env)).unwrap();
+
+    let weak = Rc::downgrade(&env);
+    drop(env);
+    assert!(weak.upgrade().is_some());
+    assert_eq!(ctx.collect_garbage(), 1);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn test_error_spans() {
+    let tests = [
+        ("let a = 1;\nlet b = a + true;", "a + true"),
+        ("let f = fn(x) { x / 0 };\nf(1)", "x / 0"),
+        ("true[0]", "true[0]"),
+        ("fn() { missing }()", "missing"),
+        ("len(1, 2)", "len(1, 2)"),
+        ("let [a, [b]] = [1, 2];", "[b]"),
+        ("let {\"k\": v} = {};", "\"k\""),
+        ("let f = fn(x, [y]) { y };\nf(1, [])", "[y]"),
+        ("[1][\"a\":]", "\"a\""),
+        ("let m = match (1) { 2 => 2 };", "match (1) { 2 => 2 }"),
+    ];
+    for (input, source) in tests.iter() {
+        let node = parser::parse(input).unwrap();
+        let err = eval(&node, Rc::new(RefCell::new(Environment::new()))).unwrap_err();
+        let span = err.span.expect(input);
+        assert_eq!(&&input[span.start..span.end], source, "{}", err);
+    }
+}
+
+fn expand(input: &str, env: &Rc<RefCell<Environment>>) -> Result<Program, EvalError> {
+    let mut program = parser::Parser::new(crate::lexer::Lexer::new(input))
+        .parse_program()
+        .unwrap();
+    define_macros(&mut program, env)?;
+    expand_macros(&mut program, env)?;
+    Ok(program)
+}
+
+#[test]
+fn test_quote_unquote() {
+    let tests = [
+        ("quote(5)", "5"),
+        ("quote(foobar + barfoo)", "(foobar + barfoo)"),
+        ("quote(unquote(4 + 4) + 8)", "(8 + 8)"),
+        ("let x = 8; quote(unquote(x) * 2)", "(8 * 2)"),
+        ("quote(unquote(true == false))", "false"),
+        ("quote(unquote(quote(4 + 4)))", "(4 + 4)"),
+        (
+            "let q = quote(4 + 4); quote(unquote(4 + 4) + unquote(q))",
+            "(8 + (4 + 4))",
+        ),
+        ("fn(n) { quote(unquote(n) + 1) }(2)", "(2 + 1)"),
+        // nested quotes keep their unquotes
+        ("quote(quote(unquote(x)))", "quote(unquote(x))"),
+    ];
+    for (input, expected) in tests.iter() {
+        match &*test_eval(input) {
+            Object::Quote(exp) => assert_eq!(exp.to_string(), *expected, "{}", input),
+            obj => panic!("expected a quote, got {:?}", obj),
+        }
+    }
+    assert_eq!(test_eval("quote(1 + 2)").inspect(), "QUOTE((1 + 2))");
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    for (input, expected) in [
+        ("unquote(1)", "unquote outside of quote"),
+        (
+            "quote(unquote([1]))",
+            "cannot unquote [1]: only integers, booleans, strings and quotes can be",
+        ),
+    ] {
+        let node = parser::parse(input).unwrap();
+        let err = eval(&node, Rc::clone(&env)).unwrap_err();
+        assert_eq!(err.message, expected);
+    }
+}
+
+#[test]
+fn test_macro_expansion() {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let input = "let number = 1;
+let function = fn(x) { x };
+let infix = macro() { quote(1 + 2) };
+let reverse = macro(a, b) { quote(unquote(b) - unquote(a)) };
+infix();
+reverse(2 + 2, 10 - 5);
+fn() { reverse(infix(), 1) };";
+    let program = expand(input, &env).unwrap();
+    assert_eq!(
+        program.to_string(),
+        "let number = 1;let function = (x) x;(1 + 2)((10 - 5) - (2 + 2))() (1 - (1 + 2))"
+    );
+    assert_eq!(
+        env.borrow().get("reverse").unwrap().inspect(),
+        "macro(a, b) {\nquote((unquote(b) - unquote(a)))\n}"
+    );
+
+    let input = r#"let unless = macro(condition, consequence, alternative) {
+    quote(if (!(unquote(condition))) { unquote(consequence) } else { unquote(alternative) });
+};
+unless(10 > 5, "not greater", "greater")"#;
+    let program = expand(input, &env).unwrap();
+    let result = eval(&Node::Program(Box::new(program)), Rc::clone(&env)).unwrap();
+    assert_eq!(result.inspect(), "greater");
+
+    // macros stay defined for later programs in the same environment
+    let program = expand("unless(false, 1, 2)", &env).unwrap();
+    assert_eq!(program.to_string(), "if (!false) 1 else 2");
+
+    for (input, expected) in [
+        (
+            "let m = macro() { 1 }; m()",
+            "macro m returned 1, not a quote",
+        ),
+        (
+            "let m = macro(a) { a }; m()",
+            "wrong number of arguments: want=1, got=0",
+        ),
+    ] {
+        assert_eq!(expand(input, &env).unwrap_err().message, expected);
+    }
+    let node = parser::parse("let m = macro() { quote(1) }; let f = m; f()").unwrap();
+    assert_eq!(
+        eval(&node, Rc::clone(&env)).unwrap_err().message,
+        "macros are expanded before evaluation and cannot be called at runtime"
+    );
+}
@@ -0,0 +1,351 @@
+//! Embedding API: evaluate Monkey source from a host program and move values between Rust and
+//! Monkey.
+//!
+//! ```
+//! use monkey_rs::interpreter::Interpreter;
+//!
+//! let mut interp = Interpreter::new();
+//! interp.set_global("base", 40);
+//! interp.eval_str("let add = fn(x) { base + x };").unwrap();
+//!
+//! let sum: i64 = interp.call_function("add", vec![2.into()]).unwrap().extract().unwrap();
+//! assert_eq!(sum, 42);
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    evaluator::{self, Context, EvalError, EvalResult, Input, Limits, Output},
+    object::{Array, Builtin, Environment, HashKey, MonkeyHash, NativeFunction, Object},
+    optimizer::{self, Passes},
+    parser::{self, node::Node, ParseErrors},
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum InterpreterError {
+    Io(io::Error),
+    Parse(ParseErrors),
+    Eval(EvalError),
+    /// A value could not be converted to the requested Rust type.
+    Convert(String),
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpreterError::Io(err) => write!(f, "{}", err),
+            InterpreterError::Parse(errors) => {
+                write!(f, "parse errors:")?;
+                for err in errors {
+                    write!(f, "\n{}", err)?;
+                }
+                Ok(())
+            }
+            InterpreterError::Eval(err) => write!(f, "{}", err),
+            InterpreterError::Convert(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for InterpreterError {}
+
+impl From<io::Error> for InterpreterError {
+    fn from(err: io::Error) -> Self {
+        InterpreterError::Io(err)
+    }
+}
+
+impl From<EvalError> for InterpreterError {
+    fn from(err: EvalError) -> Self {
+        InterpreterError::Eval(err)
+    }
+}
+
+pub type InterpreterResult<T> = Result<T, InterpreterError>;
+
+/// A Monkey session. Globals defined by one `eval_*` call stay visible to the next.
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>,
+    passes: Option<Passes>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_context(Context::new())
+    }
+
+    pub fn with_context(context: Context) -> Self {
+        let context = Rc::new(context);
+        let env = Rc::new(RefCell::new(Environment::with_context(Rc::clone(&context))));
+        context.track(&env);
+        Interpreter { env, passes: None }
+    }
+
+    /// Gets the global environment of the session.
+    pub fn env(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.env)
+    }
+
+    pub fn context(&self) -> Rc<Context> {
+        self.env.borrow().context()
+    }
+
+    /// Redirects script output such as `puts`.
+    pub fn set_output(&mut self, output: Output) {
+        self.context().set_output(output);
+    }
+
+    pub fn set_input(&mut self, input: Input) {
+        self.context().set_input(input);
+    }
+
+    /// Sets the resource limits applied to each following `eval_*` and `call_function`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.context().set_limits(limits);
+    }
+
+    /// Sets the directories `import` searches, in order, for modules not found relative to the
+    /// importing file, or to the working directory outside of `eval_file`.
+    pub fn set_search_path(&mut self, dirs: Vec<PathBuf>) {
+        self.context().set_search_path(dirs);
+    }
+
+    /// Runs the optimizer `passes` over each following program before evaluating it, `None`
+    /// evaluates programs as parsed.
+    pub fn set_optimizations(&mut self, passes: Option<Passes>) {
+        self.passes = passes;
+    }
+
+    /// Parses and evaluates `input`, returning the value of the last statement. Macros defined
+    /// at its top level are expanded first and stay defined for the following calls.
+    pub fn eval_str(&mut self, input: &str) -> InterpreterResult<Rc<Object>> {
+        let mut node = parser::parse(input).map_err(InterpreterError::Parse)?;
+        if let Node::Program(program) = &mut node {
+            evaluator::define_macros(program, &self.env)?;
+            evaluator::expand_macros(program, &self.env)?;
+            if let Some(passes) = self.passes {
+                optimizer::optimize_with(program, passes);
+            }
+        }
+        Ok(evaluator::eval(&node, Rc::clone(&self.env))?)
+    }
+
+    /// Reads and evaluates a source file. Its imports are looked up relative to its directory.
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> InterpreterResult<Rc<Object>> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path)?;
+        let ctx = self.context();
+        ctx.in_file(path, || self.eval_str(&input))
+    }
+
+    pub fn set_global<V: Into<Object>>(&mut self, name: &str, value: V) {
+        self.env
+            .borrow_mut()
+            .set(name.to_string(), Rc::new(value.into()));
+    }
+
+    /// Registers a host function as the global `name`. `arity` of `None` accepts any number of
+    /// arguments; errors returned by `func` surface as evaluation errors of the script.
+    pub fn register_native<F>(&mut self, name: &str, arity: Option<usize>, func: F)
+    where
+        F: Fn(&[Rc<Object>]) -> EvalResult + 'static,
+    {
+        let native = NativeFunction::new(name, arity, func);
+        self.set_global(name, Object::Native(Rc::new(native)));
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Rc<Object>> {
+        self.env.borrow().get(name)
+    }
+
+    /// Calls the global or builtin function `name` with `args`.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<Object>,
+    ) -> InterpreterResult<Rc<Object>> {
+        let func = match self.get_global(name) {
+            Some(func) => func,
+            None => Builtin::lookup(name).map(Rc::new).ok_or_else(|| {
+                InterpreterError::Eval(EvalError::new(format!("identifier not found: {}", name)))
+            })?,
+        };
+        let args: Vec<Rc<Object>> = args.into_iter().map(Rc::new).collect();
+        let ctx = self.context();
+        ctx.begin();
+        let result = evaluator::apply_function(&func, &args, &ctx);
+        ctx.finish();
+        Ok(result?)
+    }
+}
+
+// Functions stored in the globals keep the global environment alive through their closures,
+// so the session's environments are only freed by the garbage collector.
+impl Drop for Interpreter {
+    fn drop(&mut self) {
+        let ctx = self.context();
+        ctx.forget_modules();
+        self.env = Rc::new(RefCell::new(Environment::with_context(Rc::clone(&ctx))));
+        ctx.collect_garbage();
+    }
+}
+
+/// Conversion from a Monkey value into a Rust value.
+pub trait FromObject: Sized {
+    fn from_object(obj: &Object) -> Result<Self, String>;
+}
+
+impl Object {
+    /// Converts the object into a Rust value, e.g. `obj.extract::<Vec<i64>>()`.
+    pub fn extract<T: FromObject>(&self) -> InterpreterResult<T> {
+        T::from_object(self).map_err(InterpreterError::Convert)
+    }
+}
+
+fn type_error(expected: &str, obj: &Object) -> String {
+    format!("expected {}, got {}", expected, obj.inspect())
+}
+
+impl FromObject for i64 {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Int(i) => Ok(*i),
+            _ => Err(type_error("integer", obj)),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Bool(b) => Ok(*b),
+            _ => Err(type_error("boolean", obj)),
+        }
+    }
+}
+
+impl FromObject for String {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::String(s) => Ok(s.clone()),
+            _ => Err(type_error("string", obj)),
+        }
+    }
+}
+
+impl FromObject for () {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Null => Ok(()),
+            _ => Err(type_error("null", obj)),
+        }
+    }
+}
+
+impl FromObject for Rc<Object> {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        Ok(Rc::new(obj.clone()))
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Null => Ok(None),
+            _ => T::from_object(obj).map(Some),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Array(a) => a.elements.iter().map(|e| T::from_object(e)).collect(),
+            _ => Err(type_error("array", obj)),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(obj: &Object) -> Result<Self, String> {
+        match obj {
+            Object::Hash(h) => h
+                .pairs
+                .iter()
+                .map(|(k, v)| match k {
+                    HashKey::String(k) => Ok((k.clone(), T::from_object(v)?)),
+                    _ => Err(format!("expected string hash key, got {}", k)),
+                })
+                .collect(),
+            _ => Err(type_error("hash", obj)),
+        }
+    }
+}
+
+impl From<i64> for Object {
+    fn from(i: i64) -> Self {
+        Object::Int(i)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(b: bool) -> Self {
+        Object::Bool(b)
+    }
+}
+
+impl From<String> for Object {
+    fn from(s: String) -> Self {
+        Object::String(s)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(s: &str) -> Self {
+        Object::String(s.to_string())
+    }
+}
+
+impl From<()> for Object {
+    fn from(_: ()) -> Self {
+        Object::Null
+    }
+}
+
+impl<T: Into<Object>> From<Option<T>> for Object {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Object::Null, Into::into)
+    }
+}
+
+impl<T: Into<Object>> From<Vec<T>> for Object {
+    fn from(values: Vec<T>) -> Self {
+        let elements = values.into_iter().map(|v| Rc::new(v.into())).collect();
+        Object::Array(Rc::new(Array { elements }))
+    }
+}
+
+impl<T: Into<Object>> From<HashMap<String, T>> for Object {
+    fn from(values: HashMap<String, T>) -> Self {
+        let pairs = values
+            .into_iter()
+            .map(|(k, v)| (HashKey::String(k), Rc::new(v.into())))
+            .collect();
+        Object::Hash(Rc::new(MonkeyHash { pairs }))
+    }
+}
@@ -0,0 +1,465 @@
+use std::{collections::HashMap, io::Write, rc::Weak};
+
+use crate::evaluator::EvalErrorKind;
+
+use super::*;
+
+#[test]
+fn test_eval_str_keeps_globals() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("let a = 5;").unwrap();
+    interp.eval_str("let b = a * 2;").unwrap();
+
+    let obj = interp.eval_str("a + b").unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 15);
+}
+
+#[test]
+fn test_forward_references_across_evals() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("let f = fn() { g() };").unwrap();
+    interp.eval_str("let g = fn() { 1 };").unwrap();
+    assert_eq!(interp.eval_str("f()").unwrap().extract::<i64>().unwrap(), 1);
+
+    // a handler may use a global the host only defines afterwards
+    interp
+        .eval_str("let handler = fn(x) { scale * x };")
+        .unwrap();
+    interp.set_global("scale", 3);
+    let obj = interp
+        .call_function("handler", vec![Object::Int(2)])
+        .unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 6);
+}
+
+#[test]
+fn test_eval_str_expands_macros() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("let twice = macro(e) { quote(unquote(e) + unquote(e)) };")
+        .unwrap();
+    interp.set_optimizations(Some(Passes::default()));
+    let obj = interp.eval_str("let n = 4; twice(n * 2)").unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 16);
+}
+
+#[test]
+fn test_eval_errors() {
+    let mut interp = Interpreter::new();
+    match interp.eval_str("let = 1;") {
+        Err(InterpreterError::Parse(errors)) => assert!(!errors.is_empty()),
+        r => panic!("expected parse error but got {:?}", r),
+    }
+    match interp.eval_str("foobar") {
+        Err(InterpreterError::Eval(e)) => assert_eq!(e.message, "identifier not found: foobar"),
+        r => panic!("expected eval error but got {:?}", r),
+    }
+    match interp.eval_file("does/not/exist.monkey") {
+        Err(InterpreterError::Io(_)) => {}
+        r => panic!("expected io error but got {:?}", r),
+    }
+}
+
+#[test]
+fn test_eval_file() {
+    let path = std::env::temp_dir().join("monkey_rs_eval_file.monkey");
+    let mut file = fs::File::create(&path).unwrap();
+    writeln!(file, "let double = fn(x) {{ x * 2 }};").unwrap();
+    writeln!(file, "double(21)").unwrap();
+
+    let mut interp = Interpreter::new();
+    let obj = interp.eval_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(obj.extract::<i64>().unwrap(), 42);
+    assert!(interp.get_global("double").is_some());
+}
+
+#[test]
+fn test_globals() {
+    let mut interp = Interpreter::new();
+    interp.set_global("name", "monkey");
+    interp.set_global("nums", vec![1, 2, 3]);
+    interp.set_global("flag", Some(true));
+    interp.set_global("nothing", None::<i64>);
+
+    let obj = interp
+        .eval_str(r#"[name + "!", len(nums), flag, nothing]"#)
+        .unwrap();
+    assert_eq!(obj.to_string(), "[monkey!, 3, true, null]");
+
+    interp.eval_str(r#"let point = {"x": 1, "y": 2};"#).unwrap();
+    let point: HashMap<String, i64> = interp.get_global("point").unwrap().extract().unwrap();
+    assert_eq!(point["x"], 1);
+    assert_eq!(point["y"], 2);
+    assert!(interp.get_global("missing").is_none());
+}
+
+#[test]
+fn test_call_function() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("let join = fn(a, b) { a + b }; let n = 1;")
+        .unwrap();
+
+    let obj = interp
+        .call_function("join", vec!["foo".into(), "bar".into()])
+        .unwrap();
+    assert_eq!(obj.extract::<String>().unwrap(), "foobar");
+
+    let obj = interp
+        .call_function("len", vec![vec![1, 2].into()])
+        .unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 2);
+
+    match interp.call_function("missing", vec![]) {
+        Err(InterpreterError::Eval(e)) => assert_eq!(e.message, "identifier not found: missing"),
+        r => panic!("expected eval error but got {:?}", r),
+    }
+    match interp.call_function("n", vec![]) {
+        Err(InterpreterError::Eval(e)) => assert_eq!(e.message, "Int(1) is not a function"),
+        r => panic!("expected eval error but got {:?}", r),
+    }
+    match interp.call_function("join", vec![1.into()]) {
+        Err(InterpreterError::Eval(e)) => {
+            assert_eq!(e.message, "wrong number of arguments: want=2, got=1")
+        }
+        r => panic!("expected eval error but got {:?}", r),
+    }
+}
+
+#[test]
+fn test_extract() {
+    let mut interp = Interpreter::new();
+    let obj = interp.eval_str("[[1, 2], [3]]").unwrap();
+    assert_eq!(
+        obj.extract::<Vec<Vec<i64>>>().unwrap(),
+        vec![vec![1, 2], vec![3]]
+    );
+
+    let obj = interp.eval_str("[1, true]").unwrap();
+    match obj.extract::<Vec<i64>>() {
+        Err(InterpreterError::Convert(msg)) => assert_eq!(msg, "expected integer, got true"),
+        r => panic!("expected conversion error but got {:?}", r),
+    }
+}
+
+#[test]
+fn test_native_functions() {
+    let mut interp = Interpreter::new();
+    interp.register_native("add", Some(2), |args| {
+        let a: i64 = args[0]
+            .extract()
+            .map_err(|e| EvalError::new(e.to_string()))?;
+        let b: i64 = args[1]
+            .extract()
+            .map_err(|e| EvalError::new(e.to_string()))?;
+        Ok(Rc::new(Object::Int(a + b)))
+    });
+    interp.register_native("count", None, |args| {
+        Ok(Rc::new(Object::Int(args.len() as i64)))
+    });
+
+    let obj = interp
+        .eval_str("add(count(), add(1, count(1, 2)))")
+        .unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 3);
+
+    let obj = interp.eval_str("let f = add; f == add").unwrap();
+    assert!(obj.extract::<bool>().unwrap());
+    assert_eq!(interp.eval_str("add").unwrap().to_string(), "add");
+
+    let tests = [
+        ("add(1)", "wrong number of arguments to add: want=2, got=1"),
+        (r#"add(1, "2")"#, "expected integer, got 2"),
+    ];
+    for t in tests {
+        match interp.eval_str(t.0) {
+            Err(InterpreterError::Eval(e)) => assert_eq!(e.message, t.1),
+            r => panic!("expected eval error {} but got {:?}", t.1, r),
+        }
+    }
+}
+
+#[test]
+fn test_native_function_host_state() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut interp = Interpreter::new();
+
+    let sink = Rc::clone(&log);
+    interp.register_native("log", Some(1), move |args| {
+        sink.borrow_mut().push(args[0].to_string());
+        Ok(Rc::new(Object::Null))
+    });
+
+    interp
+        .eval_str(r#"let greet = fn(name) { log("hello " + name) }; greet("monkey");"#)
+        .unwrap();
+    interp.call_function("log", vec![1.into()]).unwrap();
+
+    assert_eq!(*log.borrow(), vec!["hello monkey", "1"]);
+}
+
+#[test]
+fn test_output_sinks() {
+    let mut interp = Interpreter::new();
+    let buf = Rc::new(RefCell::new(Vec::new()));
+    interp.set_output(Output::Buffer(Rc::clone(&buf)));
+    interp.eval_str(r#"puts("hello", [1, 2]); puts()"#).unwrap();
+    assert_eq!(
+        String::from_utf8(buf.borrow().clone()).unwrap(),
+        "hello\n[1, 2]\n"
+    );
+
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&lines);
+    interp.set_output(Output::Callback(Box::new(move |line| {
+        sink.borrow_mut().push(line.to_string())
+    })));
+    interp.eval_str(r#"puts({"a": 1}); puts(true)"#).unwrap();
+    assert_eq!(*lines.borrow(), vec!["{a: 1}", "true"]);
+}
+
+#[test]
+fn test_input_sources() {
+    let ctx = Context::new();
+    ctx.set_input(Input::Reader(Box::new("one\r\ntwo\nthree".as_bytes())));
+    assert_eq!(ctx.read_line().unwrap().as_deref(), Some("one"));
+    assert_eq!(ctx.read_line().unwrap().as_deref(), Some("two"));
+    assert_eq!(ctx.read_line().unwrap().as_deref(), Some("three"));
+    assert_eq!(ctx.read_line().unwrap(), None);
+
+    let mut lines = vec!["b".to_string(), "a".to_string()];
+    ctx.set_input(Input::Callback(Box::new(move || lines.pop())));
+    assert_eq!(ctx.read_line().unwrap().as_deref(), Some("a"));
+    assert_eq!(ctx.read_line().unwrap().as_deref(), Some("b"));
+    assert_eq!(ctx.read_line().unwrap(), None);
+}
+
+#[test]
+fn test_limits() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("let f = fn() { f() };").unwrap();
+    interp.set_limits(Limits {
+        max_depth: Some(50),
+        ..Limits::default()
+    });
+    match interp.call_function("f", vec![]) {
+        Err(InterpreterError::Eval(err)) => assert_eq!(err.kind, EvalErrorKind::DepthLimit),
+        r => panic!("expected depth limit error but got {:?}", r),
+    }
+
+    // the session stays usable after a limit is hit
+    assert_eq!(
+        interp.eval_str("1 + 1").unwrap().extract::<i64>().unwrap(),
+        2
+    );
+}
+
+fn closure_env(obj: &Object) -> Weak<RefCell<Environment>> {
+    match obj {
+        Object::Function(f) => Rc::downgrade(&f.env),
+        _ => panic!("expected function but got {:?}", obj),
+    }
+}
+
+#[test]
+fn test_session_memory_reclaimed_on_drop() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str(
+            "let fact = fn(n) { if (n == 0) { 1 } else { n * fact(n - 1) } };
+             let counter = fn(n) { let next = fn() { counter(n + 1) }; next };
+             let next = counter(0);
+             fact(10);",
+        )
+        .unwrap();
+    let global = Rc::downgrade(&interp.env());
+    let inner = closure_env(&interp.get_global("next").unwrap());
+    assert!(global.upgrade().is_some());
+
+    drop(interp);
+    assert!(global.upgrade().is_none());
+    assert!(inner.upgrade().is_none());
+}
+
+#[test]
+fn test_unreachable_closures_collected() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("let make = fn() { let loop = fn() { loop() }; loop }; let f = make();")
+        .unwrap();
+    let inner = closure_env(&interp.get_global("f").unwrap());
+
+    interp.eval_str("let f = 0;").unwrap();
+    assert!(inner.upgrade().is_some());
+    assert_eq!(interp.context().collect_garbage(), 1);
+    assert!(inner.upgrade().is_none());
+
+    // collections also run on their own as environments pile up
+    interp
+        .eval_str(
+            "let leak = fn(n) { let g = fn() { g }; if (n > 0) { leak(n - 1) } };
+             leak(3000);",
+        )
+        .unwrap();
+    assert_eq!(interp.context().collect_garbage(), 0);
+}
+
+#[test]
+fn test_call_function_collects_garbage() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str(
+            "let make = fn() { let g = fn() { g }; g };
+             let fail = fn() { let g = fn() { g }; len(1) };",
+        )
+        .unwrap();
+    let first = closure_env(&interp.call_function("make", vec![]).unwrap());
+    for _ in 0..3000 {
+        interp.call_function("make", vec![]).unwrap();
+    }
+    assert!(first.upgrade().is_none());
+
+    // calls that fail are collected after too
+    interp.context().collect_garbage();
+    for _ in 0..3000 {
+        assert!(interp.call_function("fail", vec![]).is_err());
+    }
+    assert!(interp.context().collect_garbage() < 1024);
+}
+
+#[test]
+fn test_closures_held_by_host_survive_drop() {
+    let mut interp = Interpreter::new();
+    let add = interp
+        .eval_str("let base = 40; let add = fn(x) { base + x }; add")
+        .unwrap();
+    let ctx = interp.context();
+    drop(interp);
+
+    let sum = evaluator::apply_function(&add, &vec![Rc::new(Object::Int(2))], &ctx).unwrap();
+    assert_eq!(sum.extract::<i64>().unwrap(), 42);
+}
+
+#[test]
+fn test_optimizations() {
+    let mut interp = Interpreter::new();
+    interp.set_optimizations(Some(Passes::default()));
+    interp
+        .eval_str("let limit = 2 * 10; let f = fn(n) { let step = 5; n + step };")
+        .unwrap();
+    // a later program can still rebind globals read by earlier functions
+    interp
+        .eval_str("let g = fn() { limit }; let limit = 1;")
+        .unwrap();
+    assert_eq!(
+        interp
+            .call_function("g", vec![])
+            .unwrap()
+            .extract::<i64>()
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        interp
+            .call_function("f", vec![1.into()])
+            .unwrap()
+            .extract::<i64>()
+            .unwrap(),
+        6
+    );
+}
+
+/// Writes `files` to a fresh directory named after `name` in the temporary directory.
+fn module_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    for (path, source) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_import() {
+    let dir = module_dir(
+        "monkey_rs_import",
+        &[
+            (
+                "main.monkey",
+                r#"let math = import "lib/math.monkey"; let again = import "lib/math.monkey";
+                math["square"](math["base"]) + again["base"]"#,
+            ),
+            (
+                "lib/math.monkey",
+                r#"let util = import "util.monkey"; puts("loading math");
+                export let base = util["three"]; let hidden = 1;
+                export let square = fn(x) { x * x };"#,
+            ),
+            ("lib/util.monkey", "export let three = 3;"),
+        ],
+    );
+    let buf = Rc::new(RefCell::new(Vec::new()));
+    let mut interp = Interpreter::new();
+    interp.set_output(Output::Buffer(Rc::clone(&buf)));
+    let obj = interp.eval_file(dir.join("main.monkey")).unwrap();
+    assert_eq!(obj.extract::<i64>().unwrap(), 12);
+    // evaluated once, for both imports
+    assert_eq!(
+        String::from_utf8(buf.borrow().clone()).unwrap(),
+        "loading math\n"
+    );
+    let hidden = interp.eval_str(r#"math["hidden"]"#).unwrap();
+    assert_eq!(*hidden, Object::Null);
+
+    match interp.eval_str(r#"import "nope.monkey""#) {
+        Err(InterpreterError::Eval(e)) => assert_eq!(e.message, "module not found: nope.monkey"),
+        r => panic!("expected eval error but got {:?}", r),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_import_cycle() {
+    let dir = module_dir(
+        "monkey_rs_import_cycle",
+        &[
+            ("a.monkey", r#"import "b.monkey""#),
+            ("b.monkey", r#"export let b = import "a.monkey";"#),
+        ],
+    );
+    let mut interp = Interpreter::new();
+    let err = match interp.eval_file(dir.join("a.monkey")) {
+        Err(InterpreterError::Eval(e)) => e,
+        r => panic!("expected eval error but got {:?}", r),
+    };
+    let a = dir.join("a.monkey").canonicalize().unwrap();
+    let b = dir.join("b.monkey").canonicalize().unwrap();
+    assert_eq!(
+        err.message,
+        format!(
+            "in module {b}: import cycle: {a} -> {b} -> {a}",
+            a = a.display(),
+            b = b.display()
+        )
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_path() {
+    let dir = module_dir(
+        "monkey_rs_search_path",
+        &[("std/greet.monkey", r#"export let hello = "hi";"#)],
+    );
+    let mut interp = Interpreter::new();
+    interp.set_search_path(vec![dir.join("std")]);
+    let obj = interp
+        .eval_str(r#"(import "greet.monkey")["hello"]"#)
+        .unwrap();
+    assert_eq!(obj.extract::<String>().unwrap(), "hi");
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -9,6 +9,12 @@ pub enum Keyword {
     If,
     Else,
     Return,
+    Quote,
+    Unquote,
+    Macro,
+    Import,
+    Export,
+    Match,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -29,6 +35,12 @@ impl Keyword {
             Self::If => "if",
             Self::Else => "else",
             Self::Return => "return",
+            Self::Quote => "quote",
+            Self::Unquote => "unquote",
+            Self::Macro => "macro",
+            Self::Import => "import",
+            Self::Export => "export",
+            Self::Match => "match",
         }
     }
 }
@@ -44,6 +56,12 @@ impl FromStr for Keyword {
             "true" => Ok(Self::True),
             "false" => Ok(Self::False),
             "return" => Ok(Self::Return),
+            "quote" => Ok(Self::Quote),
+            "unquote" => Ok(Self::Unquote),
+            "macro" => Ok(Self::Macro),
+            "import" => Ok(Self::Import),
+            "export" => Ok(Self::Export),
+            "match" => Ok(Self::Match),
             _ => Err(KeywordError),
         }
     }
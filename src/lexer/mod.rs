@@ -1,4 +1,6 @@
-use token::{Span, Token, TokenKind};
+use std::mem;
+
+use token::{Span, TemplatePart, Token, TokenKind};
 
 use self::{punctuator::Punctuator, token::Numeric};
 
@@ -26,29 +28,23 @@ impl<'a> Lexer<'a> {
             ch: 0 as char,
         };
         l.read_char();
-        return l;
+        l
     }
+    /// Advances to the next character. Positions are byte offsets, so the input can be sliced
+    /// at them.
     pub fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0 as char;
-        } else {
-            if let Some(ch) = self.input.chars().nth(self.read_position) {
-                self.ch = ch;
-            }
-        }
+        self.ch = self.char_at(self.read_position);
         self.position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.ch.len_utf8();
     }
     pub fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            0 as char
-        } else {
-            if let Some(ch) = self.input.chars().nth(self.read_position) {
-                ch
-            } else {
-                panic!("out of range");
-            }
-        }
+        self.char_at(self.read_position)
+    }
+    fn char_at(&self, offset: usize) -> char {
+        self.input
+            .get(offset..)
+            .and_then(|rest| rest.chars().next())
+            .unwrap_or('\u{0}')
     }
     fn skip_whitespace(&mut self) {
         while self.ch.is_ascii_whitespace() {
@@ -57,11 +53,15 @@ impl<'a> Lexer<'a> {
     }
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        let start = self.position;
         let t = match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     Punctuator::Eq.into()
+                } else if self.peek_char() == '>' {
+                    self.read_char();
+                    Punctuator::Arrow.into()
                 } else {
                     Punctuator::Assign.into()
                 }
@@ -88,13 +88,24 @@ impl<'a> Lexer<'a> {
             '}' => Punctuator::CloseBlock.into(),
             '[' => Punctuator::OpenBracket.into(),
             ':' => Punctuator::Colon.into(),
+            '?' => Punctuator::Question.into(),
             ']' => Punctuator::CloseBracket.into(),
-            '\u{0}' => TokenKind::EOF,
+            '.' if self.peek_char() == '.' && self.char_at(self.read_position + 1) == '.' => {
+                self.read_char();
+                self.read_char();
+                Punctuator::Spread.into()
+            }
+            '\u{0}' => {
+                return Token {
+                    span: Span { start, end: start },
+                    kind: TokenKind::EOF,
+                }
+            }
             '"' => {
-                let (start, end, str) = self.read_string();
+                let (start, end, kind) = self.read_string();
                 return Token {
                     span: Span { start, end },
-                    kind: TokenKind::string_literal(str),
+                    kind,
                 };
             }
             _ => {
@@ -108,7 +119,8 @@ impl<'a> Lexer<'a> {
                     let (start, end, num) = self.read_number();
                     return Token {
                         span: Span { start, end },
-                        kind: Numeric::from(num).into(),
+                        // literals that don't fit in an i64 are rejected by the parser
+                        kind: num.map_or(TokenKind::ILLEGAL, |n| Numeric::from(n).into()),
                     };
                 } else {
                     TokenKind::ILLEGAL
@@ -116,13 +128,13 @@ impl<'a> Lexer<'a> {
             }
         };
         self.read_char();
-        return Token {
+        Token {
             span: Span {
-                start: self.position - 1,
-                end: self.read_position,
+                start,
+                end: self.position,
             },
             kind: t,
-        };
+        }
     }
     fn read_identifier(&mut self) -> (usize, usize, String) {
         let pos = self.position;
@@ -131,29 +143,100 @@ impl<'a> Lexer<'a> {
         }
 
         let x = self.input[pos..self.position].to_string();
-        return (pos, self.position, x);
+        (pos, self.position, x)
     }
-    fn read_number(&mut self) -> (usize, usize, i64) {
+    fn read_number(&mut self) -> (usize, usize, Option<i64>) {
         let pos = self.position;
         while is_digit(self.ch) {
             self.read_char();
         }
-        let x = self.input[pos..self.position].parse().unwrap();
-        return (pos, self.position, x);
+        let x = self.input[pos..self.position].parse().ok();
+        (pos, self.position, x)
     }
-    fn read_string(&mut self) -> (usize, usize, String) {
-        let pos = self.position + 1;
+    /// Reads a string literal, split into text and the source of its `${...}` interpolations
+    /// if it has any. `\${` stands for a literal `${`.
+    fn read_string(&mut self) -> (usize, usize, TokenKind) {
+        let start = self.position;
+        let mut parts = Vec::new();
+        let mut text = String::new();
         loop {
             self.read_char();
-            if self.ch == '"' || self.ch == '\u{0}' {
-                break;
+            match self.ch {
+                '"' | '\u{0}' => break,
+                '\\' if self.input[self.read_position..].starts_with("${") => {
+                    text.push_str("${");
+                    self.read_char();
+                    self.read_char();
+                }
+                '$' if self.peek_char() == '{' => {
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(mem::take(&mut text).into()));
+                    }
+                    let interpolation_start = self.position;
+                    self.read_char();
+                    let expression_start = self.read_position;
+                    if !self.skip_interpolation() {
+                        // point at the `${` left open rather than at the whole rest of the input
+                        let end = interpolation_start + 2;
+                        return (
+                            interpolation_start,
+                            end,
+                            TokenKind::UnterminatedInterpolation,
+                        );
+                    }
+                    let span = Span::new(expression_start, self.position);
+                    parts.push(TemplatePart::Expression(span));
+                }
+                ch => text.push(ch),
             }
         }
-        let x = self.input[pos..self.position].to_string();
+        let kind = if parts.is_empty() {
+            TokenKind::string_literal(text)
+        } else {
+            if !text.is_empty() {
+                parts.push(TemplatePart::Text(text.into()));
+            }
+            TokenKind::TemplateString(parts)
+        };
         if self.ch == '"' {
             self.read_char();
         }
-        return (pos, self.position, x);
+        (start, self.position, kind)
+    }
+    /// Skips from the `{` of an interpolation to its closing `}`, past any nested braces and
+    /// strings. Returns false when the input ends first.
+    fn skip_interpolation(&mut self) -> bool {
+        let mut depth = 0;
+        loop {
+            self.read_char();
+            match self.ch {
+                '\u{0}' => return false,
+                '{' => depth += 1,
+                '}' if depth == 0 => return true,
+                '}' => depth -= 1,
+                '"' => loop {
+                    self.read_char();
+                    match self.ch {
+                        '"' => break,
+                        '\u{0}' => return false,
+                        _ => {}
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+    /// A lexer over the `span` of the input, e.g. an interpolation, whose tokens keep their
+    /// offsets into the whole input.
+    pub fn slice(&self, span: Span) -> Lexer<'a> {
+        let mut l = Lexer {
+            input: &self.input[..span.end],
+            position: span.start,
+            read_position: span.start,
+            ch: 0 as char,
+        };
+        l.read_char();
+        l
     }
 }
 
@@ -161,5 +244,5 @@ fn is_letter(c: char) -> bool {
     c.is_ascii_alphabetic() || c == '_'
 }
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
@@ -24,8 +24,11 @@ pub enum Punctuator {
     OpenParen,    // (
     CloseParen,   // )
     Colon,        // :
+    Question,     // ?
     OpenBracket,  // [
     CloseBracket, // ]
+    Spread,       // ...
+    Arrow,        // =>
 
     /// `&&`
     BoolAnd,
@@ -79,8 +82,8 @@ impl fmt::Display for Punctuator {
                 Self::LessThanOrEq => "<=",
                 Self::Mul => "*",
                 Self::Not => "!",
-                Self::OpenBlock => "{{",
-                Self::CloseBlock => "}}",
+                Self::OpenBlock => "{",
+                Self::CloseBlock => "}",
                 Self::OpenParen => "(",
                 Self::CloseParen => ")",
                 Self::Semicolon => ";",
@@ -88,8 +91,11 @@ impl fmt::Display for Punctuator {
                 Self::Comma => ",",
                 Self::NotEq => "!=",
                 Self::Colon => ":",
+                Self::Question => "?",
                 Self::OpenBracket => "[",
                 Self::CloseBracket => "]",
+                Self::Spread => "...",
+                Self::Arrow => "=>",
                 Self::BoolAnd => "&&",
                 Self::BoolOr => "||",
             }
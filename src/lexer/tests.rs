@@ -1,4 +1,9 @@
-use super::{keyword::Keyword, punctuator::Punctuator, token::TokenKind, Lexer};
+use super::{
+    keyword::Keyword,
+    punctuator::Punctuator,
+    token::{Span, TemplatePart, TokenKind},
+    Lexer,
+};
 
 fn expect_tokens(lexer: &mut Lexer, expected: &[TokenKind]) {
     for expect in expected.iter() {
@@ -137,3 +142,136 @@ if (5 < 10) {
     ];
     expect_tokens(&mut lexer, &expected);
 }
+
+#[test]
+fn check_number_overflow() {
+    let mut lexer = Lexer::new("9223372036854775807 9223372036854775808");
+
+    let expected = [
+        TokenKind::numeric_literal(9223372036854775807),
+        TokenKind::ILLEGAL,
+    ];
+
+    expect_tokens(&mut lexer, &expected);
+}
+
+#[test]
+fn check_spans() {
+    let s = "let x = a != \"hi\";";
+    let mut lexer = Lexer::new(s);
+
+    let mut spans = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        spans.push(&s[token.span.start..token.span.end]);
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+    assert_eq!(spans, ["let", "x", "=", "a", "!=", "\"hi\"", ";", ""]);
+    assert_eq!(lexer.next_token().span, Span::new(s.len(), s.len()));
+}
+
+#[test]
+fn check_unicode() {
+    let s = "let s = \"héllo 🐒\"; s";
+    let mut lexer = Lexer::new(s);
+
+    let mut spans = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+        spans.push((token.kind, &s[token.span.start..token.span.end]));
+    }
+    assert_eq!(
+        spans[3],
+        (TokenKind::string_literal("héllo 🐒"), "\"héllo 🐒\"")
+    );
+    assert_eq!(spans[5].1, "s");
+}
+
+#[test]
+fn check_template_string() {
+    let s = r#""a ${ {"b": "}"}["b"] }${c}""#;
+    let mut lexer = Lexer::new(s);
+
+    let expected = [TokenKind::TemplateString(vec![
+        TemplatePart::Text("a ".into()),
+        TemplatePart::Expression(Span::new(5, 22)),
+        TemplatePart::Expression(Span::new(25, 26)),
+    ])];
+
+    expect_tokens(&mut lexer, &expected);
+
+    // `\${` is a literal `${`, other backslashes are kept as they are
+    let mut lexer = Lexer::new(r#""\${a} \n ${b}" "\${c}""#);
+    let expected = [
+        TokenKind::TemplateString(vec![
+            TemplatePart::Text("${a} \\n ".into()),
+            TemplatePart::Expression(Span::new(12, 13)),
+        ]),
+        TokenKind::string_literal("${c}"),
+    ];
+    expect_tokens(&mut lexer, &expected);
+
+    let s = "x + \"a ${b\"";
+    let mut lexer = Lexer::new(s);
+    lexer.next_token();
+    lexer.next_token();
+    let token = lexer.next_token();
+    assert_eq!(*token.kind(), TokenKind::UnterminatedInterpolation);
+    assert_eq!(&s[token.span.start..token.span.end], "${");
+    assert_eq!(*lexer.next_token().kind(), TokenKind::EOF);
+}
+
+#[test]
+fn check_spread() {
+    let mut lexer = Lexer::new("[...a] ..");
+
+    let expected = [
+        TokenKind::punctuator(Punctuator::OpenBracket),
+        TokenKind::punctuator(Punctuator::Spread),
+        TokenKind::identifier("a"),
+        TokenKind::punctuator(Punctuator::CloseBracket),
+        TokenKind::ILLEGAL,
+        TokenKind::ILLEGAL,
+    ];
+
+    expect_tokens(&mut lexer, &expected);
+}
+
+#[test]
+fn check_match() {
+    let mut lexer = Lexer::new("match (x) { _ => 1 }");
+
+    let expected = [
+        TokenKind::keyword(Keyword::Match),
+        TokenKind::punctuator(Punctuator::OpenParen),
+        TokenKind::identifier("x"),
+        TokenKind::punctuator(Punctuator::CloseParen),
+        TokenKind::punctuator(Punctuator::OpenBlock),
+        TokenKind::identifier("_"),
+        TokenKind::punctuator(Punctuator::Arrow),
+        TokenKind::numeric_literal(1),
+        TokenKind::punctuator(Punctuator::CloseBlock),
+    ];
+
+    expect_tokens(&mut lexer, &expected);
+}
+
+#[test]
+fn check_ternary() {
+    let mut lexer = Lexer::new("a ? b : c");
+
+    let expected = [
+        TokenKind::identifier("a"),
+        TokenKind::punctuator(Punctuator::Question),
+        TokenKind::identifier("b"),
+        TokenKind::punctuator(Punctuator::Colon),
+        TokenKind::identifier("c"),
+    ];
+
+    expect_tokens(&mut lexer, &expected);
+}
@@ -1,6 +1,8 @@
 use super::{keyword::Keyword, punctuator::Punctuator};
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
@@ -9,11 +11,8 @@ pub struct Token {
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "start: {}, end: {}, kind: {}",
-            self.span.start, self.span.end, self.kind
-        )
+        // the span is reported separately, so messages only name what the token is
+        write!(f, "{}", self.kind)
     }
 }
 impl Token {
@@ -36,12 +35,26 @@ impl Token {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// A range of byte offsets into the source, `end` exclusive.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
 }
 
+impl Span {
+    #[inline]
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    #[inline]
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Numeric {
     // An integer
@@ -55,6 +68,14 @@ impl From<i64> for Numeric {
     }
 }
 
+/// A piece of a `TemplateString`, in source order.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TemplatePart {
+    Text(Box<str>),
+    /// The source between `${` and `}`, left for the parser to lex.
+    Expression(Span),
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum TokenKind {
     BooleanLiteral(bool),
@@ -64,6 +85,10 @@ pub enum TokenKind {
     Punctuator(Punctuator),
     NumericLiteral(Numeric),
     StringLiteral(Box<str>),
+    /// A string literal with `${expression}` interpolations.
+    TemplateString(Vec<TemplatePart>),
+    /// The `${` of a string interpolation the input ends inside of.
+    UnterminatedInterpolation,
     // TODO comment
     // Comment
     // TODO user error
@@ -149,6 +174,8 @@ impl fmt::Display for TokenKind {
             Self::Keyword(ref word) => write!(f, "{}", word),
             Self::NumericLiteral(Numeric::Integer(num)) => write!(f, "{}", num),
             Self::StringLiteral(ref lit) => write!(f, "{}", lit),
+            Self::TemplateString(_) => write!(f, "template string"),
+            Self::UnterminatedInterpolation => write!(f, "unterminated interpolation"),
             Self::Punctuator(ref punc) => write!(f, "{}", punc),
             Self::ILLEGAL => write!(f, "ILLEGAL"),
         }
@@ -166,6 +193,12 @@ pub fn lookup_identifier(identifier: &str) -> TokenKind {
         "if" => Keyword::If.into(),
         "else" => Keyword::Else.into(),
         "return" => Keyword::Return.into(),
+        "quote" => Keyword::Quote.into(),
+        "unquote" => Keyword::Unquote.into(),
+        "macro" => Keyword::Macro.into(),
+        "import" => Keyword::Import.into(),
+        "export" => Keyword::Export.into(),
+        "match" => Keyword::Match.into(),
         _ => TokenKind::identifier(identifier),
     }
 }
@@ -1,6 +1,10 @@
 pub mod evaluator;
 pub mod interpreter;
 pub mod lexer;
+pub mod lint;
+pub mod lsp;
 pub mod object;
+pub mod optimizer;
 pub mod parser;
 pub mod repl;
+pub mod resolver;
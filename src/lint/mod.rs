@@ -0,0 +1,336 @@
+//! Warnings about code that runs but is likely a mistake.
+//!
+//! The linter only looks at the tree and the bindings it can see, so it never reports errors the
+//! evaluator would, and it stays quiet about anything it cannot tell without running the program,
+//! like the arity of a function passed as an argument.
+
+use std::{collections::HashSet, convert::TryFrom, fmt, rc::Rc};
+
+use serde::Serialize;
+
+use crate::{
+    evaluator::{eval_infix_expression, eval_prefix_expression, is_truthy},
+    lexer::token::Span,
+    object::{HashKey, Object},
+    parser::{
+        node::{
+            BlockStatement, CallExpression, Expression, HashLiteral, IfExpression, Program,
+            QuoteExpression, Statement, TernaryExpression,
+        },
+        visitor::{
+            walk_block_statement, walk_call_expression, walk_hash_literal, walk_if_expression,
+            walk_program, walk_ternary_expression, walk_unquoted, Visitor,
+        },
+    },
+    resolver::{
+        self,
+        symbols::{DefinitionKind, Symbols, Target, ValueKind},
+        DiagnosticKind,
+    },
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// A `let` binding that is never read.
+    UnusedLet,
+    /// A parameter or `let` hiding a binding of an enclosing scope.
+    Shadowed,
+    /// A call to a known function literal with the wrong number of arguments.
+    WrongArity,
+    /// A statement after a `return` in the same block.
+    Unreachable,
+    /// An `if` whose condition is the same every time.
+    ConstantCondition,
+    /// A key repeated in a hash literal, which only keeps the last value.
+    DuplicateKey,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedLet,
+        Rule::Shadowed,
+        Rule::WrongArity,
+        Rule::Unreachable,
+        Rule::ConstantCondition,
+        Rule::DuplicateKey,
+    ];
+
+    pub fn lookup(name: &str) -> Option<Rule> {
+        Self::ALL.iter().find(|r| r.name() == name).copied()
+    }
+
+    /// The name the rule is enabled and reported by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnusedLet => "unused-let",
+            Rule::Shadowed => "shadowed",
+            Rule::WrongArity => "wrong-arity",
+            Rule::Unreachable => "unreachable",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::DuplicateKey => "duplicate-key",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The rules to check, all enabled by default.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rules {
+    pub unused_let: bool,
+    pub shadowed: bool,
+    pub wrong_arity: bool,
+    pub unreachable: bool,
+    pub constant_condition: bool,
+    pub duplicate_key: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            unused_let: true,
+            shadowed: true,
+            wrong_arity: true,
+            unreachable: true,
+            constant_condition: true,
+            duplicate_key: true,
+        }
+    }
+}
+
+impl Rules {
+    fn flag(&mut self, rule: Rule) -> &mut bool {
+        match rule {
+            Rule::UnusedLet => &mut self.unused_let,
+            Rule::Shadowed => &mut self.shadowed,
+            Rule::WrongArity => &mut self.wrong_arity,
+            Rule::Unreachable => &mut self.unreachable,
+            Rule::ConstantCondition => &mut self.constant_condition,
+            Rule::DuplicateKey => &mut self.duplicate_key,
+        }
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        match rule {
+            Rule::UnusedLet => self.unused_let,
+            Rule::Shadowed => self.shadowed,
+            Rule::WrongArity => self.wrong_arity,
+            Rule::Unreachable => self.unreachable,
+            Rule::ConstantCondition => self.constant_condition,
+            Rule::DuplicateKey => self.duplicate_key,
+        }
+    }
+
+    pub fn set(&mut self, rule: Rule, enabled: bool) {
+        *self.flag(rule) = enabled;
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Warning {
+    pub rule: Rule,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+pub fn lint(program: &Program) -> Vec<Warning> {
+    lint_with(program, Rules::default())
+}
+
+/// Checks `program` against the enabled `rules`, returning the warnings in source order.
+pub fn lint_with(program: &Program, rules: Rules) -> Vec<Warning> {
+    let mut linter = Linter {
+        rules,
+        symbols: Symbols::new(program),
+        warnings: Vec::new(),
+    };
+    linter.visit_program(program);
+    if rules.unused_let {
+        linter.unused_lets();
+    }
+    if rules.shadowed {
+        for d in resolver::resolve(&mut program.clone()) {
+            if d.kind == DiagnosticKind::Shadowed {
+                linter.warn(Rule::Shadowed, d.to_string(), d.span);
+            }
+        }
+    }
+    let mut warnings = linter.warnings;
+    warnings.sort_by_key(|w| w.span.start);
+    warnings
+}
+
+/// The one-based line and column of the byte offset `offset` in `text`, counting columns in
+/// characters.
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+struct Linter {
+    rules: Rules,
+    symbols: Symbols,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn warn(&mut self, rule: Rule, message: String, span: Span) {
+        self.warnings.push(Warning {
+            rule,
+            message,
+            span,
+        });
+    }
+
+    fn unused_lets(&mut self) {
+        for id in 0..self.symbols.definitions.len() {
+            let def = &self.symbols.definitions[id];
+            // a leading underscore marks a binding kept on purpose; parameters and the names of a
+            // `match` pattern are often bound only to get at the others
+            if def.kind != DefinitionKind::Let
+                || def.exported
+                || def.name.starts_with('_')
+                || self.symbols.reads(id) > 0
+            {
+                continue;
+            }
+            let (message, span) = (format!("{} is never read", def.name), def.span);
+            self.warn(Rule::UnusedLet, message, span);
+        }
+    }
+
+    fn check_statements(&mut self, stmts: &[Statement]) {
+        if !self.rules.unreachable {
+            return;
+        }
+        let returned = stmts.iter().position(|s| matches!(s, Statement::Return(_)));
+        if let Some(unreachable) = returned.and_then(|i| stmts.get(i + 1)) {
+            let span = unreachable.span().to(stmts.last().unwrap().span());
+            self.warn(Rule::Unreachable, "unreachable code".to_string(), span);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        if self.rules.constant_condition {
+            if let Some(truthy) = constant_truthiness(condition) {
+                let message = format!("condition is always {}", truthy);
+                self.warn(Rule::ConstantCondition, message, condition.span());
+            }
+        }
+    }
+
+    /// The parameter count of the function literal `exp` evaluates to, if known.
+    fn arity(&self, exp: &Expression) -> Option<usize> {
+        match exp {
+            Expression::Function(func) => Some(func.parameters.len()),
+            Expression::Identifier(ident) => match self.symbols.reference(ident.span)? {
+                Target::Definition(id) => match &self.symbols.definitions[id].value {
+                    ValueKind::Function(params) => Some(params.len()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Linter {
+    fn visit_program(&mut self, program: &'ast Program) {
+        self.check_statements(&program.body);
+        walk_program(self, program);
+    }
+
+    fn visit_block_statement(&mut self, block: &'ast BlockStatement) {
+        self.check_statements(&block.statements);
+        walk_block_statement(self, block);
+    }
+
+    fn visit_if_expression(&mut self, exp: &'ast IfExpression) {
+        self.check_condition(&exp.condition);
+        walk_if_expression(self, exp);
+    }
+
+    fn visit_ternary_expression(&mut self, exp: &'ast TernaryExpression) {
+        self.check_condition(&exp.condition);
+        walk_ternary_expression(self, exp);
+    }
+
+    fn visit_call_expression(&mut self, exp: &'ast CallExpression) {
+        if self.rules.wrong_arity {
+            if let Some(want) = self.arity(&exp.function) {
+                let got = exp.arguments.len();
+                if want != got {
+                    let message = format!("wrong number of arguments: want={}, got={}", want, got);
+                    self.warn(Rule::WrongArity, message, exp.span);
+                }
+            }
+        }
+        walk_call_expression(self, exp);
+    }
+
+    fn visit_hash_literal(&mut self, hash: &'ast HashLiteral) {
+        if self.rules.duplicate_key {
+            let mut seen = HashSet::new();
+            for (key, _) in &hash.pairs {
+                let key_value = constant(key).and_then(|obj| HashKey::try_from(&*obj).ok());
+                if let Some(key_value) = key_value {
+                    if !seen.insert(key_value.clone()) {
+                        let message = match key_value {
+                            HashKey::String(s) => format!("duplicate key \"{}\"", s),
+                            _ => format!("duplicate key {}", key_value),
+                        };
+                        self.warn(Rule::DuplicateKey, message, key.span());
+                    }
+                }
+            }
+        }
+        walk_hash_literal(self, hash);
+    }
+
+    // quoted code only runs where a macro splices it
+    fn visit_quote_expression(&mut self, exp: &'ast QuoteExpression) {
+        walk_unquoted(self, exp);
+    }
+}
+
+/// The value of an expression made of literals and operators, unless evaluating it fails.
+fn constant(exp: &Expression) -> Option<Rc<Object>> {
+    match exp {
+        Expression::Integer(lit) => Some(Rc::new(Object::Int(lit.value))),
+        Expression::Boolean(lit) => Some(Rc::new(Object::Bool(lit.value))),
+        Expression::String(lit) => Some(Rc::new(Object::String(lit.value.clone()))),
+        Expression::Prefix(prefix) => {
+            eval_prefix_expression(&prefix.operator, constant(&prefix.right)?).ok()
+        }
+        Expression::Infix(infix) => {
+            let (left, right) = (constant(&infix.left)?, constant(&infix.right)?);
+            eval_infix_expression(&infix.operator, left, right).ok()
+        }
+        _ => None,
+    }
+}
+
+fn constant_truthiness(exp: &Expression) -> Option<bool> {
+    match exp {
+        // whatever they hold, arrays, hashes and functions are truthy
+        Expression::Array(_) | Expression::Hash(_) | Expression::Function(_) => Some(true),
+        _ => constant(exp).map(|obj| is_truthy(&obj)),
+    }
+}
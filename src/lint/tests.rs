@@ -0,0 +1,158 @@
+use super::*;
+use crate::{lexer::Lexer, parser::Parser};
+
+fn check(input: &str, rules: Rules) -> Vec<(Rule, String, &str)> {
+    let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+    lint_with(&program, rules)
+        .into_iter()
+        .map(|w| (w.rule, w.message, &input[w.span.start..w.span.end]))
+        .collect()
+}
+
+fn only(rule: Rule) -> Rules {
+    let mut rules = Rules::default();
+    for r in Rule::ALL.iter() {
+        rules.set(*r, *r == rule);
+    }
+    rules
+}
+
+#[test]
+fn test_unused_let() {
+    let input = "let a = 1; let b = 2; let _c = 3; let f = fn(x) { let y = x; a }; f(b);";
+    assert_eq!(
+        check(input, only(Rule::UnusedLet)),
+        vec![(Rule::UnusedLet, "y is never read".to_string(), "y")]
+    );
+    assert_eq!(
+        check(
+            r#"let [p, ...q] = [1]; let {"k": [r]} = {}; fn([s, t]) { s + p + r }"#,
+            only(Rule::UnusedLet)
+        ),
+        vec![(Rule::UnusedLet, "q is never read".to_string(), "q")]
+    );
+    assert_eq!(
+        check(
+            "match ([1, 2]) { [a, b] if b > 1 => 1, [c, d] => c }",
+            only(Rule::UnusedLet)
+        ),
+        vec![]
+    );
+    // reads from a later function body count
+    assert_eq!(
+        check("let g = fn() { n }; let n = 1; g()", only(Rule::UnusedLet)),
+        vec![]
+    );
+}
+
+#[test]
+fn test_shadowed() {
+    assert_eq!(
+        check(
+            "let x = 1; fn(x) { let len = x; len }",
+            only(Rule::Shadowed)
+        ),
+        vec![(
+            Rule::Shadowed,
+            "x shadows an outer binding".to_string(),
+            "x"
+        )]
+    );
+}
+
+#[test]
+fn test_wrong_arity() {
+    let input = "let add = fn(a, b) { a + b }; add(1); add(1, 2); fn(x) { x }(1, 2); len(1, 2)";
+    assert_eq!(
+        check(input, only(Rule::WrongArity)),
+        vec![
+            (
+                Rule::WrongArity,
+                "wrong number of arguments: want=2, got=1".to_string(),
+                "add(1)"
+            ),
+            (
+                Rule::WrongArity,
+                "wrong number of arguments: want=1, got=2".to_string(),
+                "fn(x) { x }(1, 2)"
+            ),
+        ]
+    );
+    // through another binding, but not once it is rebound to something unknown
+    let input = "let f = fn(a) { a }; let g = f; g(); let f = first([f]); f()";
+    assert_eq!(check(input, only(Rule::WrongArity)).len(), 1);
+}
+
+#[test]
+fn test_unreachable() {
+    let input = "fn() { if (true) { return 1; 2; 3 } return 4; 5 }";
+    let warnings = check(input, only(Rule::Unreachable));
+    let spans: Vec<&str> = warnings.iter().map(|w| w.2).collect();
+    assert_eq!(spans, vec!["2; 3", "5"]);
+}
+
+#[test]
+fn test_constant_condition() {
+    let input = "if (1 < 2) { 1 }; if (!true) { 2 }; if ([]) { 3 }; if (x > 1) { 4 }; \
+        if (1 / 0) { 5 }; if x { 6 } else if \"\" { 7 }; 0 ? 8 : 9";
+    let warnings: Vec<(String, &str)> = check(input, only(Rule::ConstantCondition))
+        .into_iter()
+        .map(|w| (w.1, w.2))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            ("condition is always true".to_string(), "1 < 2"),
+            ("condition is always false".to_string(), "!true"),
+            ("condition is always true".to_string(), "[]"),
+            ("condition is always true".to_string(), "\"\""),
+            ("condition is always true".to_string(), "0"),
+        ]
+    );
+}
+
+#[test]
+fn test_duplicate_key() {
+    let input = r#"{"a": 1, "b": 2, "a": 3, 1 + 1: 4, 2: 5, true: 6, x: 7, x: 8}"#;
+    let warnings: Vec<(String, &str)> = check(input, only(Rule::DuplicateKey))
+        .into_iter()
+        .map(|w| (w.1, w.2))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (r#"duplicate key "a""#.to_string(), r#""a""#),
+            ("duplicate key 2".to_string(), "2"),
+        ]
+    );
+}
+
+#[test]
+fn test_rules() {
+    let mut rules = Rules::default();
+    assert!(Rule::ALL.iter().all(|r| rules.is_enabled(*r)));
+    rules.set(Rule::DuplicateKey, false);
+    assert!(!rules.duplicate_key);
+    assert_eq!(
+        Rule::lookup("constant-condition"),
+        Some(Rule::ConstantCondition)
+    );
+    assert_eq!(Rule::lookup("nope"), None);
+
+    let input = "let x = {1: 1, 1: 2}; return x; x";
+    let found: Vec<Rule> = check(input, Rules::default())
+        .into_iter()
+        .map(|w| w.0)
+        .collect();
+    assert_eq!(found, vec![Rule::DuplicateKey, Rule::Unreachable]);
+    assert_eq!(check(input, rules).len(), 1);
+}
+
+#[test]
+fn test_line_column() {
+    let text = "let a = 1;\n  let b = 2;";
+    assert_eq!(line_column(text, 0), (1, 1));
+    assert_eq!(line_column(text, 13), (2, 3));
+    let text = "\"é\"; x";
+    assert_eq!(line_column(text, 6), (1, 6));
+}
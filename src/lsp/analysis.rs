@@ -0,0 +1,195 @@
+//! What the server knows about one document: its diagnostics, and where its names are bound and
+//! read.
+
+use std::{cmp::Reverse, collections::HashSet};
+
+use crate::{
+    lexer::{
+        token::{Span, TokenKind},
+        Lexer,
+    },
+    object::Builtin,
+    parser::{ParseError, Parser},
+    resolver::{
+        self,
+        symbols::{Definition, DefinitionKind, Symbols, ValueKind},
+        DiagnosticKind,
+    },
+};
+
+/// A zero-based line and UTF-16 column, as the protocol counts them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// Converts between byte offsets into a document and `Position`s.
+pub(super) struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { text, line_starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = floor_char_boundary(self.text, offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        Position { line, character }
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        let start = match self.line_starts.get(position.line) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub(super) struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Symbols,
+    /// Set when the document does not parse, in which case nothing is bound.
+    pub parse_failed: bool,
+}
+
+impl Analysis {
+    pub fn new(text: &str) -> Self {
+        let mut analysis = Analysis::default();
+        let mut program = match Parser::new(Lexer::new(text)).parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                analysis.parse_failed = true;
+                analysis.diagnostics = errors.iter().map(parse_diagnostic).collect();
+                return analysis;
+            }
+        };
+        analysis.symbols = Symbols::new(&program);
+        for d in resolver::resolve(&mut program) {
+            analysis.diagnostics.push(Diagnostic {
+                span: d.span,
+                severity: match d.kind {
+                    DiagnosticKind::Undefined => Severity::Error,
+                    DiagnosticKind::Shadowed => Severity::Warning,
+                },
+                message: d.to_string(),
+            });
+        }
+        analysis
+    }
+
+    /// The names that can be read at `offset`, innermost bindings first, then the builtins.
+    pub fn completions(&self, text: &str, offset: usize) -> Vec<Completion> {
+        let mut items = Vec::new();
+        if self.parse_failed {
+            // offer whatever the document already names
+            let mut l = Lexer::new(text);
+            loop {
+                match l.next_token().kind {
+                    TokenKind::Identifier(label) => items.push(Completion {
+                        label: label.into(),
+                        function: false,
+                        detail: String::new(),
+                    }),
+                    TokenKind::EOF => break,
+                    _ => {}
+                }
+            }
+        } else {
+            let mut visible: Vec<&Definition> = self
+                .symbols
+                .definitions
+                .iter()
+                .filter(|d| d.scope.start <= offset && offset <= d.scope.end)
+                .collect();
+            visible.sort_by_key(|d| Reverse(d.scope.start));
+            items.extend(visible.into_iter().map(|d| Completion {
+                label: d.name.clone(),
+                function: matches!(d.value, ValueKind::Function(_)),
+                detail: match (&d.kind, &d.value) {
+                    (_, ValueKind::Function(params)) => format!("fn({})", params.join(", ")),
+                    (DefinitionKind::Parameter, _) => "parameter".to_string(),
+                    (DefinitionKind::MatchBinding, _) => "match binding".to_string(),
+                    (DefinitionKind::Let, ValueKind::Unknown) => String::new(),
+                    (DefinitionKind::Let, value) => value.name().to_string(),
+                },
+            }));
+        }
+        items.extend(Builtin::ALL.iter().map(|b| Completion {
+            label: b.name().to_string(),
+            function: true,
+            detail: "builtin".to_string(),
+        }));
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.label.clone()));
+        items
+    }
+}
+
+pub(super) struct Completion {
+    pub label: String,
+    pub function: bool,
+    pub detail: String,
+}
+
+/// The Markdown shown when hovering a name bound to `def`.
+pub(super) fn hover(def: &Definition) -> String {
+    let let_ = if def.exported { "export let" } else { "let" };
+    let signature = match (&def.kind, &def.value) {
+        (DefinitionKind::Let, ValueKind::Function(params)) => {
+            format!("{} {} = fn({})", let_, def.name, params.join(", "))
+        }
+        (DefinitionKind::Let, ValueKind::Unknown) => format!("{} {}", let_, def.name),
+        (DefinitionKind::Let, value) => format!("{} {}: {}", let_, def.name, value.name()),
+        (DefinitionKind::Parameter, _) => format!("{}: parameter", def.name),
+        (DefinitionKind::MatchBinding, _) => format!("{}: match binding", def.name),
+    };
+    format!("```monkey\n{}\n```", signature)
+}
+
+fn parse_diagnostic(err: &ParseError) -> Diagnostic {
+    Diagnostic {
+        span: err.span,
+        severity: Severity::Error,
+        message: err.message.clone(),
+    }
+}
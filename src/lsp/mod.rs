@@ -0,0 +1,399 @@
+//! A Language Server Protocol server for Monkey, spoken over any reader and writer.
+//!
+//! Documents are synced in full on every change and analyzed from scratch, which is cheap for
+//! scripts of the size Monkey is used for. The server publishes parse errors and resolver
+//! diagnostics, and answers semantic tokens, go-to-definition, hover and completion requests.
+//!
+//! Offsets inside the server are byte offsets into the document, converted to the protocol's
+//! UTF-16 positions at the edges.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use self::analysis::{hover, Analysis, LineIndex, Position, Severity};
+use crate::{
+    lexer::{
+        punctuator::Punctuator,
+        token::{Span, TokenKind},
+        Lexer,
+    },
+    resolver::symbols::{DefinitionKind, Target, ValueKind},
+};
+
+mod analysis;
+#[cfg(test)]
+mod tests;
+
+/// The token types of the semantic tokens legend, indexed by the encoded tokens.
+const TOKEN_TYPES: [&str; 7] = [
+    "keyword",
+    "variable",
+    "parameter",
+    "function",
+    "string",
+    "number",
+    "operator",
+];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serves requests read from `input`, writing responses and notifications to `output`, until
+/// the client sends `exit` or closes `input`.
+pub fn serve<R: BufRead, W: Write>(input: R, output: W) -> io::Result<()> {
+    let mut server = Server {
+        input,
+        output,
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    server.run()
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+struct Server<R, W> {
+    input: R,
+    output: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+/// Why a request could not be answered.
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params() -> Self {
+        ResponseError {
+            code: INVALID_PARAMS,
+            message: "invalid params".to_string(),
+        }
+    }
+}
+
+type Response = Result<Value, ResponseError>;
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    fn run(&mut self) -> io::Result<()> {
+        while let Some(body) = self.read_message()? {
+            let message: Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(err) => {
+                    let error = ResponseError {
+                        code: PARSE_ERROR,
+                        message: err.to_string(),
+                    };
+                    self.respond(Value::Null, Err(error))?;
+                    continue;
+                }
+            };
+            let method = message["method"].as_str().unwrap_or_default();
+            let params = &message["params"];
+            match message.get("id") {
+                // a request
+                Some(id) => {
+                    let response = if self.shutdown {
+                        Err(ResponseError {
+                            code: INVALID_REQUEST,
+                            message: "the server is shut down".to_string(),
+                        })
+                    } else {
+                        self.request(method, params)
+                    };
+                    self.respond(id.clone(), response)?;
+                }
+                None if method == "exit" => return Ok(()),
+                None => self.notification(method, params)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Response {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // full documents on every change
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true,
+                    },
+                },
+                "serverInfo": { "name": "monkey-rs", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/semanticTokens/full" => {
+                let doc = self.document(params)?;
+                Ok(json!({ "data": semantic_tokens(doc) }))
+            }
+            "textDocument/definition" => {
+                let (doc, uri, offset) = self.position(params)?;
+                let index = LineIndex::new(&doc.text);
+                Ok(match doc.analysis.symbols.reference_at(offset) {
+                    Some((_, Target::Definition(id))) => {
+                        let span = doc.analysis.symbols.definitions[id].span;
+                        json!({ "uri": uri, "range": range(&index, span) })
+                    }
+                    _ => Value::Null,
+                })
+            }
+            "textDocument/hover" => {
+                let (doc, _, offset) = self.position(params)?;
+                let index = LineIndex::new(&doc.text);
+                let (span, contents) = match doc.analysis.symbols.reference_at(offset) {
+                    Some((span, Target::Definition(id))) => {
+                        (span, hover(&doc.analysis.symbols.definitions[id]))
+                    }
+                    Some((span, Target::Builtin(b))) => {
+                        (span, format!("```monkey\nbuiltin {}\n```", b.name()))
+                    }
+                    _ => return Ok(Value::Null),
+                };
+                Ok(json!({
+                    "contents": { "kind": "markdown", "value": contents },
+                    "range": range(&index, span),
+                }))
+            }
+            "textDocument/completion" => {
+                let (doc, _, offset) = self.position(params)?;
+                let items: Vec<Value> = doc
+                    .analysis
+                    .completions(&doc.text, offset)
+                    .into_iter()
+                    .map(|item| {
+                        json!({
+                            "label": item.label,
+                            // `Function` or `Variable`
+                            "kind": if item.function { 3 } else { 6 },
+                            "detail": item.detail,
+                        })
+                    })
+                    .collect();
+                Ok(Value::Array(items))
+            }
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method: {}", method),
+            }),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let uri = match params["textDocument"]["uri"].as_str() {
+            Some(uri) => uri.to_string(),
+            None => return Ok(()),
+        };
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // with full sync the last change holds the whole document
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+            }
+            _ => None,
+        };
+        let text = match text {
+            Some(text) => text.to_string(),
+            None => return Ok(()),
+        };
+        let doc = Document {
+            analysis: Analysis::new(&text),
+            text,
+        };
+        let index = LineIndex::new(&doc.text);
+        let diagnostics: Vec<Value> = doc
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|d| {
+                json!({
+                    "range": range(&index, d.span),
+                    "severity": match d.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    },
+                    "source": "monkey-rs",
+                    "message": d.message,
+                })
+            })
+            .collect();
+        self.documents.insert(uri.clone(), doc);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn document(&self, params: &Value) -> Result<&Document, ResponseError> {
+        params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+            .ok_or_else(ResponseError::invalid_params)
+    }
+
+    /// The document, URI and byte offset of a `TextDocumentPositionParams`.
+    fn position<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a Document, &'a str, usize), ResponseError> {
+        let doc = self.document(params)?;
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let (line, character) = match (
+            params["position"]["line"].as_u64(),
+            params["position"]["character"].as_u64(),
+        ) {
+            (Some(line), Some(character)) => (line as usize, character as usize),
+            _ => return Err(ResponseError::invalid_params()),
+        };
+        let offset = LineIndex::new(&doc.text).offset(Position { line, character });
+        Ok((doc, uri, offset))
+    }
+
+    /// Reads the body of the next message, or `None` once the input is closed.
+    fn read_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let length = length.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+        })?;
+        let mut body = vec![0; length];
+        self.input.read_exact(&mut body)?;
+        Ok(Some(body))
+    }
+
+    fn write_message(&mut self, message: Value) -> io::Result<()> {
+        let body = message.to_string();
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        self.output.flush()
+    }
+
+    fn respond(&mut self, id: Value, response: Response) -> io::Result<()> {
+        let message = match response {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        };
+        self.write_message(message)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.write_message(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+}
+
+fn range(index: &LineIndex, span: Span) -> Value {
+    let (start, end) = (index.position(span.start), index.position(span.end));
+    json!({
+        "start": { "line": start.line, "character": start.character },
+        "end": { "line": end.line, "character": end.character },
+    })
+}
+
+/// Encodes the tokens of a document as the protocol's relative five-integer groups. Tokens
+/// spanning several lines, which only strings can, are left out.
+fn semantic_tokens(doc: &Document) -> Vec<usize> {
+    let index = LineIndex::new(&doc.text);
+    let mut data = Vec::new();
+    let mut previous = Position {
+        line: 0,
+        character: 0,
+    };
+    let mut l = Lexer::new(&doc.text);
+    loop {
+        let token = l.next_token();
+        let token_type = match &token.kind {
+            TokenKind::EOF => break,
+            TokenKind::Keyword(_) | TokenKind::BooleanLiteral(_) => 0,
+            TokenKind::Identifier(_) => match doc.analysis.symbols.reference_at(token.span.start) {
+                Some((_, Target::Definition(id))) => {
+                    let def = &doc.analysis.symbols.definitions[id];
+                    match (&def.kind, &def.value) {
+                        (_, ValueKind::Function(_)) => 3,
+                        (DefinitionKind::Parameter, _) => 2,
+                        _ => 1,
+                    }
+                }
+                Some((_, Target::Builtin(_))) => 3,
+                _ => 1,
+            },
+            TokenKind::StringLiteral(_) | TokenKind::TemplateString(_) => 4,
+            TokenKind::NumericLiteral(_) => 5,
+            TokenKind::Punctuator(p) if is_operator(*p) => 6,
+            _ => continue,
+        };
+        let (start, end) = (
+            index.position(token.span.start),
+            index.position(token.span.end),
+        );
+        if start.line != end.line {
+            continue;
+        }
+        let delta_start = if start.line == previous.line {
+            start.character - previous.character
+        } else {
+            start.character
+        };
+        data.extend_from_slice(&[
+            start.line - previous.line,
+            delta_start,
+            end.character - start.character,
+            token_type,
+            0,
+        ]);
+        previous = start;
+    }
+    data
+}
+
+fn is_operator(p: Punctuator) -> bool {
+    p.as_binop().is_some() || p == Punctuator::Not || p == Punctuator::Assign
+}
@@ -0,0 +1,318 @@
+use serde_json::{json, Value};
+
+use super::*;
+
+const URI: &str = "file:///test.monkey";
+
+/// Runs the server over `messages` and returns everything it wrote, decoded.
+fn run(messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        let body = message.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    let mut output = Vec::new();
+    serve(&input[..], &mut output).unwrap();
+
+    let mut messages = Vec::new();
+    let mut rest = &output[..];
+    while !rest.is_empty() {
+        let header_end = rest.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let header = std::str::from_utf8(&rest[..header_end]).unwrap();
+        let length: usize = header["Content-Length: ".len()..].parse().unwrap();
+        let body = &rest[header_end + 4..header_end + 4 + length];
+        messages.push(serde_json::from_slice(body).unwrap());
+        rest = &rest[header_end + 4 + length..];
+    }
+    messages
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn open(text: &str) -> Value {
+    notification(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": URI, "languageId": "monkey", "version": 1, "text": text } }),
+    )
+}
+
+fn at(line: u64, character: u64) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+/// Opens `text`, sends `requests` and returns the results of the requests in order.
+fn results(text: &str, requests: &[(&str, Value)]) -> Vec<Value> {
+    let mut messages = vec![request(0, "initialize", json!({})), open(text)];
+    for (i, (method, params)) in requests.iter().enumerate() {
+        messages.push(request(i as u64 + 1, method, params.clone()));
+    }
+    run(&messages)
+        .into_iter()
+        .filter(|m| m["id"].as_u64().is_some_and(|id| id > 0))
+        .map(|m| m["result"].clone())
+        .collect()
+}
+
+#[test]
+fn test_initialize_and_exit() {
+    let output = run(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        request(2, "shutdown", Value::Null),
+        request(3, "textDocument/hover", at(0, 0)),
+        notification("exit", Value::Null),
+        request(4, "shutdown", Value::Null),
+    ]);
+    assert_eq!(output.len(), 3);
+    let capabilities = &output[0]["result"]["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(
+        capabilities["semanticTokensProvider"]["legend"]["tokenTypes"],
+        json!(TOKEN_TYPES)
+    );
+    assert_eq!(
+        output[1],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+    assert_eq!(output[2]["error"]["code"], INVALID_REQUEST);
+}
+
+#[test]
+fn test_unknown_method_and_bad_json() {
+    let mut input = b"Content-Length: 5\r\n\r\n{oops".to_vec();
+    let body = request(1, "workspace/symbol", json!({})).to_string();
+    write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    let mut output = Vec::new();
+    serve(&input[..], &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(&format!("\"code\":{}", PARSE_ERROR)));
+    assert!(output.contains(&format!("\"code\":{}", METHOD_NOT_FOUND)));
+}
+
+#[test]
+fn test_diagnostics() {
+    let output = run(&[
+        open("let x = 1;\nlet f = fn(x) { y };"),
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": "let = 1;" }] }),
+        ),
+        notification(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+    let diagnostics: Vec<&Value> = output
+        .iter()
+        .map(|m| {
+            assert_eq!(m["method"], "textDocument/publishDiagnostics");
+            &m["params"]["diagnostics"]
+        })
+        .collect();
+
+    assert_eq!(
+        diagnostics[0],
+        &json!([
+            {
+                "range": { "start": { "line": 1, "character": 11 }, "end": { "line": 1, "character": 12 } },
+                "severity": 2,
+                "source": "monkey-rs",
+                "message": "x shadows an outer binding",
+            },
+            {
+                "range": { "start": { "line": 1, "character": 16 }, "end": { "line": 1, "character": 17 } },
+                "severity": 1,
+                "source": "monkey-rs",
+                "message": "identifier not found: y",
+            },
+        ])
+    );
+    assert_eq!(diagnostics[1][0]["severity"], 1);
+    assert_eq!(diagnostics[1][0]["message"], "invalid pattern =");
+    assert_eq!(
+        diagnostics[1][0]["range"]["start"],
+        json!({ "line": 0, "character": 4 })
+    );
+    assert_eq!(diagnostics[2], &json!([]));
+}
+
+#[test]
+fn test_definition() {
+    let text = "let add = fn(a, b) { a + b };\nlet x = add(1, 2);\nlet x = x + 1;\nx";
+    let results = results(
+        text,
+        &[
+            ("textDocument/definition", at(1, 9)),
+            ("textDocument/definition", at(0, 21)),
+            ("textDocument/definition", at(3, 1)),
+            ("textDocument/definition", at(2, 8)),
+            ("textDocument/definition", at(1, 12)),
+        ],
+    );
+    let location = |line, start, end| {
+        json!({
+            "uri": URI,
+            "range": {
+                "start": { "line": line, "character": start },
+                "end": { "line": line, "character": end },
+            },
+        })
+    };
+    assert_eq!(results[0], location(0, 4, 7));
+    assert_eq!(results[1], location(0, 13, 14));
+    // the latest binding made before the read
+    assert_eq!(results[2], location(2, 4, 5));
+    assert_eq!(results[3], location(1, 4, 5));
+    assert_eq!(results[4], Value::Null);
+}
+
+#[test]
+fn test_hover() {
+    let text = "let add = fn(a, b) { a + b };\nlet n = 1;\nlet m = n;\nlen(add)";
+    let results = results(
+        text,
+        &[
+            ("textDocument/hover", at(3, 5)),
+            ("textDocument/hover", at(0, 21)),
+            ("textDocument/hover", at(2, 5)),
+            ("textDocument/hover", at(3, 1)),
+            ("textDocument/hover", at(1, 9)),
+        ],
+    );
+    assert_eq!(
+        results[0]["contents"]["value"],
+        "```monkey\nlet add = fn(a, b)\n```"
+    );
+    assert_eq!(
+        results[0]["range"],
+        json!({ "start": { "line": 3, "character": 4 }, "end": { "line": 3, "character": 7 } })
+    );
+    assert_eq!(
+        results[1]["contents"]["value"],
+        "```monkey\na: parameter\n```"
+    );
+    assert_eq!(
+        results[2]["contents"]["value"],
+        "```monkey\nlet m: integer\n```"
+    );
+    assert_eq!(
+        results[3]["contents"]["value"],
+        "```monkey\nbuiltin len\n```"
+    );
+    assert_eq!(results[4], Value::Null);
+}
+
+#[test]
+fn test_completion() {
+    let labels = |result: &Value| -> Vec<String> {
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let builtins: Vec<String> = crate::object::Builtin::ALL
+        .iter()
+        .map(|b| b.name().to_string())
+        .collect();
+
+    let text = "let total = 0;\nlet f = fn(x) { let y = x;  };\n";
+    let results = results(
+        text,
+        &[
+            ("textDocument/completion", at(1, 27)),
+            ("textDocument/completion", at(2, 0)),
+        ],
+    );
+    let mut expected = vec!["x", "y", "total", "f"];
+    expected.extend(builtins.iter().map(String::as_str));
+    assert_eq!(labels(&results[0]), expected);
+    assert_eq!(
+        results[0][0],
+        json!({ "label": "x", "kind": 6, "detail": "parameter" })
+    );
+    assert_eq!(
+        results[0][3],
+        json!({ "label": "f", "kind": 3, "detail": "fn(x)" })
+    );
+    assert_eq!(labels(&results[1])[..2], ["total", "f"]);
+
+    // while the document does not parse, the names it mentions are offered
+    let results = self::results(
+        "let count = 1; count +",
+        &[("textDocument/completion", at(0, 22))],
+    );
+    assert_eq!(labels(&results[0])[0], "count");
+    assert_eq!(labels(&results[0]).len(), builtins.len() + 1);
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let text = "let f = fn(a) {\n  len(a) + 10\n};\nf(\"s\")";
+    let results = results(
+        text,
+        &[(
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": URI } }),
+        )],
+    );
+    #[rustfmt::skip]
+    let expected = vec![
+        0, 0, 3, 0, 0, // let
+        0, 4, 1, 3, 0, // f
+        0, 2, 1, 6, 0, // =
+        0, 2, 2, 0, 0, // fn
+        0, 3, 1, 2, 0, // a
+        1, 2, 3, 3, 0, // len
+        0, 4, 1, 2, 0, // a
+        0, 3, 1, 6, 0, // +
+        0, 2, 2, 5, 0, // 10
+        2, 0, 1, 3, 0, // f
+        0, 2, 3, 4, 0, // "s"
+    ];
+    assert_eq!(results[0]["data"], json!(expected));
+}
+
+#[test]
+fn test_line_index() {
+    let text = "a\nbé😀c\n";
+    let index = LineIndex::new(text);
+    let c = text.find('c').unwrap();
+    assert_eq!(
+        index.position(c),
+        Position {
+            line: 1,
+            character: 4
+        }
+    );
+    assert_eq!(
+        index.offset(Position {
+            line: 1,
+            character: 4
+        }),
+        c
+    );
+    // past the end of a line or of the document
+    assert_eq!(
+        index.offset(Position {
+            line: 0,
+            character: 9
+        }),
+        1
+    );
+    assert_eq!(
+        index.offset(Position {
+            line: 7,
+            character: 0
+        }),
+        text.len()
+    );
+}
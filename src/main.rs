@@ -1,8 +1,48 @@
-use std::io;
+use std::{env, fs, io, process};
 
-use monkey_rs::repl;
+use serde::Serialize;
+
+use monkey_rs::{
+    lexer::{token::Span, Lexer},
+    lint::{self, Rule, Rules},
+    lsp, optimizer,
+    parser::Parser,
+    repl,
+};
+
+const USAGE: &str = "usage:
+  monkey-rs                         start the REPL
+  monkey-rs parse [--json] [--optimize] <file>
+                                    print the AST of a file, optionally optimized
+  monkey-rs lint [--json] [--disable <rule>] [--enable <rule>] <file>
+                                    warn about likely mistakes in a file; rules are
+                                    unused-let, shadowed, wrong-arity, unreachable,
+                                    constant-condition, duplicate-key or all
+  monkey-rs lsp                     run a language server on stdin and stdout";
 
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => start_repl(),
+        Some("parse") => parse_file(&args[1..]),
+        Some("lint") => lint_file(&args[1..]),
+        Some("lsp") => {
+            let input = io::stdin();
+            let output = io::stdout();
+            lsp::serve(input.lock(), output.lock())
+        }
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(arg) => {
+            eprintln!("unknown command: {}\n{}", arg, USAGE);
+            process::exit(2)
+        }
+    }
+}
+
+fn start_repl() -> io::Result<()> {
     // TODO get user name
     println!("This is the Monkey programming language!");
     let input = io::stdin();
@@ -10,3 +50,143 @@ fn main() -> io::Result<()> {
 
     repl::start(input.lock(), output.lock())
 }
+
+fn parse_file(args: &[String]) -> io::Result<()> {
+    let mut json = false;
+    let mut optimize = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--optimize" => optimize = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("unexpected argument: {}\n{}", arg, USAGE);
+                process::exit(2)
+            }
+        }
+    }
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("missing file\n{}", USAGE);
+            process::exit(2)
+        }
+    };
+
+    let input = fs::read_to_string(path)?;
+    let mut program = match Parser::new(Lexer::new(&input)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("parse error: {}", err);
+            }
+            process::exit(1)
+        }
+    };
+
+    if optimize {
+        optimizer::optimize(&mut program);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&program)?);
+    } else {
+        println!("{}", program);
+    }
+    Ok(())
+}
+
+fn lint_file(args: &[String]) -> io::Result<()> {
+    let mut json = false;
+    let mut rules = Rules::default();
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--enable" | "--disable" => {
+                let enabled = arg == "--enable";
+                let selected: Vec<Rule> = match args.next().map(String::as_str) {
+                    Some("all") => Rule::ALL.to_vec(),
+                    Some(name) => match Rule::lookup(name) {
+                        Some(rule) => vec![rule],
+                        None => {
+                            eprintln!("unknown rule: {}\n{}", name, USAGE);
+                            process::exit(2)
+                        }
+                    },
+                    None => {
+                        eprintln!("missing rule after {}\n{}", arg, USAGE);
+                        process::exit(2)
+                    }
+                };
+                for rule in selected {
+                    rules.set(rule, enabled);
+                }
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("unexpected argument: {}\n{}", arg, USAGE);
+                process::exit(2)
+            }
+        }
+    }
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("missing file\n{}", USAGE);
+            process::exit(2)
+        }
+    };
+
+    let input = fs::read_to_string(path)?;
+    let program = match Parser::new(Lexer::new(&input)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            if json {
+                let errors: Vec<serde_json::Value> = errors
+                    .iter()
+                    .map(|err| json_entry(&input, "parse", &err.message, err.span))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&errors)?);
+            } else {
+                for err in errors {
+                    let (line, column) = lint::line_column(&input, err.span.start);
+                    eprintln!("{}:{}:{}: parse error: {}", path, line, column, err);
+                }
+            }
+            process::exit(1)
+        }
+    };
+
+    let warnings = lint::lint_with(&program, rules);
+    if json {
+        let warnings: Vec<serde_json::Value> = warnings
+            .iter()
+            .map(|w| json_entry(&input, w.rule, &w.message, w.span))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&warnings)?);
+    } else {
+        for w in &warnings {
+            let (line, column) = lint::line_column(&input, w.span.start);
+            println!("{}:{}:{}: {}", path, line, column, w);
+        }
+    }
+    if !warnings.is_empty() {
+        process::exit(1)
+    }
+    Ok(())
+}
+
+/// An entry of `lint --json`, for a warning or a parse error.
+fn json_entry<R: Serialize>(input: &str, rule: R, message: &str, span: Span) -> serde_json::Value {
+    let (line, column) = lint::line_column(input, span.start);
+    serde_json::json!({
+        "rule": rule,
+        "message": message,
+        "span": span,
+        "line": line,
+        "column": column,
+    })
+}
@@ -0,0 +1,257 @@
+//! The array builtins. The ones taking a function call it through `apply_function`, so it may be
+//! a Monkey function as well as a builtin or a native one.
+
+use std::{cmp::Ordering, convert::TryFrom, rc::Rc};
+
+use super::{check_arity, Array, Object};
+use crate::evaluator::{apply_function, is_truthy, Context, EvalError, EvalResult};
+
+pub(super) fn new_array(elements: Vec<Rc<Object>>) -> EvalResult {
+    Ok(Rc::new(Object::Array(Rc::new(Array { elements }))))
+}
+
+/// The elements of the array argument `obj` of the builtin `name`.
+pub(super) fn elements<'a>(name: &str, obj: &'a Object) -> Result<&'a [Rc<Object>], EvalError> {
+    match obj {
+        Object::Array(a) => Ok(&a.elements),
+        obj => Err(EvalError::new(format!(
+            "argument to {} must be an array, got {}",
+            name, obj
+        ))),
+    }
+}
+
+pub(super) fn integer(name: &str, obj: &Object) -> Result<i64, EvalError> {
+    match obj {
+        Object::Int(i) => Ok(*i),
+        obj => Err(EvalError::new(format!(
+            "argument to {} must be an integer, got {}",
+            name, obj
+        ))),
+    }
+}
+
+/// The integer argument `obj` as a position in something of length `len`: negative positions
+/// count from the end, and positions out of range are clamped to it.
+pub(super) fn position(name: &str, obj: &Object, len: usize) -> Result<usize, EvalError> {
+    let i = integer(name, obj)?;
+    let len = i64::try_from(len).unwrap_or(i64::MAX);
+    let i = if i < 0 { len.saturating_add(i) } else { i };
+    Ok(i.clamp(0, len) as usize)
+}
+
+pub(super) fn map(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("map", args, 2, 2)?;
+    let mut mapped = Vec::new();
+    for el in elements("map", &args[0])? {
+        mapped.push(apply_function(&args[1], &vec![Rc::clone(el)], ctx)?);
+    }
+    new_array(mapped)
+}
+
+pub(super) fn filter(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("filter", args, 2, 2)?;
+    let mut kept = Vec::new();
+    for el in elements("filter", &args[0])? {
+        if is_truthy(&*apply_function(&args[1], &vec![Rc::clone(el)], ctx)?) {
+            kept.push(Rc::clone(el));
+        }
+    }
+    new_array(kept)
+}
+
+/// `reduce(array, f, initial)`, or starting from the first element when `initial` is left out.
+pub(super) fn reduce(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("reduce", args, 2, 3)?;
+    let mut items = elements("reduce", &args[0])?.iter();
+    let mut acc = match args.get(2).or_else(|| items.next()) {
+        Some(initial) => Rc::clone(initial),
+        None => {
+            return Err(EvalError::new(
+                "reduce of an empty array with no initial value",
+            ))
+        }
+    };
+    for el in items {
+        acc = apply_function(&args[1], &vec![acc, Rc::clone(el)], ctx)?;
+    }
+    Ok(acc)
+}
+
+/// `sort(array)` sorts integers or strings in ascending order, `sort(array, f)` by `f(a, b)`
+/// returning a negative integer, zero or a positive one when `a` goes before, with or after `b`.
+/// The sort is stable.
+pub(super) fn sort(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("sort", args, 1, 2)?;
+    let items = elements("sort", &args[0])?.to_vec();
+    let compare = |a: &Rc<Object>, b: &Rc<Object>| match args.get(1) {
+        Some(f) => match &*apply_function(f, &vec![Rc::clone(a), Rc::clone(b)], ctx)? {
+            Object::Int(i) => Ok(i.cmp(&0)),
+            obj => Err(EvalError::new(format!(
+                "sort comparator must return an integer, got {}",
+                obj
+            ))),
+        },
+        None => match (&**a, &**b) {
+            (Object::Int(a), Object::Int(b)) => Ok(a.cmp(b)),
+            (Object::String(a), Object::String(b)) => Ok(a.cmp(b)),
+            _ => Err(EvalError::new(format!(
+                "sort cannot compare {} and {}",
+                a, b
+            ))),
+        },
+    };
+    new_array(merge_sort(items, &compare)?)
+}
+
+// `slice::sort_by` may panic when the comparison is not a total order, which a Monkey
+// comparator need not be
+fn merge_sort<F>(mut items: Vec<Rc<Object>>, compare: &F) -> Result<Vec<Rc<Object>>, EvalError>
+where
+    F: Fn(&Rc<Object>, &Rc<Object>) -> Result<Ordering, EvalError>,
+{
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+    let mut merged = Vec::new();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let next = match compare(l, r)? {
+            Ordering::Greater => right.next(),
+            _ => left.next(),
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+pub(super) fn reverse(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("reverse", args, 1, 1)?;
+    new_array(
+        elements("reverse", &args[0])?
+            .iter()
+            .rev()
+            .cloned()
+            .collect(),
+    )
+}
+
+/// `slice(array, start, end)` with `end` defaulting to the length of the array.
+pub(super) fn slice(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("slice", args, 2, 3)?;
+    let items = elements("slice", &args[0])?;
+    let start = position("slice", &args[1], items.len())?;
+    let end = match args.get(2) {
+        Some(end) => position("slice", end, items.len())?,
+        None => items.len(),
+    };
+    new_array(items[start..end.max(start)].to_vec())
+}
+
+pub(super) fn concat(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    let arrays = args
+        .iter()
+        .map(|arg| elements("concat", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let len = arrays.iter().map(|a| a.len()).sum();
+    ctx.check_allocation(len)?;
+    let mut concatenated = Vec::with_capacity(len);
+    for elements in arrays {
+        concatenated.extend(elements.iter().cloned());
+    }
+    new_array(concatenated)
+}
+
+pub(super) fn contains(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("contains", args, 2, 2)?;
+    let found = elements("contains", &args[0])?.contains(&args[1]);
+    Ok(Rc::new(Object::Bool(found)))
+}
+
+/// The position of the first element equal to the value, or -1.
+pub(super) fn index_of(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("index_of", args, 2, 2)?;
+    let i = elements("index_of", &args[0])?
+        .iter()
+        .position(|el| *el == args[1])
+        .map_or(-1, |i| i as i64);
+    Ok(Rc::new(Object::Int(i)))
+}
+
+/// The pairs of elements at the same position, as long as the shorter array.
+pub(super) fn zip(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("zip", args, 2, 2)?;
+    let pairs = elements("zip", &args[0])?
+        .iter()
+        .zip(elements("zip", &args[1])?)
+        .map(|(a, b)| {
+            Rc::new(Object::Array(Rc::new(Array {
+                elements: vec![Rc::clone(a), Rc::clone(b)],
+            })))
+        })
+        .collect();
+    new_array(pairs)
+}
+
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`: the integers from `start`
+/// up to, but not including, `end`.
+pub(super) fn range(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("range", args, 1, 3)?;
+    let ints = args
+        .iter()
+        .map(|arg| integer("range", arg))
+        .collect::<Result<Vec<i64>, EvalError>>()?;
+    let (start, end, step) = match ints[..] {
+        [end] => (0, end, 1),
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Err(EvalError::new("range step must not be 0"));
+    }
+    let (start, end, step) = (i128::from(start), i128::from(end), i128::from(step));
+    let len = if step > 0 {
+        (end - start + step - 1) / step
+    } else {
+        (start - end - step - 1) / -step
+    };
+    let len = usize::try_from(len.max(0)).unwrap_or(usize::MAX);
+    // a range is only charged once built, too late to stop a huge one
+    ctx.check_allocation(len)?;
+    let ints = (0..len as i128).map(|i| Rc::new(Object::Int((start + i * step) as i64)));
+    new_array(ints.collect())
+}
+
+/// `join(array, separator)` of the elements as `puts` prints them, the separator defaulting to
+/// the empty string.
+pub(super) fn join(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("join", args, 1, 2)?;
+    let separator = match args.get(1).map(|a| &**a) {
+        None => "",
+        Some(Object::String(s)) => s,
+        Some(obj) => {
+            return Err(EvalError::new(format!(
+                "separator for join must be a string, got {}",
+                obj
+            )))
+        }
+    };
+    let elements = elements("join", &args[0])?;
+    let mut len = separator
+        .len()
+        .saturating_mul(elements.len().saturating_sub(1));
+    ctx.check_allocation(len)?;
+    let mut strings = Vec::with_capacity(elements.len());
+    for el in elements {
+        let s = el.inspect();
+        len = len.saturating_add(s.len());
+        ctx.check_allocation(len)?;
+        strings.push(s);
+    }
+    Ok(Rc::new(Object::String(strings.join(separator))))
+}
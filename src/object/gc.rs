@@ -0,0 +1,155 @@
+//! Cycle collection for environments.
+//!
+//! A closure keeps the environment it was defined in alive, and `let f = fn() { ... }` stores
+//! the closure back into that same environment, so reference counting alone never frees either.
+//! The collector finds such cycles by trial deletion: references from inside the object graph
+//! are subtracted from the reference counts, whatever is left over is held from outside (the
+//! host, an evaluation in progress) and is a root, and every environment not reachable from a
+//! root is garbage. Clearing the stores of the garbage environments breaks their cycles.
+
+use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
+
+use super::{Environment, Object};
+
+#[derive(Default)]
+struct Node {
+    strong: usize,
+    internal: usize,
+    children: Vec<usize>,
+    reachable: bool,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: HashMap<usize, Node>,
+}
+
+fn addr<T>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
+
+impl Graph {
+    /// Adds a node, returning `false` if it was already scanned.
+    fn visit<T>(&mut self, rc: &Rc<T>) -> bool {
+        let node = self.nodes.entry(addr(rc)).or_default();
+        if node.strong > 0 {
+            return false;
+        }
+        node.strong = Rc::strong_count(rc);
+        true
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.nodes.entry(to).or_default().internal += 1;
+        self.nodes.get_mut(&from).unwrap().children.push(to);
+    }
+
+    fn scan_env(&mut self, env: &Rc<RefCell<Environment>>) {
+        if !self.visit(env) {
+            return;
+        }
+        let from = addr(env);
+        let env = match env.try_borrow() {
+            Ok(env) => env,
+            // in use by an evaluation, so it is alive anyway
+            Err(_) => {
+                self.nodes.get_mut(&from).unwrap().strong = usize::MAX;
+                return;
+            }
+        };
+        if let Some(outer) = &env.outer {
+            self.edge(from, addr(outer));
+            self.scan_env(outer);
+        }
+        for value in env.store.values().chain(env.slots.iter().flatten()) {
+            self.edge(from, addr(value));
+            self.scan_object(value);
+        }
+    }
+
+    fn scan_object(&mut self, obj: &Rc<Object>) {
+        if !self.visit(obj) {
+            return;
+        }
+        let from = addr(obj);
+        stacker::maybe_grow(32 * 1024, 1024 * 1024, || match &**obj {
+            Object::Function(f) | Object::Macro(f) => {
+                self.edge(from, addr(f));
+                if self.visit(f) {
+                    self.edge(addr(f), addr(&f.env));
+                    self.scan_env(&f.env);
+                }
+            }
+            Object::Return(r) => {
+                self.edge(from, addr(r));
+                if self.visit(r) {
+                    self.edge(addr(r), addr(&r.value));
+                    self.scan_object(&r.value);
+                }
+            }
+            Object::Array(a) => {
+                self.edge(from, addr(a));
+                if self.visit(a) {
+                    for e in &a.elements {
+                        self.edge(addr(a), addr(e));
+                        self.scan_object(e);
+                    }
+                }
+            }
+            Object::Hash(h) => {
+                self.edge(from, addr(h));
+                if self.visit(h) {
+                    for v in h.pairs.values() {
+                        self.edge(addr(h), addr(v));
+                        self.scan_object(v);
+                    }
+                }
+            }
+            // native functions may capture objects, which then count as held by the host
+            _ => {}
+        });
+    }
+
+    fn mark(&mut self) {
+        let mut pending: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.strong > n.internal)
+            .map(|(a, _)| *a)
+            .collect();
+        while let Some(a) = pending.pop() {
+            let node = self.nodes.get_mut(&a).unwrap();
+            if node.reachable {
+                continue;
+            }
+            node.reachable = true;
+            pending.extend(node.children.iter().copied());
+        }
+    }
+}
+
+/// Frees the environments of `envs` that are only kept alive by reference cycles, returning
+/// how many were freed. The caller's handles in `envs` are not counted as references.
+pub(crate) fn collect(envs: &[Rc<RefCell<Environment>>]) -> usize {
+    let mut graph = Graph::default();
+    for env in envs {
+        graph.scan_env(env);
+    }
+    for env in envs {
+        graph.nodes.get_mut(&addr(env)).unwrap().internal += 1;
+    }
+    graph.mark();
+
+    let garbage: Vec<_> = envs
+        .iter()
+        .filter(|env| !graph.nodes[&addr(env)].reachable)
+        .map(|env| {
+            let mut env = env.borrow_mut();
+            (mem::take(&mut env.store), mem::take(&mut env.slots))
+        })
+        .collect();
+    let freed = garbage.len();
+    // dropping the values may free other environments, so only once nothing is borrowed
+    drop(garbage);
+    freed
+}
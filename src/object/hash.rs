@@ -0,0 +1,103 @@
+//! The hash builtins. Those changing a hash return a new one, keeping the order of the pairs.
+
+use std::{convert::TryFrom, rc::Rc};
+
+use indexmap::IndexMap;
+
+use super::{
+    array::{elements, new_array},
+    check_arity, Array, HashKey, MonkeyHash, Object,
+};
+use crate::evaluator::{EvalError, EvalResult};
+
+fn new_hash(pairs: IndexMap<HashKey, Rc<Object>>) -> EvalResult {
+    Ok(Rc::new(Object::Hash(Rc::new(MonkeyHash { pairs }))))
+}
+
+/// The pairs of the hash argument `obj` of the builtin `name`.
+fn pairs<'a>(name: &str, obj: &'a Object) -> Result<&'a IndexMap<HashKey, Rc<Object>>, EvalError> {
+    match obj {
+        Object::Hash(h) => Ok(&h.pairs),
+        obj => Err(EvalError::new(format!(
+            "argument to {} must be a hash, got {}",
+            name, obj
+        ))),
+    }
+}
+
+// the same error as indexing a hash with the key
+fn key(obj: &Object) -> Result<HashKey, EvalError> {
+    HashKey::try_from(obj).map_err(EvalError::new)
+}
+
+pub(super) fn keys(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("keys", args, 1, 1)?;
+    let keys = pairs("keys", &args[0])?
+        .keys()
+        .map(|k| Rc::new(k.to_object()))
+        .collect();
+    new_array(keys)
+}
+
+pub(super) fn values(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("values", args, 1, 1)?;
+    let values = pairs("values", &args[0])?.values().cloned().collect();
+    new_array(values)
+}
+
+/// The pairs as `[key, value]` arrays.
+pub(super) fn entries(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("entries", args, 1, 1)?;
+    let entries = pairs("entries", &args[0])?
+        .iter()
+        .map(|(k, v)| {
+            let elements = vec![Rc::new(k.to_object()), Rc::clone(v)];
+            Rc::new(Object::Array(Rc::new(Array { elements })))
+        })
+        .collect();
+    new_array(entries)
+}
+
+pub(super) fn has(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("has", args, 2, 2)?;
+    let found = pairs("has", &args[0])?.contains_key(&key(&args[1])?);
+    Ok(Rc::new(Object::Bool(found)))
+}
+
+/// A copy of the hash without the key.
+pub(super) fn delete(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("delete", args, 2, 2)?;
+    let mut pairs = pairs("delete", &args[0])?.clone();
+    pairs.shift_remove(&key(&args[1])?);
+    new_hash(pairs)
+}
+
+/// The pairs of both hashes, the second one's value winning for a key in both.
+pub(super) fn merge(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("merge", args, 2, 2)?;
+    let mut merged = pairs("merge", &args[0])?.clone();
+    for (k, v) in pairs("merge", &args[1])? {
+        merged.insert(k.clone(), Rc::clone(v));
+    }
+    new_hash(merged)
+}
+
+/// The hash of an array of `[key, value]` arrays, the inverse of `entries`.
+pub(super) fn from_entries(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("from_entries", args, 1, 1)?;
+    let mut pairs = IndexMap::new();
+    for entry in elements("from_entries", &args[0])? {
+        match &**entry {
+            Object::Array(a) if a.elements.len() == 2 => {
+                pairs.insert(key(&a.elements[0])?, Rc::clone(&a.elements[1]));
+            }
+            obj => {
+                return Err(EvalError::new(format!(
+                    "entry for from_entries must be a [key, value] array, got {}",
+                    obj
+                )))
+            }
+        }
+    }
+    new_hash(pairs)
+}
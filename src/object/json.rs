@@ -0,0 +1,120 @@
+use std::{io, rc::Rc};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Map, Number, Serializer, Value};
+
+use super::{Array, HashKey, MonkeyHash, Object};
+use crate::evaluator::{Context, EvalError};
+
+impl Object {
+    /// Converts the object into a JSON value. Hashes must only have string keys, and
+    /// functions have no JSON representation.
+    pub fn to_json(&self) -> Result<Value, String> {
+        match self {
+            Object::Int(i) => Ok(Value::Number(Number::from(*i))),
+            Object::Bool(b) => Ok(Value::Bool(*b)),
+            Object::String(s) => Ok(Value::String(s.clone())),
+            Object::Null => Ok(Value::Null),
+            Object::Return(r) => r.value.to_json(),
+            Object::Array(a) => a
+                .elements
+                .iter()
+                .map(|e| e.to_json())
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Object::Hash(h) => {
+                let mut map = Map::new();
+                for (key, value) in &h.pairs {
+                    match key {
+                        HashKey::String(k) => {
+                            map.insert(k.clone(), value.to_json()?);
+                        }
+                        k => return Err(format!("hash key {} is not a string", k)),
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+            Object::Quote(_) => Err(format!("cannot convert quote {} to JSON", self.inspect())),
+            Object::Function(_) | Object::Macro(_) | Object::Builtin(_) | Object::Native(_) => Err(
+                format!("cannot convert function {} to JSON", self.inspect()),
+            ),
+        }
+    }
+
+    /// Builds an object from a JSON value. Numbers must fit in an `i64`, since Monkey has no
+    /// floating point type.
+    pub fn from_json(value: &Value) -> Result<Object, String> {
+        match value {
+            Value::Null => Ok(Object::Null),
+            Value::Bool(b) => Ok(Object::Bool(*b)),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => Ok(Object::Int(i)),
+                None => Err(format!("number {} is not an integer", n)),
+            },
+            Value::String(s) => Ok(Object::String(s.clone())),
+            Value::Array(values) => {
+                let mut elements = Vec::with_capacity(values.len());
+                for v in values {
+                    elements.push(Rc::new(Object::from_json(v)?));
+                }
+                Ok(Object::Array(Rc::new(Array { elements })))
+            }
+            Value::Object(map) => {
+                let mut pairs = IndexMap::new();
+                for (k, v) in map {
+                    pairs.insert(HashKey::String(k.clone()), Rc::new(Object::from_json(v)?));
+                }
+                Ok(Object::Hash(Rc::new(MonkeyHash { pairs })))
+            }
+        }
+    }
+}
+
+/// Parses a JSON document into an object.
+pub fn parse(input: &str) -> Result<Object, String> {
+    let value: Value = serde_json::from_str(input).map_err(|e| e.to_string())?;
+    Object::from_json(&value)
+}
+
+/// The widest indent `stringify` accepts, as in JavaScript's `JSON.stringify`.
+pub const MAX_INDENT: usize = 10;
+
+/// Serializes an object to a JSON string, pretty printed with `indent` spaces when it is
+/// greater than zero. The string is measured first and only built when it fits the memory
+/// limit of `ctx`.
+pub fn stringify(obj: &Object, indent: usize, ctx: &Context) -> Result<String, EvalError> {
+    let error = |err: String| EvalError::new(format!("json_stringify: {}", err));
+    let value = obj.to_json().map_err(error)?;
+    let indent = " ".repeat(indent);
+
+    let mut size = ByteCount(0);
+    write(&value, indent.as_bytes(), &mut size).map_err(|e| error(e.to_string()))?;
+    ctx.check_allocation(size.0)?;
+
+    let mut buf = Vec::with_capacity(size.0);
+    write(&value, indent.as_bytes(), &mut buf).map_err(|e| error(e.to_string()))?;
+    String::from_utf8(buf).map_err(|e| error(e.to_string()))
+}
+
+fn write<W: io::Write>(value: &Value, indent: &[u8], writer: W) -> serde_json::Result<()> {
+    if indent.is_empty() {
+        return serde_json::to_writer(writer, value);
+    }
+    let mut ser = Serializer::with_formatter(writer, PrettyFormatter::with_indent(indent));
+    value.serialize(&mut ser)
+}
+
+/// A writer that only counts the bytes written to it.
+struct ByteCount(usize);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
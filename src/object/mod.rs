@@ -1,11 +1,22 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::convert::TryFrom;
 use std::{fmt, rc::Rc};
 
-use crate::parser::node;
+use indexmap::IndexMap;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use crate::{
+    evaluator::{Context, EvalError, EvalResult},
+    parser::node,
+};
+
+mod array;
+pub(crate) mod gc;
+mod hash;
+pub mod json;
+mod string;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Object {
     Int(i64),
     Bool(bool),
@@ -13,8 +24,13 @@ pub enum Object {
     Return(Rc<Return>),
     Function(Rc<Function>),
     Builtin(Builtin),
+    Native(Rc<NativeFunction>),
     Array(Rc<Array>),
     Hash(Rc<MonkeyHash>),
+    /// The expression of an evaluated `quote`, with its `unquote`s replaced.
+    Quote(Rc<node::Expression>),
+    /// A macro, applied to quoted arguments when calls to it are expanded.
+    Macro(Rc<Function>),
     Null,
     // CompiledFunction(Rc<CompiledFunction>),
     // Closure(Rc<Closure>),
@@ -23,15 +39,36 @@ pub enum Object {
 #[derive(Clone, Debug)]
 pub struct Environment {
     pub store: HashMap<String, Rc<Object>>,
+    /// Values of resolved local bindings, indexed by `Binding::Local::slot`.
+    pub slots: Vec<Option<Rc<Object>>>,
     pub outer: Option<Rc<RefCell<Environment>>>,
+    context: Rc<Context>,
+    /// Set once the garbage collector knows about the environment.
+    pub(crate) tracked: Cell<bool>,
+}
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl Environment {
     pub fn new() -> Self {
+        Self::with_context(Rc::new(Context::new()))
+    }
+    pub fn with_context(context: Rc<Context>) -> Self {
         Environment {
             store: HashMap::new(),
+            slots: Vec::new(),
             outer: None,
+            context,
+            tracked: Cell::new(false),
         }
     }
+    /// Gets the evaluation context shared with the enclosing environments.
+    pub fn context(&self) -> Rc<Context> {
+        Rc::clone(&self.context)
+    }
     pub fn set(&mut self, name: String, val: Rc<Object>) {
         self.store.insert(name, val);
     }
@@ -46,67 +83,102 @@ impl Environment {
         }
     }
     pub fn new_enclosed(env: Rc<RefCell<Environment>>) -> Environment {
+        let context = env.borrow().context();
         Environment {
             store: HashMap::new(),
-            outer: Some(Rc::clone(&env)),
+            slots: Vec::new(),
+            outer: Some(env),
+            context,
+            tracked: Cell::new(false),
+        }
+    }
+    pub fn set_slot(&mut self, slot: usize, val: Rc<Object>) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(val);
+    }
+    /// Gets the value of `slot` in the environment `depth` levels up, `None` if it has not been
+    /// assigned yet.
+    pub fn get_slot(&self, depth: usize, slot: usize) -> Option<Rc<Object>> {
+        if depth == 0 {
+            return self.slots.get(slot).cloned().flatten();
+        }
+        match &self.outer {
+            Some(o) => o.borrow().get_slot(depth - 1, slot),
+            None => None,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Return {
     pub value: Rc<Object>,
 }
-impl PartialEq for Return {
-    fn eq(&self, _other: &Return) -> bool {
-        unimplemented!("partial eq not implemented for Return")
-    }
-}
-
-impl Eq for Return {}
-impl Hash for Return {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        // we should never hash an array so should be fine
-        panic!("hash for return not supported");
-    }
-}
 
 #[derive(Debug, Clone)]
 pub struct Function {
-    pub parameters: Vec<node::IdentifierExpression>,
+    pub parameters: Vec<node::Pattern>,
     pub body: node::BlockStatement,
     pub env: Rc<RefCell<Environment>>,
 }
 
 impl Function {
-    fn inspect(&self) -> String {
-        let params: Vec<String> = (&self.parameters)
-            .into_iter()
-            .map(|p| p.to_string())
-            .collect();
-        format!(
-            "fn({}) {{\n{}\n}}",
-            params.join(", "),
-            self.body.to_string()
-        )
+    fn inspect(&self, keyword: &str) -> String {
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        format!("{}({}) {{\n{}\n}}", keyword, params.join(", "), self.body)
     }
 }
 
+// Functions are only equal to themselves: two literals with the same body still close over
+// different environments.
 impl PartialEq for Function {
-    fn eq(&self, _other: &Function) -> bool {
-        // TODO: implement this, but it should never get used
-        panic!("partial eq not implemented for function");
+    fn eq(&self, other: &Function) -> bool {
+        std::ptr::eq(self, other)
     }
 }
 impl Eq for Function {}
-impl Hash for Function {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        // we should never hash an array so should be fine
-        panic!("hash for function not supported");
+
+pub type NativeFn = dyn Fn(&[Rc<Object>]) -> EvalResult;
+
+/// A function implemented by the host program.
+pub struct NativeFunction {
+    pub name: String,
+    /// The number of arguments the function takes, `None` accepts any number.
+    pub arity: Option<usize>,
+    pub func: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: Option<usize>, func: F) -> Self
+    where
+        F: Fn(&[Rc<Object>]) -> EvalResult + 'static,
+    {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            func: Box::new(func),
+        }
     }
 }
 
-#[derive(Hash, Clone, Debug, PartialEq, Eq)]
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &NativeFunction) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+impl Eq for NativeFunction {}
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Builtin {
     Len,
     Puts,
@@ -114,41 +186,159 @@ pub enum Builtin {
     Last,
     Rest,
     Push,
+    JsonParse,
+    JsonStringify,
+    Map,
+    Filter,
+    Reduce,
+    Sort,
+    Reverse,
+    Slice,
+    Concat,
+    Contains,
+    IndexOf,
+    Zip,
+    Range,
+    Join,
+    Keys,
+    Values,
+    Entries,
+    Has,
+    Delete,
+    Merge,
+    FromEntries,
+    Split,
+    Trim,
+    Upper,
+    Lower,
+    Replace,
+    StartsWith,
+    EndsWith,
+    Substr,
+    Chars,
+    Repeat,
+    ToInt,
+    ToString,
+    Format,
 }
 
 impl Builtin {
+    pub const ALL: [Builtin; 40] = [
+        Builtin::Len,
+        Builtin::Puts,
+        Builtin::First,
+        Builtin::Last,
+        Builtin::Rest,
+        Builtin::Push,
+        Builtin::JsonParse,
+        Builtin::JsonStringify,
+        Builtin::Map,
+        Builtin::Filter,
+        Builtin::Reduce,
+        Builtin::Sort,
+        Builtin::Reverse,
+        Builtin::Slice,
+        Builtin::Concat,
+        Builtin::Contains,
+        Builtin::IndexOf,
+        Builtin::Zip,
+        Builtin::Range,
+        Builtin::Join,
+        Builtin::Keys,
+        Builtin::Values,
+        Builtin::Entries,
+        Builtin::Has,
+        Builtin::Delete,
+        Builtin::Merge,
+        Builtin::FromEntries,
+        Builtin::Split,
+        Builtin::Trim,
+        Builtin::Upper,
+        Builtin::Lower,
+        Builtin::Replace,
+        Builtin::StartsWith,
+        Builtin::EndsWith,
+        Builtin::Substr,
+        Builtin::Chars,
+        Builtin::Repeat,
+        Builtin::ToInt,
+        Builtin::ToString,
+        Builtin::Format,
+    ];
+
     pub fn lookup(name: &str) -> Option<Object> {
-        match name {
-            "len" => Some(Object::Builtin(Builtin::Len)),
-            "first" => Some(Object::Builtin(Builtin::First)),
-            "last" => Some(Object::Builtin(Builtin::Last)),
-            "rest" => Some(Object::Builtin(Builtin::Rest)),
-            "push" => Some(Object::Builtin(Builtin::Push)),
-            "puts" => Some(Object::Builtin(Builtin::Puts)),
-            _ => None,
+        Self::ALL
+            .iter()
+            .find(|b| b.name() == name)
+            .map(|b| Object::Builtin(*b))
+    }
+
+    /// The name the builtin is bound to in every environment.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Len => "len",
+            Builtin::First => "first",
+            Builtin::Last => "last",
+            Builtin::Rest => "rest",
+            Builtin::Push => "push",
+            Builtin::Puts => "puts",
+            Builtin::JsonParse => "json_parse",
+            Builtin::JsonStringify => "json_stringify",
+            Builtin::Map => "map",
+            Builtin::Filter => "filter",
+            Builtin::Reduce => "reduce",
+            Builtin::Sort => "sort",
+            Builtin::Reverse => "reverse",
+            Builtin::Slice => "slice",
+            Builtin::Concat => "concat",
+            Builtin::Contains => "contains",
+            Builtin::IndexOf => "index_of",
+            Builtin::Zip => "zip",
+            Builtin::Range => "range",
+            Builtin::Join => "join",
+            Builtin::Keys => "keys",
+            Builtin::Values => "values",
+            Builtin::Entries => "entries",
+            Builtin::Has => "has",
+            Builtin::Delete => "delete",
+            Builtin::Merge => "merge",
+            Builtin::FromEntries => "from_entries",
+            Builtin::Split => "split",
+            Builtin::Trim => "trim",
+            Builtin::Upper => "upper",
+            Builtin::Lower => "lower",
+            Builtin::Replace => "replace",
+            Builtin::StartsWith => "starts_with",
+            Builtin::EndsWith => "ends_with",
+            Builtin::Substr => "substr",
+            Builtin::Chars => "chars",
+            Builtin::Repeat => "repeat",
+            Builtin::ToInt => "to_int",
+            Builtin::ToString => "to_string",
+            Builtin::Format => "format",
         }
     }
 
-    pub fn apply(&self, args: &Vec<Rc<Object>>) -> Result<Rc<Object>, String> {
+    pub fn apply(&self, ctx: &Context, args: &Vec<Rc<Object>>) -> EvalResult {
         match self {
             Builtin::Len => {
                 if args.len() != 1 {
-                    return Err("len takes only 1 array or string argument".to_string());
+                    return Err(EvalError::new("len takes only 1 array or string argument"));
                 }
 
                 let arg = &*Rc::clone(args.first().unwrap());
                 match arg {
-                    Object::String(s) => Ok(Rc::new(Object::Int(s.len() as i64))),
+                    Object::String(s) => Ok(Rc::new(Object::Int(s.chars().count() as i64))),
                     Object::Array(a) => Ok(Rc::new(Object::Int(a.elements.len() as i64))),
-                    obj => Err(format!(
+                    obj => Err(EvalError::new(format!(
                         "object {:?} not supported as an argument for len",
                         obj
-                    )),
+                    ))),
                 }
             }
             Builtin::First => {
                 if args.len() != 1 {
-                    return Err("first takes only 1 array argument".to_string());
+                    return Err(EvalError::new("first takes only 1 array argument"));
                 }
 
                 let arg = &*Rc::clone(args.first().unwrap());
@@ -157,15 +347,15 @@ impl Builtin {
                         Some(el) => Ok(Rc::clone(el)),
                         None => Ok(Rc::new(Object::Null)),
                     },
-                    obj => Err(format!(
+                    obj => Err(EvalError::new(format!(
                         "object {:?} not supported as an argument for first",
                         obj
-                    )),
+                    ))),
                 }
             }
             Builtin::Last => {
                 if args.len() != 1 {
-                    return Err("last takes only 1 array argument".to_string());
+                    return Err(EvalError::new("last takes only 1 array argument"));
                 }
 
                 let arg = &*Rc::clone(args.first().unwrap());
@@ -174,15 +364,15 @@ impl Builtin {
                         Some(el) => Ok(Rc::clone(el)),
                         None => Ok(Rc::new(Object::Null)),
                     },
-                    obj => Err(format!(
+                    obj => Err(EvalError::new(format!(
                         "object {:?} not supported as an argument for last",
                         obj
-                    )),
+                    ))),
                 }
             }
             Builtin::Rest => {
                 if args.len() != 1 {
-                    return Err("rest takes only 1 array argument".to_string());
+                    return Err(EvalError::new("rest takes only 1 array argument"));
                 }
 
                 let arg = &*Rc::clone(args.first().unwrap());
@@ -196,15 +386,15 @@ impl Builtin {
                             Ok(Rc::new(Object::Array(Rc::new(Array { elements }))))
                         }
                     }
-                    obj => Err(format!(
+                    obj => Err(EvalError::new(format!(
                         "object {:?} is not supported as an argument for rest",
                         obj
-                    )),
+                    ))),
                 }
             }
             Builtin::Push => {
                 if args.len() != 2 {
-                    return Err("push takes an array and an object".to_string());
+                    return Err(EvalError::new("push takes an array and an object"));
                 }
 
                 let array = &*Rc::clone(args.first().unwrap());
@@ -217,15 +407,90 @@ impl Builtin {
                         elements.push(obj);
                         Ok(Rc::new(Object::Array(Rc::new(Array { elements }))))
                     }
-                    _ => Err("first argument to push must be an array".to_string()),
+                    _ => Err(EvalError::new("first argument to push must be an array")),
                 }
             }
             Builtin::Puts => {
                 for arg in args {
-                    println!("{}", arg.inspect())
+                    ctx.write_line(&arg.inspect())
+                        .map_err(|e| EvalError::new(format!("puts: {}", e)))?;
                 }
                 Ok(Rc::new(Object::Null))
             }
+            Builtin::JsonParse => {
+                if args.len() != 1 {
+                    return Err(EvalError::new("json_parse takes only 1 string argument"));
+                }
+
+                match &**args.first().unwrap() {
+                    Object::String(s) => match json::parse(s) {
+                        Ok(obj) => Ok(Rc::new(obj)),
+                        Err(err) => Err(EvalError::new(format!("json_parse: {}", err))),
+                    },
+                    obj => Err(EvalError::new(format!(
+                        "object {:?} not supported as an argument for json_parse",
+                        obj
+                    ))),
+                }
+            }
+            Builtin::JsonStringify => {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(EvalError::new(
+                        "json_stringify takes an object and an optional indent",
+                    ));
+                }
+
+                let indent = match args.get(1).map(|a| &**a) {
+                    None => 0,
+                    Some(Object::Int(i)) if (0..=json::MAX_INDENT as i64).contains(i) => {
+                        *i as usize
+                    }
+                    Some(obj) => {
+                        return Err(EvalError::new(format!(
+                            "indent {} for json_stringify must be an integer from 0 to {}",
+                            obj.inspect(),
+                            json::MAX_INDENT
+                        )))
+                    }
+                };
+                let s = json::stringify(args.first().unwrap(), indent, ctx)?;
+                Ok(Rc::new(Object::String(s)))
+            }
+            Builtin::Map => array::map(ctx, args),
+            Builtin::Filter => array::filter(ctx, args),
+            Builtin::Reduce => array::reduce(ctx, args),
+            Builtin::Sort => array::sort(ctx, args),
+            Builtin::Reverse => array::reverse(args),
+            Builtin::Slice => array::slice(args),
+            Builtin::Concat => array::concat(ctx, args),
+            Builtin::Contains => match args.first().map(|a| &**a) {
+                Some(Object::String(_)) => string::contains(args),
+                _ => array::contains(args),
+            },
+            Builtin::IndexOf => array::index_of(args),
+            Builtin::Zip => array::zip(args),
+            Builtin::Range => array::range(ctx, args),
+            Builtin::Join => array::join(ctx, args),
+            Builtin::Keys => hash::keys(args),
+            Builtin::Values => hash::values(args),
+            Builtin::Entries => hash::entries(args),
+            Builtin::Has => hash::has(args),
+            Builtin::Delete => hash::delete(args),
+            Builtin::Merge => hash::merge(args),
+            Builtin::FromEntries => hash::from_entries(args),
+            Builtin::Split => string::split(args),
+            Builtin::Trim => string::trim(args),
+            Builtin::Upper => string::upper(args),
+            Builtin::Lower => string::lower(args),
+            Builtin::Replace => string::replace(ctx, args),
+            Builtin::StartsWith => string::starts_with(args),
+            Builtin::EndsWith => string::ends_with(args),
+            Builtin::Substr => string::substr(args),
+            Builtin::Chars => string::chars(args),
+            Builtin::Repeat => string::repeat(ctx, args),
+            Builtin::ToInt => string::to_int(args),
+            Builtin::ToString => string::to_string(args),
+            Builtin::Format => string::format(ctx, args),
         }
     }
 
@@ -234,17 +499,28 @@ impl Builtin {
     }
 
     fn inspect(&self) -> String {
-        match self {
-            Builtin::Len => "len".to_string(),
-            Builtin::First => "first".to_string(),
-            Builtin::Last => "last".to_string(),
-            Builtin::Rest => "rest".to_string(),
-            Builtin::Push => "push".to_string(),
-            Builtin::Puts => "puts".to_string(),
-        }
+        self.name().to_string()
     }
 }
 
+/// Checks that the builtin `name` got from `min` to `max` arguments.
+fn check_arity(name: &str, args: &[Rc<Object>], min: usize, max: usize) -> Result<(), EvalError> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let want = if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(EvalError::new(format!(
+        "wrong number of arguments to {}: want={}, got={}",
+        name,
+        want,
+        args.len()
+    )))
+}
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct Array {
     pub elements: Vec<Rc<Object>>,
@@ -252,41 +528,63 @@ pub struct Array {
 
 impl Array {
     fn inspect(&self) -> String {
-        let elements: Vec<String> = (&self.elements)
-            .into_iter()
-            .map(|e| e.to_string())
-            .collect();
+        let elements: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
         format!("[{}]", elements.join(", "))
     }
 }
 
-impl Hash for Array {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        // we should never hash an array so should be fine
-        panic!("hash for array not supported");
+/// The values that can be used as keys of a `MonkeyHash`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum HashKey {
+    Int(i64),
+    Bool(bool),
+    String(String),
+}
+
+impl HashKey {
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Int(i) => Object::Int(*i),
+            HashKey::Bool(b) => Object::Bool(*b),
+            HashKey::String(s) => Object::String(s.clone()),
+        }
+    }
+}
+
+impl TryFrom<&Object> for HashKey {
+    type Error = String;
+
+    fn try_from(obj: &Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Int(i) => Ok(HashKey::Int(*i)),
+            Object::Bool(b) => Ok(HashKey::Bool(*b)),
+            Object::String(s) => Ok(HashKey::String(s.clone())),
+            _ => Err(format!("unusable as hash key: {}", obj)),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_object())
     }
 }
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct MonkeyHash {
-    pub pairs: HashMap<Rc<Object>, Rc<Object>>,
+    // insertion ordered, so inspect() and iteration are stable between runs
+    pub pairs: IndexMap<HashKey, Rc<Object>>,
 }
 impl MonkeyHash {
     fn inspect(&self) -> String {
-        let pairs: Vec<String> = (&self.pairs)
-            .into_iter()
-            .map(|(key, value)| format!("{}: {}", key.inspect(), value.inspect()))
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value.inspect()))
             .collect();
         format!("{{{}}}", pairs.join(", "))
     }
 }
-impl Hash for MonkeyHash {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        // should never happen
-        panic!("hash not implmented for monkey hash");
-    }
-}
-
 impl Object {
     pub fn inspect(&self) -> String {
         match self {
@@ -294,10 +592,13 @@ impl Object {
             Object::Bool(b) => b.to_string(),
             Object::String(s) => s.clone(),
             Object::Return(r) => r.value.inspect(),
-            Object::Function(f) => f.inspect(),
+            Object::Function(f) => f.inspect("fn"),
             Object::Builtin(b) => b.inspect(),
+            Object::Native(n) => n.name.clone(),
             Object::Array(a) => a.inspect(),
             Object::Hash(h) => h.inspect(),
+            Object::Quote(exp) => format!("QUOTE({})", exp),
+            Object::Macro(m) => m.inspect("macro"),
             Object::Null => String::from("null"),
             // Object::CompiledFunction(f) => f.inspect(),
             // Object::Closure(c) => c.inspect(),
@@ -0,0 +1,193 @@
+//! The string builtins. Positions and lengths count characters, not bytes.
+
+use std::rc::Rc;
+
+use super::{
+    array::{integer, new_array, position},
+    check_arity, Object,
+};
+use crate::evaluator::{Context, EvalError, EvalResult};
+
+fn new_string(s: String) -> EvalResult {
+    Ok(Rc::new(Object::String(s)))
+}
+
+fn new_bool(b: bool) -> EvalResult {
+    Ok(Rc::new(Object::Bool(b)))
+}
+
+/// The string argument `obj` of the builtin `name`.
+fn string<'a>(name: &str, obj: &'a Object) -> Result<&'a str, EvalError> {
+    match obj {
+        Object::String(s) => Ok(s),
+        obj => Err(EvalError::new(format!(
+            "argument to {} must be a string, got {}",
+            name, obj
+        ))),
+    }
+}
+
+/// `split(s, separator)`, or `split(s)` at runs of whitespace. An empty separator splits the
+/// string into its characters.
+pub(super) fn split(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("split", args, 1, 2)?;
+    let s = string("split", &args[0])?;
+    let parts: Vec<&str> = match args.get(1) {
+        None => s.split_whitespace().collect(),
+        Some(separator) => match string("split", separator)? {
+            "" => return chars(&args[..1]),
+            separator => s.split(separator).collect(),
+        },
+    };
+    new_array(
+        parts
+            .into_iter()
+            .map(|part| Rc::new(Object::String(part.to_string())))
+            .collect(),
+    )
+}
+
+pub(super) fn trim(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("trim", args, 1, 1)?;
+    new_string(string("trim", &args[0])?.trim().to_string())
+}
+
+pub(super) fn upper(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("upper", args, 1, 1)?;
+    new_string(string("upper", &args[0])?.to_uppercase())
+}
+
+pub(super) fn lower(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("lower", args, 1, 1)?;
+    new_string(string("lower", &args[0])?.to_lowercase())
+}
+
+/// `replace(s, from, to)` replaces every occurrence of `from`.
+pub(super) fn replace(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("replace", args, 3, 3)?;
+    let s = string("replace", &args[0])?;
+    let (from, to) = (string("replace", &args[1])?, string("replace", &args[2])?);
+    let matches = s.matches(from).count();
+    let len = (s.len() - matches * from.len()).saturating_add(matches.saturating_mul(to.len()));
+    ctx.check_allocation(len)?;
+    new_string(s.replace(from, to))
+}
+
+pub(super) fn starts_with(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("starts_with", args, 2, 2)?;
+    let s = string("starts_with", &args[0])?;
+    new_bool(s.starts_with(string("starts_with", &args[1])?))
+}
+
+pub(super) fn ends_with(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("ends_with", args, 2, 2)?;
+    let s = string("ends_with", &args[0])?;
+    new_bool(s.ends_with(string("ends_with", &args[1])?))
+}
+
+pub(super) fn contains(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("contains", args, 2, 2)?;
+    let s = string("contains", &args[0])?;
+    new_bool(s.contains(string("contains", &args[1])?))
+}
+
+/// `substr(s, start, length)`, `length` defaulting to the rest of the string. A negative `start`
+/// counts from the end.
+pub(super) fn substr(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("substr", args, 2, 3)?;
+    let s = string("substr", &args[0])?;
+    let start = position("substr", &args[1], s.chars().count())?;
+    let len = match args.get(2) {
+        Some(len) => integer("substr", len)?.max(0) as usize,
+        None => usize::MAX,
+    };
+    new_string(s.chars().skip(start).take(len).collect())
+}
+
+pub(super) fn chars(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("chars", args, 1, 1)?;
+    let chars = string("chars", &args[0])?
+        .chars()
+        .map(|c| Rc::new(Object::String(c.to_string())))
+        .collect();
+    new_array(chars)
+}
+
+pub(super) fn repeat(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    check_arity("repeat", args, 2, 2)?;
+    let s = string("repeat", &args[0])?;
+    let n = integer("repeat", &args[1])?;
+    if n < 0 {
+        return Err(EvalError::new(format!(
+            "count for repeat must not be negative, got {}",
+            n
+        )));
+    }
+    let n = n as usize;
+    ctx.check_allocation(s.len().saturating_mul(n))?;
+    new_string(s.repeat(n))
+}
+
+/// The integer a string spells, ignoring surrounding whitespace, or null if it spells none.
+pub(super) fn to_int(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("to_int", args, 1, 1)?;
+    match &*args[0] {
+        Object::Int(_) => Ok(Rc::clone(&args[0])),
+        obj => match string("to_int", obj)?.trim().parse() {
+            Ok(i) => Ok(Rc::new(Object::Int(i))),
+            Err(_) => Ok(Rc::new(Object::Null)),
+        },
+    }
+}
+
+/// Any value as `puts` prints it.
+pub(super) fn to_string(args: &[Rc<Object>]) -> EvalResult {
+    check_arity("to_string", args, 1, 1)?;
+    new_string(args[0].inspect())
+}
+
+/// `format(template, values...)` replaces each `{}` of the template by the next value as `puts`
+/// prints it. `{{` and `}}` stand for literal braces.
+pub(super) fn format(ctx: &Context, args: &[Rc<Object>]) -> EvalResult {
+    let template = match args.first() {
+        Some(template) => string("format", template)?,
+        None => {
+            return Err(EvalError::new(
+                "wrong number of arguments to format: want=1 or more, got=0",
+            ))
+        }
+    };
+    let mut values = args[1..].iter();
+    let mut placeholders = 0;
+    let mut formatted = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(value) = values.next() {
+                    let value = value.inspect();
+                    ctx.check_allocation(formatted.len().saturating_add(value.len()))?;
+                    formatted.push_str(&value);
+                }
+            }
+            ('{', _) | ('}', _) => {
+                return Err(EvalError::new(format!("unmatched {} in format string", c)))
+            }
+            _ => formatted.push(c),
+        }
+    }
+    if placeholders != args.len() - 1 {
+        return Err(EvalError::new(format!(
+            "format string has {} placeholders but got {} values",
+            placeholders,
+            args.len() - 1
+        )));
+    }
+    new_string(formatted)
+}
@@ -0,0 +1,412 @@
+//! Optional AST optimizations run between parsing and evaluation.
+//!
+//! Every pass keeps the result of a program identical to evaluating it unoptimized. Folding
+//! reuses the evaluator's own operators and leaves expressions that would fail, like `1 / 0`,
+//! for the evaluator to report.
+
+use std::{collections::HashMap, mem, rc::Rc};
+
+use crate::{
+    evaluator::{eval_infix_expression, eval_prefix_expression, is_truthy},
+    lexer::token::Span,
+    object::Object,
+    parser::node::{
+        Alternative, BlockStatement, BooleanLiteral, Expression, FunctionLiteral,
+        IdentifierExpression, IfExpression, IntegerLiteral, MatchArm, Pattern, Program, Statement,
+        StringLiteral,
+    },
+    resolver::{arm_bindings, parameter_names, scope_bindings},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The passes to run, all enabled by default.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Passes {
+    /// Evaluates prefix and infix operators applied to literals, e.g. `2 * 60` to `120`.
+    pub fold_constants: bool,
+    /// Replaces `if` expressions with a literal condition by the branch taken.
+    pub eliminate_dead_branches: bool,
+    /// Replaces reads of a `let` bound to a literal by the literal.
+    pub inline_constants: bool,
+}
+
+impl Default for Passes {
+    fn default() -> Self {
+        Passes {
+            fold_constants: true,
+            eliminate_dead_branches: true,
+            inline_constants: true,
+        }
+    }
+}
+
+pub fn optimize(program: &mut Program) {
+    optimize_with(program, Passes::default())
+}
+
+pub fn optimize_with(program: &mut Program, passes: Passes) {
+    let mut optimizer = Optimizer {
+        passes,
+        scopes: Vec::new(),
+    };
+    optimizer.enter_scope(&[], scope_bindings(&program.body), false);
+    optimizer.optimize_statements(&mut program.body, true);
+}
+
+struct Scope {
+    // how many times each name of the scope is bound, parameters included
+    bindings: HashMap<String, usize>,
+    constants: HashMap<String, Object>,
+    // set for the scope of a `match` arm, which runs as soon as it is entered
+    arm: bool,
+}
+
+struct Optimizer {
+    passes: Passes,
+    scopes: Vec<Scope>,
+}
+
+impl Optimizer {
+    fn enter_scope(
+        &mut self,
+        parameters: &[IdentifierExpression],
+        names: Vec<IdentifierExpression>,
+        arm: bool,
+    ) {
+        let mut bindings = HashMap::new();
+        for ident in parameters.iter().cloned().chain(names) {
+            *bindings.entry(ident.name).or_insert(0) += 1;
+        }
+        self.scopes.push(Scope {
+            bindings,
+            constants: HashMap::new(),
+            arm,
+        });
+    }
+
+    fn constant(&self, name: &str) -> Option<&Object> {
+        let mut in_function = false;
+        for (i, scope) in self.scopes.iter().enumerate().rev() {
+            if !scope.bindings.contains_key(name) {
+                in_function |= !scope.arm;
+                continue;
+            }
+            // later evaluations in the same environment may rebind a global before a function
+            // reading it is called, so globals are only inlined outside of functions
+            if i == 0 && in_function {
+                return None;
+            }
+            return scope.constants.get(name);
+        }
+        None
+    }
+
+    /// Optimizes a list of statements; `top` is set when it is the body of a scope rather than
+    /// a block that may not run.
+    fn optimize_statements(&mut self, stmts: &mut Vec<Statement>, top: bool) {
+        let count = stmts.len();
+        let mut optimized = Vec::with_capacity(count);
+        for (i, mut stmt) in mem::take(stmts).into_iter().enumerate() {
+            self.optimize_statement(&mut stmt, top);
+            if self.passes.eliminate_dead_branches {
+                if let Some(taken) = taken_branch(&mut stmt) {
+                    match taken {
+                        // the branch's statements run in the same scope anyway
+                        Some(block) if !block.statements.is_empty() => {
+                            optimized.append(&mut block.statements);
+                            continue;
+                        }
+                        // the value of the last statement is the value of the list
+                        None if i + 1 < count => continue,
+                        _ => {}
+                    }
+                }
+            }
+            optimized.push(stmt);
+        }
+        *stmts = optimized;
+    }
+
+    fn optimize_statement(&mut self, stmt: &mut Statement, top: bool) {
+        match stmt {
+            Statement::Let(stmt) => {
+                self.optimize_expression(&mut stmt.value);
+                let scope = self.scopes.last_mut().unwrap();
+                if let Pattern::Identifier(ident) = &stmt.pattern {
+                    if self.passes.inline_constants
+                        && top
+                        && scope.bindings.get(&ident.name) == Some(&1)
+                    {
+                        if let Some(value) = literal_object(&stmt.value) {
+                            scope.constants.insert(ident.name.clone(), value);
+                        }
+                    }
+                }
+            }
+            Statement::Return(ret) => self.optimize_expression(&mut ret.value),
+            Statement::Expression(exp) => self.optimize_expression(&mut exp.expression),
+        }
+    }
+
+    fn optimize_block(&mut self, block: &mut BlockStatement) {
+        self.optimize_statements(&mut block.statements, false);
+    }
+
+    fn optimize_expression(&mut self, exp: &mut Expression) {
+        match exp {
+            Expression::Integer(_)
+            | Expression::String(_)
+            | Expression::Boolean(_)
+            | Expression::Import(_) => {}
+            Expression::Identifier(ident) => {
+                if self.passes.inline_constants {
+                    if let Some(value) = self.constant(&ident.name) {
+                        *exp = object_literal(value, ident.span).unwrap();
+                    }
+                }
+            }
+            Expression::Prefix(prefix) => {
+                self.optimize_expression(&mut prefix.right);
+                if self.passes.fold_constants {
+                    if let Some(right) = literal_object(&prefix.right) {
+                        if let Ok(obj) = eval_prefix_expression(&prefix.operator, Rc::new(right)) {
+                            if let Some(folded) = object_literal(&obj, prefix.span) {
+                                *exp = folded;
+                            }
+                        }
+                    }
+                }
+            }
+            Expression::Infix(infix) => {
+                self.optimize_expression(&mut infix.left);
+                self.optimize_expression(&mut infix.right);
+                if self.passes.fold_constants {
+                    if let (Some(left), Some(right)) =
+                        (literal_object(&infix.left), literal_object(&infix.right))
+                    {
+                        let result =
+                            eval_infix_expression(&infix.operator, Rc::new(left), Rc::new(right));
+                        let folded = result.ok().and_then(|obj| object_literal(&obj, infix.span));
+                        if let Some(folded) = folded {
+                            *exp = folded;
+                        }
+                    }
+                }
+            }
+            Expression::If(if_exp) => {
+                self.optimize_if(if_exp);
+                if self.passes.eliminate_dead_branches {
+                    if let Some(folded) = eliminate_dead_branch(if_exp) {
+                        *exp = folded;
+                    }
+                }
+            }
+            Expression::Ternary(ternary) => {
+                self.optimize_expression(&mut ternary.condition);
+                self.optimize_expression(&mut ternary.consequence);
+                self.optimize_expression(&mut ternary.alternative);
+                if self.passes.eliminate_dead_branches {
+                    if let Some(condition) = literal_object(&ternary.condition) {
+                        let branch = match is_truthy(&condition) {
+                            true => &mut ternary.consequence,
+                            false => &mut ternary.alternative,
+                        };
+                        *exp = mem::replace(branch, Expression::from(false));
+                    }
+                }
+            }
+            Expression::Function(func) => self.optimize_function(func),
+            Expression::Call(call) => {
+                self.optimize_expression(&mut call.function);
+                for arg in &mut call.arguments {
+                    self.optimize_expression(arg);
+                }
+            }
+            Expression::Array(array) => {
+                for e in &mut array.elements {
+                    self.optimize_expression(e);
+                }
+            }
+            Expression::Index(index) => {
+                self.optimize_expression(&mut index.left);
+                self.optimize_expression(&mut index.index);
+            }
+            Expression::Match(exp) => {
+                self.optimize_expression(&mut exp.value);
+                for arm in &mut exp.arms {
+                    self.optimize_match_arm(arm);
+                }
+            }
+            Expression::Slice(slice) => {
+                self.optimize_expression(&mut slice.left);
+                for bound in slice.start.iter_mut().chain(&mut slice.end) {
+                    self.optimize_expression(bound);
+                }
+            }
+            Expression::Hash(hash) => {
+                for (key, value) in &mut hash.pairs {
+                    self.optimize_expression(key);
+                    self.optimize_expression(value);
+                }
+            }
+            Expression::Interpolation(interpolation) => {
+                for part in &mut interpolation.parts {
+                    self.optimize_expression(part);
+                }
+                if self.passes.fold_constants {
+                    let parts: Option<Vec<Object>> =
+                        interpolation.parts.iter().map(literal_object).collect();
+                    if let Some(parts) = parts {
+                        let value = parts.iter().map(Object::inspect).collect();
+                        *exp = Expression::String(StringLiteral {
+                            value,
+                            span: interpolation.span,
+                        });
+                    }
+                }
+            }
+            Expression::Unquote(exp) => self.optimize_expression(&mut exp.expression),
+            // quoted code is a value of the program, and macros run before the optimizer
+            Expression::Quote(_) | Expression::Macro(_) => {}
+        }
+    }
+
+    fn optimize_if(&mut self, if_exp: &mut IfExpression) {
+        self.optimize_expression(&mut if_exp.condition);
+        self.optimize_block(&mut if_exp.consequence);
+        match &mut if_exp.alternative {
+            Some(Alternative::Block(alt)) => self.optimize_block(alt),
+            Some(Alternative::If(alt)) => self.optimize_if(alt),
+            None => {}
+        }
+        if self.passes.eliminate_dead_branches {
+            eliminate_dead_else_if(&mut if_exp.alternative);
+        }
+    }
+
+    fn optimize_function(&mut self, func: &mut FunctionLiteral) {
+        let parameters = parameter_names(&func.parameters);
+        self.enter_scope(&parameters, scope_bindings(&func.body.statements), false);
+        self.optimize_statements(&mut func.body.statements, true);
+        self.scopes.pop();
+    }
+
+    fn optimize_match_arm(&mut self, arm: &mut MatchArm) {
+        let names = arm
+            .pattern
+            .identifiers()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.enter_scope(&names, arm_bindings(arm), true);
+        if let Some(guard) = &mut arm.guard {
+            self.optimize_expression(guard);
+        }
+        self.optimize_expression(&mut arm.body);
+        self.scopes.pop();
+    }
+}
+
+fn literal_object(exp: &Expression) -> Option<Object> {
+    match exp {
+        Expression::Integer(lit) => Some(Object::Int(lit.value)),
+        Expression::Boolean(lit) => Some(Object::Bool(lit.value)),
+        Expression::String(lit) => Some(Object::String(lit.value.clone())),
+        _ => None,
+    }
+}
+
+/// The literal for `obj`, spanning the source of the expression it replaces.
+fn object_literal(obj: &Object, span: Span) -> Option<Expression> {
+    match obj {
+        Object::Int(value) => Some(Expression::Integer(IntegerLiteral {
+            value: *value,
+            span,
+        })),
+        Object::Bool(value) => Some(Expression::Boolean(BooleanLiteral {
+            value: *value,
+            span,
+        })),
+        Object::String(value) => Some(Expression::String(StringLiteral {
+            value: value.clone(),
+            span,
+        })),
+        _ => None,
+    }
+}
+
+/// Reduces an `if` with a literal condition to the branch taken: its only expression if it has
+/// one, otherwise an `if (true)` or an empty `if (false)`, which `taken_branch` recognizes.
+fn eliminate_dead_branch(if_exp: &mut IfExpression) -> Option<Expression> {
+    let truthy = is_truthy(&literal_object(&if_exp.condition)?);
+    let span = if_exp.span;
+    let empty = BlockStatement {
+        statements: vec![],
+        span,
+    };
+    let branch = if truthy {
+        Some(mem::replace(&mut if_exp.consequence, empty.clone()))
+    } else {
+        match if_exp.alternative.take() {
+            Some(Alternative::Block(alt)) => Some(alt),
+            Some(Alternative::If(alt)) => return Some(Expression::If(alt)),
+            None => None,
+        }
+    };
+    if let Some(BlockStatement { statements, .. }) = &branch {
+        if let [Statement::Expression(exp)] = statements.as_slice() {
+            return Some(exp.expression.clone());
+        }
+    }
+    *if_exp = IfExpression {
+        condition: Expression::Boolean(BooleanLiteral {
+            value: branch.is_some(),
+            span: if_exp.condition.span(),
+        }),
+        consequence: branch.unwrap_or(empty),
+        alternative: None,
+        span,
+    };
+    None
+}
+
+/// Replaces an `else if` with a literal condition by the branch it takes.
+fn eliminate_dead_else_if(alternative: &mut Option<Alternative>) {
+    let truthy = match alternative {
+        Some(Alternative::If(alt)) => match literal_object(&alt.condition) {
+            Some(condition) => is_truthy(&condition),
+            None => return,
+        },
+        _ => return,
+    };
+    if let Some(Alternative::If(alt)) = alternative.take() {
+        *alternative = match truthy {
+            true => Some(Alternative::Block(alt.consequence)),
+            false => alt.alternative,
+        };
+    }
+}
+
+/// The statements that run for an `if` statement reduced by `eliminate_dead_branch`.
+fn taken_branch(stmt: &mut Statement) -> Option<Option<&mut BlockStatement>> {
+    let if_exp = match stmt {
+        Statement::Expression(exp) => match &mut exp.expression {
+            Expression::If(if_exp) if if_exp.alternative.is_none() => if_exp,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match if_exp.condition {
+        Expression::Boolean(BooleanLiteral { value: true, .. }) => {
+            Some(Some(&mut if_exp.consequence))
+        }
+        Expression::Boolean(BooleanLiteral { value: false, .. })
+            if if_exp.consequence.statements.is_empty() =>
+        {
+            Some(None)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,150 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::*;
+use crate::{evaluator, lexer::Lexer, object::Environment, parser::node::Node, parser::Parser};
+
+fn setup(input: &str, passes: Passes) -> Program {
+    let mut program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+    optimize_with(&mut program, passes);
+    program
+}
+
+fn eval(program: Program) -> String {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    match evaluator::eval(&Node::Program(Box::new(program)), env) {
+        Ok(obj) => obj.inspect(),
+        Err(err) => format!("error: {}", err),
+    }
+}
+
+#[test]
+fn test_constant_folding() {
+    let passes = Passes {
+        fold_constants: true,
+        eliminate_dead_branches: false,
+        inline_constants: false,
+    };
+    let tests = [
+        ("2 * 60 * 60", "7200"),
+        ("-(1 + 2)", "-3"),
+        ("!true == false", "true"),
+        ("1 < 2 == true", "true"),
+        (r#""a" + "b" == "ab""#, "true"),
+        ("x * (2 + 3)", "(x * 5)"),
+        (r#""${1 + 1} and ${true}""#, "2 and true"),
+        (r#""${x + (1 + 1)}!""#, r#""${(x + 2)}!""#),
+        // left for the evaluator to report
+        ("1 / 0", "(1 / 0)"),
+        ("9223372036854775807 + 1", "(9223372036854775807 + 1)"),
+        ("true + 1", "(true + 1)"),
+    ];
+
+    for (input, expected) in tests.iter() {
+        assert_eq!(setup(input, passes).to_string(), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_dead_branch_elimination() {
+    let tests = [
+        ("if (true) { 1 } else { 2 }", "1"),
+        ("if (1 > 2) { 1 } else { 2 }", "2"),
+        ("if (false) { 1 }", "if false "),
+        ("let x = 1; if (false) { 1 }; x", "let x = 1;1"),
+        (
+            "let f = fn() { if (true) { let a = 1; a + 1 } }",
+            "let f = () let a = 1;(a + 1);",
+        ),
+        ("if (x) { 1 } else { 2 }", "if x 1 else 2"),
+        ("if false { 1 } else if x { 2 } else { 3 }", "if x 2 else 3"),
+        ("if x { 1 } else if 1 < 2 { 2 } else { 3 }", "if x 1 else 2"),
+        (
+            "if x { 1 } else if false { 2 } else if y { 3 }",
+            "if x 1 else if y 3",
+        ),
+        ("if x { 1 } else if false { 2 }", "if x 1"),
+        ("1 < 2 ? x : y", "x"),
+        ("x ? 1 : 2 > 3 ? 4 : 5", "(x ? 1 : 5)"),
+    ];
+
+    for (input, expected) in tests.iter() {
+        assert_eq!(
+            setup(input, Passes::default()).to_string(),
+            *expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_constant_inlining() {
+    let tests = [
+        (
+            "let f = fn(n) { let h = 60 * 60; n * h }",
+            "let f = (n) let h = 3600;(n * 3600);",
+        ),
+        // bound twice, or conditionally
+        (
+            "let f = fn() { let a = 1; let a = 2; a }",
+            "let f = () let a = 1;let a = 2;a;",
+        ),
+        (
+            "let f = fn(c) { let a = 1; if (c) { let b = 2; } b }",
+            "let f = (c) let a = 1;if c let b = 2;b;",
+        ),
+        // parameters and read before the binding
+        ("let f = fn(a) { let a = 1; a }", "let f = (a) let a = 1;a;"),
+        (
+            "let f = fn() { let g = fn() { a }; let a = 1; g() }",
+            "let f = () let g = () a;let a = 1;g();",
+        ),
+        // nested functions, unless they shadow the name
+        (
+            "let f = fn() { let a = 1; fn() { a }; fn(a) { a } }",
+            "let f = () let a = 1;() 1(a) a;",
+        ),
+        // globals only at the top level
+        (
+            "let a = 1; let f = fn() { a }; a",
+            "let a = 1;let f = () a;1",
+        ),
+    ];
+
+    for (input, expected) in tests.iter() {
+        assert_eq!(
+            setup(input, Passes::default()).to_string(),
+            *expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_semantics_preserved() {
+    let tests = [
+        "let secs = 2 * 60 * 60; secs / 60",
+        "let a = 5; let f = fn(x) { if (x > a) { x - a } else { a - x } }; [f(1), f(10)]",
+        "let f = fn() { if (true) { return 1; } 2 }; f()",
+        "let f = fn() { if (false) { return 1; } }; f()",
+        "let f = fn(c) { if (c) { let v = 1; } v }; f(false)",
+        "if (1) { let x = 10; } x * 2",
+        "let x = 1; if (false) { 1 }",
+        "-(-9223372036854775807 - 1)",
+        "let f = fn() { let g = fn() { a }; let r = g(); let a = 1; r }; f()",
+        r#"{"a" + "b": 1 + 1, 2 * 2: !true}"#,
+        "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10)",
+        "let f = fn(x) { if x > 1 { 1 } else if false { 2 } else if x < 0 { 3 } }; [f(2), f(-1), f(0)]",
+        "let f = fn(x) { x > 1 ? x : false ? 0 : -x }; [f(2), f(-3)]",
+        "let f = fn(v) { let x = 1; match (v + 1) { 2 => x, x if x > 2 => x * 2 } }; [f(1), f(5)]",
+        "let x = 1; [match (5) { x => x }, x]",
+    ];
+
+    for input in tests.iter() {
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let mut optimized = program.clone();
+        optimize(&mut optimized);
+        assert_eq!(eval(optimized), eval(program), "{}", input);
+    }
+}
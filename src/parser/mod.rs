@@ -1,9 +1,9 @@
-use std::collections::HashMap;
+use std::fmt;
 
 use crate::lexer::{
     keyword::Keyword,
     punctuator::Punctuator,
-    token::{Numeric, Token, TokenKind},
+    token::{Numeric, Span, TemplatePart, Token, TokenKind},
     Lexer,
 };
 
@@ -11,6 +11,7 @@ use self::node::{Expression, Node, Program, Statement};
 
 mod ast;
 pub mod node;
+pub mod visitor;
 
 #[cfg(test)]
 mod tests;
@@ -18,6 +19,7 @@ mod tests;
 #[derive(PartialEq, PartialOrd)]
 enum Precedence {
     Lowest,
+    Ternary,
     Equals,
     LessGreater,
     Sum,
@@ -29,6 +31,7 @@ enum Precedence {
 impl Precedence {
     fn token_precedence(tok: &TokenKind) -> Precedence {
         match tok {
+            TokenKind::Punctuator(Punctuator::Question) => Precedence::Ternary,
             TokenKind::Punctuator(Punctuator::Eq) => Precedence::Equals,
             TokenKind::Punctuator(Punctuator::NotEq) => Precedence::Equals,
             TokenKind::Punctuator(Punctuator::LessThan) => Precedence::LessGreater,
@@ -43,8 +46,29 @@ impl Precedence {
         }
     }
 }
-type ParseError = String;
-type ParseErrors = Vec<ParseError>;
+/// A syntax error, located at the token the parser gave up on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    fn new(message: String, token: &Token) -> Self {
+        ParseError {
+            message,
+            span: token.span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+pub type ParseErrors = Vec<ParseError>;
 type PrefixFn = fn(parser: &mut Parser<'_>) -> ParseResult<Expression>;
 type InfixFn = fn(parser: &mut Parser<'_>, left: Expression) -> ParseResult<Expression>;
 pub type ParseResult<T> = Result<T, ParseError>;
@@ -86,6 +110,7 @@ impl<'a> Parser<'a> {
                 | Punctuator::LessThan => Some(Parser::parse_infix_expression),
                 Punctuator::OpenParen => Some(Parser::parse_call_expression),
                 Punctuator::OpenBracket => Some(Parser::parse_index_expression),
+                Punctuator::Question => Some(Parser::parse_ternary_expression),
                 _ => None,
             },
             _ => None,
@@ -96,29 +121,59 @@ impl<'a> Parser<'a> {
         parser: &mut Parser<'_>,
         left: Expression,
     ) -> ParseResult<Expression> {
+        let start = left.span().start;
+        let colon = TokenKind::Punctuator(Punctuator::Colon);
+        let close = TokenKind::Punctuator(Punctuator::CloseBracket);
+        let lower = if parser.peek_token_is(&colon) {
+            None
+        } else {
+            parser.next_token();
+            Some(parser.parse_expression(Precedence::Lowest)?)
+        };
+        if !parser.peek_token_is(&colon) {
+            parser.expect_peek(&close)?;
+            if let Some(index) = lower {
+                return Ok(Expression::Index(Box::new(node::IndexExpression {
+                    left,
+                    index,
+                    span: parser.span_from(start),
+                })));
+            }
+        }
         parser.next_token();
-
-        let exp = node::IndexExpression {
-            left,
-            index: parser.parse_expression(Precedence::Lowest)?,
+        let upper = if parser.peek_token_is(&close) {
+            None
+        } else {
+            parser.next_token();
+            Some(parser.parse_expression(Precedence::Lowest)?)
         };
+        parser.expect_peek(&close)?;
 
-        parser.expect_peek(&TokenKind::Punctuator(Punctuator::CloseBracket))?;
-
-        Ok(Expression::Index(Box::new(exp)))
+        Ok(Expression::Slice(Box::new(node::SliceExpression {
+            left,
+            start: lower,
+            end: upper,
+            span: parser.span_from(start),
+        })))
     }
     fn prefix_fn(&mut self) -> Option<PrefixFn> {
         match self.cur_token.kind() {
             TokenKind::Identifier(_) => Some(Parser::parse_identifier),
             TokenKind::NumericLiteral(_) => Some(Parser::parse_integer_literal),
             TokenKind::StringLiteral(_) => Some(Parser::parse_string_literal),
+            TokenKind::TemplateString(_) => Some(Parser::parse_interpolation),
             TokenKind::Punctuator(Punctuator::Not) | TokenKind::Punctuator(Punctuator::Sub) => {
                 Some(Parser::parse_prefix_expression)
             }
             TokenKind::BooleanLiteral(_) => Some(Parser::parse_boolean),
             TokenKind::Punctuator(Punctuator::OpenParen) => Some(Parser::parse_grouped_expression),
             TokenKind::Keyword(Keyword::If) => Some(Parser::parse_if_expression),
+            TokenKind::Keyword(Keyword::Match) => Some(Parser::parse_match_expression),
             TokenKind::Keyword(Keyword::Function) => Some(Parser::parse_function_literal),
+            TokenKind::Keyword(Keyword::Macro) => Some(Parser::parse_macro_literal),
+            TokenKind::Keyword(Keyword::Quote) => Some(Parser::parse_quote_expression),
+            TokenKind::Keyword(Keyword::Unquote) => Some(Parser::parse_unquote_expression),
+            TokenKind::Keyword(Keyword::Import) => Some(Parser::parse_import_expression),
             TokenKind::Punctuator(Punctuator::OpenBracket) => Some(Parser::parse_array_literal),
             TokenKind::Punctuator(Punctuator::OpenBlock) => Some(Parser::parse_hash_literal),
             _ => None,
@@ -126,29 +181,36 @@ impl<'a> Parser<'a> {
     }
     fn parse_identifier(parser: &mut Parser<'_>) -> ParseResult<Expression> {
         if let TokenKind::Identifier(ref name) = parser.cur_token.kind() {
-            return Ok(Expression::Identifier(name.to_string()));
+            return Ok(Expression::Identifier(node::IdentifierExpression::new(
+                name,
+                parser.cur_token.span,
+            )));
         }
 
-        Err(format!(
-            "unexpected error on identifier parse with {}",
-            parser.cur_token
+        Err(ParseError::new(
+            format!(
+                "unexpected error on identifier parse with {}",
+                parser.cur_token
+            ),
+            &parser.cur_token,
         ))
     }
     fn peek_precedence(&self) -> Precedence {
-        Precedence::token_precedence(&self.peek_token.kind())
+        Precedence::token_precedence(self.peek_token.kind())
     }
     fn cur_precedence(&self) -> Precedence {
-        Precedence::token_precedence(&self.cur_token.kind())
+        Precedence::token_precedence(self.cur_token.kind())
     }
     fn parse_expression(&mut self, precedence: Precedence) -> ParseResult<Expression> {
         let mut left_exp: Expression;
         if let Some(f) = self.prefix_fn() {
             left_exp = f(self)?;
         } else {
-            return Err(format!(
-                "no prefix parse function for {} found",
-                self.cur_token
-            ));
+            let message = match self.cur_token.kind() {
+                TokenKind::UnterminatedInterpolation => "unterminated interpolation".to_string(),
+                _ => format!("no prefix parse function for {} found", self.cur_token),
+            };
+            return Err(ParseError::new(message, &self.cur_token));
         }
         while !self.peek_token_is(&TokenKind::Punctuator(Punctuator::Semicolon))
             && precedence < self.peek_precedence()
@@ -163,6 +225,10 @@ impl<'a> Parser<'a> {
         }
         Ok(left_exp)
     }
+    /// The span from `start` to the end of the current token, the last one of a node.
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.cur_token.span.end)
+    }
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
         self.peek_token = self.l.next_token();
@@ -177,15 +243,18 @@ impl<'a> Parser<'a> {
             };
             self.next_token();
         }
-        if errors.len() > 0 {
+        if !errors.is_empty() {
             return Err(errors);
         }
+        program.span = self.span_from(0);
 
         Ok(program)
     }
     fn parse_statement(&mut self) -> ParseResult<Statement> {
         match self.cur_token.kind {
-            TokenKind::Keyword(Keyword::Let) => self.parse_let_statement(),
+            TokenKind::Keyword(Keyword::Let) | TokenKind::Keyword(Keyword::Export) => {
+                self.parse_let_statement()
+            }
             TokenKind::Keyword(Keyword::Return) => self.parse_return_statement(),
             _ => self.parse_expression_statement(),
             // _ => Err(format!("invalid statement token {}", self.cur_token)),
@@ -198,43 +267,123 @@ impl<'a> Parser<'a> {
         self.cur_token.kind == *t
     }
     fn parse_let_statement(&mut self) -> ParseResult<Statement> {
-        // read ident
-        let name = self.expect_ident()?;
+        let start = self.cur_token.span.start;
+        let exported = self.cur_token_is(&TokenKind::Keyword(Keyword::Export));
+        if exported {
+            self.expect_peek(&TokenKind::Keyword(Keyword::Let))?;
+        }
+        self.next_token();
+        let pattern = self.parse_pattern(false)?;
         self.expect_peek(&TokenKind::punctuator(Punctuator::Assign))?;
         self.next_token();
         let value = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token_is(&TokenKind::punctuator(Punctuator::Semicolon)) {
             self.next_token();
         }
-        Ok(Statement::Let(Box::new(node::LetStatement { name, value })))
+        Ok(Statement::Let(Box::new(node::LetStatement {
+            pattern,
+            value,
+            exported,
+            span: self.span_from(start),
+        })))
     }
-    fn expect_ident(&mut self) -> Result<String, ParseError> {
-        let name = match self.peek_token.kind() {
-            TokenKind::Identifier(name) => name.to_string(),
-            _ => return Err(format!("invalid identifier {}", self.peek_token)),
-        };
-
-        self.next_token();
-        Ok(name)
+    /// Parses the pattern of a `let`, a parameter or, when `refutable`, a `match` arm starting at
+    /// the current token. Only the latter may contain literals, which a value can fail to match.
+    fn parse_pattern(&mut self, refutable: bool) -> ParseResult<node::Pattern> {
+        let start = self.cur_token.span.start;
+        match self.cur_token.kind() {
+            TokenKind::Identifier(name) if &**name == "_" => Ok(node::Pattern::Wildcard {
+                span: self.cur_token.span,
+            }),
+            TokenKind::Identifier(name) => Ok(node::Pattern::Identifier(
+                node::IdentifierExpression::new(name, self.cur_token.span),
+            )),
+            TokenKind::NumericLiteral(_) if refutable => {
+                Parser::parse_integer_literal(self).map(node::Pattern::Literal)
+            }
+            TokenKind::Punctuator(Punctuator::Sub)
+                if refutable && matches!(self.peek_token.kind(), TokenKind::NumericLiteral(_)) =>
+            {
+                Parser::parse_prefix_expression(self).map(node::Pattern::Literal)
+            }
+            TokenKind::StringLiteral(_) if refutable => {
+                Parser::parse_string_literal(self).map(node::Pattern::Literal)
+            }
+            TokenKind::BooleanLiteral(_) if refutable => {
+                Parser::parse_boolean(self).map(node::Pattern::Literal)
+            }
+            TokenKind::Punctuator(Punctuator::OpenBracket) => {
+                let close = TokenKind::Punctuator(Punctuator::CloseBracket);
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.peek_token_is(&close) {
+                    self.next_token();
+                    // the rest can only come last
+                    if self.cur_token_is(&TokenKind::Punctuator(Punctuator::Spread)) {
+                        self.next_token();
+                        rest = Some(self.parse_identifier_into_identifier_expression()?);
+                        break;
+                    }
+                    elements.push(self.parse_pattern(refutable)?);
+                    if !self.peek_token_is(&close) {
+                        self.expect_peek(&TokenKind::Punctuator(Punctuator::Comma))?;
+                    }
+                }
+                self.expect_peek(&close)?;
+                Ok(node::Pattern::Array(Box::new(node::ArrayPattern {
+                    elements,
+                    rest,
+                    span: self.span_from(start),
+                })))
+            }
+            TokenKind::Punctuator(Punctuator::OpenBlock) => {
+                let close = TokenKind::Punctuator(Punctuator::CloseBlock);
+                let mut pairs = Vec::new();
+                while !self.peek_token_is(&close) {
+                    self.next_token();
+                    let key = self.parse_expression(Precedence::Lowest)?;
+                    self.expect_peek(&TokenKind::Punctuator(Punctuator::Colon))?;
+                    self.next_token();
+                    pairs.push((key, self.parse_pattern(refutable)?));
+                    if !self.peek_token_is(&close) {
+                        self.expect_peek(&TokenKind::Punctuator(Punctuator::Comma))?;
+                    }
+                }
+                self.expect_peek(&close)?;
+                Ok(node::Pattern::Hash(Box::new(node::HashPattern {
+                    pairs,
+                    span: self.span_from(start),
+                })))
+            }
+            _ => Err(ParseError::new(
+                format!("invalid pattern {}", self.cur_token),
+                &self.cur_token,
+            )),
+        }
     }
     fn expect_peek(&mut self, kind: &TokenKind) -> ParseResult<()> {
         if self.peek_token_is(kind) {
             self.next_token();
             Ok(())
         } else {
-            let e = format!("expected token: {} got: {}", kind, self.cur_token);
-            Err(e)
+            let e = format!("expected token: {} got: {}", kind, self.peek_token);
+            Err(ParseError::new(e, &self.peek_token))
         }
     }
     fn parse_return_statement(&mut self) -> ParseResult<Statement> {
+        let start = self.cur_token.span.start;
         self.next_token();
         let value = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token_is(&TokenKind::punctuator(Punctuator::Semicolon)) {
             self.next_token();
         }
-        Ok(Statement::Return(Box::new(node::ReturnStatement { value })))
+        Ok(Statement::Return(Box::new(node::ReturnStatement {
+            value,
+            span: self.span_from(start),
+        })))
     }
     fn parse_expression_statement(&mut self) -> ParseResult<Statement> {
+        let start = self.cur_token.span.start;
         let expression = self.parse_expression(Precedence::Lowest)?;
 
         if self.peek_token_is(&TokenKind::Punctuator(Punctuator::Semicolon)) {
@@ -243,35 +392,87 @@ impl<'a> Parser<'a> {
 
         Ok(Statement::Expression(Box::new(node::ExpressionStatement {
             expression,
+            span: self.span_from(start),
         })))
     }
     fn parse_integer_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
         if let TokenKind::NumericLiteral(Numeric::Integer(value)) = parser.cur_token.kind() {
-            return Ok(Expression::Integer(*value));
+            return Ok(Expression::Integer(node::IntegerLiteral {
+                value: *value,
+                span: parser.cur_token.span,
+            }));
         }
 
-        Err(format!(
-            "error parsing integer literal {}",
-            parser.cur_token
+        Err(ParseError::new(
+            format!("error parsing integer literal {}", parser.cur_token),
+            &parser.cur_token,
         ))
     }
     fn parse_string_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
         if let TokenKind::StringLiteral(ref s) = parser.cur_token.kind() {
-            return Ok(Expression::String(s.to_string()));
+            return Ok(Expression::String(node::StringLiteral {
+                value: s.to_string(),
+                span: parser.cur_token.span,
+            }));
         }
 
-        Err(format!(
-            "unexpected error on string parse with {}",
-            parser.cur_token
+        Err(ParseError::new(
+            format!("unexpected error on string parse with {}", parser.cur_token),
+            &parser.cur_token,
         ))
     }
+    fn parse_interpolation(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let parts = match parser.cur_token.kind() {
+            TokenKind::TemplateString(parts) => parts.clone(),
+            _ => {
+                return Err(ParseError::new(
+                    format!("unexpected error on string parse with {}", parser.cur_token),
+                    &parser.cur_token,
+                ))
+            }
+        };
+        let mut expressions = Vec::new();
+        for part in parts {
+            match part {
+                TemplatePart::Text(text) => {
+                    expressions.push(Expression::String(node::StringLiteral {
+                        value: text.to_string(),
+                        span: parser.cur_token.span,
+                    }))
+                }
+                TemplatePart::Expression(span) => {
+                    let mut p = Parser::new(parser.l.slice(span));
+                    expressions.push(p.parse_expression(Precedence::Lowest)?);
+                    if !p.peek_token_is(&TokenKind::EOF) {
+                        return Err(ParseError::new(
+                            format!("expected }} after interpolation, got {}", p.peek_token),
+                            &p.peek_token,
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(Expression::Interpolation(Box::new(
+            node::InterpolationExpression {
+                parts: expressions,
+                span: parser.cur_token.span,
+            },
+        )))
+    }
     fn parse_boolean(parser: &mut Parser<'_>) -> ParseResult<Expression> {
         match parser.cur_token.kind() {
-            TokenKind::BooleanLiteral(v) => Ok(Expression::Boolean(*v)),
-            _ => panic!("couldn't parse {:?} to boolean", parser.cur_token),
+            TokenKind::BooleanLiteral(v) => Ok(Expression::Boolean(node::BooleanLiteral {
+                value: *v,
+                span: parser.cur_token.span,
+            })),
+            _ => Err(ParseError::new(
+                format!("error parsing boolean literal {}", parser.cur_token),
+                &parser.cur_token,
+            )),
         }
     }
     fn parse_prefix_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
         let operator = parser.cur_token.kind().clone();
 
         parser.next_token();
@@ -281,12 +482,14 @@ impl<'a> Parser<'a> {
         Ok(Expression::Prefix(Box::new(node::PrefixExpression {
             operator,
             right,
+            span: parser.span_from(start),
         })))
     }
     fn parse_infix_expression(
         parser: &mut Parser<'_>,
         left: Expression,
     ) -> ParseResult<Expression> {
+        let start = left.span().start;
         let operator = parser.cur_token.kind().clone();
         let precedence = parser.cur_precedence();
 
@@ -298,6 +501,7 @@ impl<'a> Parser<'a> {
             operator,
             left,
             right,
+            span: parser.span_from(start),
         })))
     }
     fn parse_grouped_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
@@ -308,32 +512,102 @@ impl<'a> Parser<'a> {
         exp
     }
     fn parse_if_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
-        parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenParen))?;
-        parser.next_token();
-        let condition = parser.parse_expression(Precedence::Lowest)?;
-
-        parser.expect_peek(&TokenKind::Punctuator(Punctuator::CloseParen))?;
-        parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
-        let consequence = parser.parse_block_statement()?;
+        parser.parse_if().map(|exp| Expression::If(Box::new(exp)))
+    }
+    /// Parses `if condition { ... }`, the parentheses a condition usually comes in being those
+    /// of a grouped expression, followed by an optional `else { ... }` or `else if ...`.
+    fn parse_if(&mut self) -> ParseResult<node::IfExpression> {
+        let start = self.cur_token.span.start;
+        self.next_token();
+        let condition = self.parse_expression(Precedence::Lowest)?;
 
-        let alternative = if parser.peek_token_is(&TokenKind::Keyword(Keyword::Else)) {
-            parser.next_token();
+        self.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
+        let consequence = self.parse_block_statement()?;
 
-            parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
+        let alternative = if self.peek_token_is(&TokenKind::Keyword(Keyword::Else)) {
+            self.next_token();
 
-            let alt_block = parser.parse_block_statement()?;
-            Some(alt_block)
+            if self.peek_token_is(&TokenKind::Keyword(Keyword::If)) {
+                self.next_token();
+                Some(node::Alternative::If(Box::new(self.parse_if()?)))
+            } else {
+                self.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
+                Some(node::Alternative::Block(self.parse_block_statement()?))
+            }
         } else {
             None
         };
 
-        Ok(Expression::If(Box::new(node::IfExpression {
+        Ok(node::IfExpression {
             condition,
             consequence,
             alternative,
+            span: self.span_from(start),
+        })
+    }
+    fn parse_ternary_expression(
+        parser: &mut Parser<'_>,
+        condition: Expression,
+    ) -> ParseResult<Expression> {
+        let start = condition.span().start;
+        parser.next_token();
+        let consequence = parser.parse_expression(Precedence::Lowest)?;
+        parser.expect_peek(&TokenKind::Punctuator(Punctuator::Colon))?;
+        parser.next_token();
+        // parsing the alternative at the lowest precedence makes `?` right-associative
+        let alternative = parser.parse_expression(Precedence::Lowest)?;
+        Ok(Expression::Ternary(Box::new(node::TernaryExpression {
+            condition,
+            consequence,
+            alternative,
+            span: parser.span_from(start),
+        })))
+    }
+    fn parse_match_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
+        parser.next_token();
+        let value = parser.parse_expression(Precedence::Lowest)?;
+        parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
+
+        let close = TokenKind::Punctuator(Punctuator::CloseBlock);
+        let mut arms = Vec::new();
+        while !parser.peek_token_is(&close) {
+            parser.next_token();
+            arms.push(parser.parse_match_arm()?);
+            if !parser.peek_token_is(&close) {
+                parser.expect_peek(&TokenKind::Punctuator(Punctuator::Comma))?;
+            }
+        }
+        parser.expect_peek(&close)?;
+
+        Ok(Expression::Match(Box::new(node::MatchExpression {
+            value,
+            arms,
+            span: parser.span_from(start),
         })))
     }
+    fn parse_match_arm(&mut self) -> ParseResult<node::MatchArm> {
+        let start = self.cur_token.span.start;
+        let pattern = self.parse_pattern(true)?;
+        let guard = if self.peek_token_is(&TokenKind::Keyword(Keyword::If)) {
+            self.next_token();
+            self.next_token();
+            Some(self.parse_expression(Precedence::Lowest)?)
+        } else {
+            None
+        };
+        self.expect_peek(&TokenKind::Punctuator(Punctuator::Arrow))?;
+        self.next_token();
+        let body = self.parse_expression(Precedence::Lowest)?;
+        Ok(node::MatchArm {
+            pattern,
+            guard,
+            body,
+            span: self.span_from(start),
+        })
+    }
     fn parse_block_statement(&mut self) -> ParseResult<node::BlockStatement> {
+        let start = self.cur_token.span.start;
         let mut statements = Vec::new();
 
         self.next_token();
@@ -347,9 +621,13 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
-        Ok(node::BlockStatement { statements })
+        Ok(node::BlockStatement {
+            statements,
+            span: self.span_from(start),
+        })
     }
     fn parse_function_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
         parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenParen))?;
         let parameters = parser.parse_function_parameters()?;
 
@@ -360,53 +638,113 @@ impl<'a> Parser<'a> {
         Ok(Expression::Function(Box::new(node::FunctionLiteral {
             parameters,
             body,
+            span: parser.span_from(start),
+        })))
+    }
+    fn parse_macro_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
+        parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenParen))?;
+        let parameters = parser.parse_function_parameters()?;
+
+        parser.expect_peek(&TokenKind::Punctuator(Punctuator::OpenBlock))?;
+
+        let body = parser.parse_block_statement()?;
+
+        Ok(Expression::Macro(Box::new(node::MacroLiteral {
+            parameters,
+            body,
+            span: parser.span_from(start),
+        })))
+    }
+    fn parse_quote_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
+        let expression = parser.parse_keyword_argument()?;
+        Ok(Expression::Quote(Box::new(node::QuoteExpression {
+            expression,
+            span: parser.span_from(start),
         })))
     }
-    fn parse_function_parameters(&mut self) -> Result<Vec<node::IdentifierExpression>, ParseError> {
-        let mut identifiers: Vec<node::IdentifierExpression> = Vec::new();
+    fn parse_import_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
+        let path = match parser.peek_token.kind() {
+            TokenKind::StringLiteral(path) => path.to_string(),
+            _ => {
+                return Err(ParseError::new(
+                    format!("expected module path string, got {}", parser.peek_token),
+                    &parser.peek_token,
+                ))
+            }
+        };
+        parser.next_token();
+        Ok(Expression::Import(Box::new(node::ImportExpression {
+            path,
+            span: parser.span_from(start),
+        })))
+    }
+    fn parse_unquote_expression(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
+        let expression = parser.parse_keyword_argument()?;
+        Ok(Expression::Unquote(Box::new(node::UnquoteExpression {
+            expression,
+            span: parser.span_from(start),
+        })))
+    }
+    /// Parses the single parenthesized argument of `quote` or `unquote`.
+    fn parse_keyword_argument(&mut self) -> ParseResult<Expression> {
+        self.expect_peek(&TokenKind::Punctuator(Punctuator::OpenParen))?;
+        self.next_token();
+        let expression = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(&TokenKind::Punctuator(Punctuator::CloseParen))?;
+        Ok(expression)
+    }
+    fn parse_function_parameters(&mut self) -> Result<Vec<node::Pattern>, ParseError> {
+        let mut parameters: Vec<node::Pattern> = Vec::new();
 
         if self.peek_token_is(&TokenKind::Punctuator(Punctuator::CloseParen)) {
             self.next_token();
-            return Ok(identifiers);
+            return Ok(parameters);
         }
 
         self.next_token();
 
-        identifiers.push(self.parse_identifier_into_identifier_expression()?);
+        parameters.push(self.parse_pattern(false)?);
 
         while self.peek_token_is(&TokenKind::Punctuator(Punctuator::Comma)) {
             self.next_token();
             self.next_token();
-            identifiers.push(self.parse_identifier_into_identifier_expression()?);
+            parameters.push(self.parse_pattern(false)?);
         }
 
         self.expect_peek(&TokenKind::Punctuator(Punctuator::CloseParen))?;
 
-        Ok(identifiers)
+        Ok(parameters)
     }
     fn parse_identifier_into_identifier_expression(
         &mut self,
     ) -> ParseResult<node::IdentifierExpression> {
         if let TokenKind::Identifier(ref name) = self.cur_token.kind() {
-            return Ok(node::IdentifierExpression {
-                name: name.to_string(),
-            });
+            return Ok(node::IdentifierExpression::new(name, self.cur_token.span));
         }
 
-        Err(format!(
-            "unexpected error on identifier parse with {}",
-            self.cur_token
+        Err(ParseError::new(
+            format!(
+                "unexpected error on identifier parse with {}",
+                self.cur_token
+            ),
+            &self.cur_token,
         ))
     }
     fn parse_call_expression(
         parser: &mut Parser<'_>,
         function: Expression,
     ) -> ParseResult<Expression> {
+        let start = function.span().start;
         let arguments =
             parser.parse_expression_list(TokenKind::Punctuator(Punctuator::CloseParen))?;
         Ok(Expression::Call(Box::new(node::CallExpression {
             function,
             arguments,
+            span: parser.span_from(start),
         })))
     }
     fn parse_expression_list(&mut self, end: TokenKind) -> ParseResult<Vec<Expression>> {
@@ -431,12 +769,17 @@ impl<'a> Parser<'a> {
         Ok(list)
     }
     fn parse_array_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
+        let start = parser.cur_token.span.start;
         let elements =
             parser.parse_expression_list(TokenKind::Punctuator(Punctuator::CloseBracket))?;
-        Ok(Expression::Array(Box::new(node::ArrayLiteral { elements })))
+        Ok(Expression::Array(Box::new(node::ArrayLiteral {
+            elements,
+            span: parser.span_from(start),
+        })))
     }
     fn parse_hash_literal(parser: &mut Parser<'_>) -> ParseResult<Expression> {
-        let mut pairs: HashMap<Expression, Expression> = HashMap::new();
+        let start = parser.cur_token.span.start;
+        let mut pairs = Vec::new();
 
         while !parser.peek_token_is(&TokenKind::Punctuator(Punctuator::CloseBlock)) {
             parser.next_token();
@@ -446,7 +789,7 @@ impl<'a> Parser<'a> {
             parser.next_token();
             let value = parser.parse_expression(Precedence::Lowest)?;
 
-            pairs.insert(key, value);
+            pairs.push((key, value));
 
             if !parser.peek_token_is(&TokenKind::Punctuator(Punctuator::CloseBlock)) {
                 parser.expect_peek(&TokenKind::Punctuator(Punctuator::Comma))?;
@@ -455,6 +798,9 @@ impl<'a> Parser<'a> {
 
         parser.expect_peek(&TokenKind::Punctuator(Punctuator::CloseBlock))?;
 
-        Ok(Expression::Hash(Box::new(node::HashLiteral { pairs })))
+        Ok(Expression::Hash(Box::new(node::HashLiteral {
+            pairs,
+            span: parser.span_from(start),
+        })))
     }
 }
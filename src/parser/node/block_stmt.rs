@@ -1,17 +1,19 @@
 use std::fmt;
 
-use super::Statement;
+use serde::{Deserialize, Serialize};
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+use super::{printer::Printer, Statement};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct BlockStatement {
     pub statements: Vec<Statement>,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for BlockStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for stmt in &self.statements {
-            write!(f, "{}", stmt)?;
-        }
-        Ok(())
+        Printer::print(f, |p| p.visit_block_statement(self))
     }
 }
@@ -1,19 +1,20 @@
 use std::fmt;
 
-use super::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+use super::{printer::Printer, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct CallExpression {
     pub function: Expression,
     pub arguments: Vec<Expression>,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for CallExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let arg_list: Vec<String> = (&self.arguments)
-            .into_iter()
-            .map(|exp| exp.to_string())
-            .collect();
-        write!(f, "{}({})", self.function.to_string(), arg_list.join(", "))
+        Printer::print(f, |p| p.visit_call_expression(self))
     }
 }
@@ -1,14 +1,19 @@
 use std::fmt;
 
-use super::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use super::{printer::Printer, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 pub struct ExpressionStatement {
     pub expression: Expression,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for ExpressionStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.expression)
+        Printer::print(f, |p| p.visit_expression_statement(self))
     }
 }
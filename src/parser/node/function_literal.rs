@@ -1,30 +1,46 @@
 use std::fmt;
 
-use super::BlockStatement;
+use serde::{Deserialize, Serialize};
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+use super::{printer::Printer, Binding, BlockStatement, Pattern};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct FunctionLiteral {
-    pub parameters: Vec<IdentifierExpression>,
+    pub parameters: Vec<Pattern>,
     pub body: BlockStatement,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for FunctionLiteral {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let param_list: Vec<String> = (&self.parameters)
-            .into_iter()
-            .map(|p| p.to_string())
-            .collect();
-        write!(f, "({}) {}", param_list.join(", "), self.body)
+        Printer::print(f, |p| p.visit_function_literal(self))
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct IdentifierExpression {
     pub name: String,
+    /// Filled in by the resolver; identifiers that were not resolved are looked up by name.
+    #[serde(skip)]
+    pub binding: Option<Binding>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl IdentifierExpression {
+    pub fn new(name: &str, span: Span) -> Self {
+        IdentifierExpression {
+            name: name.to_string(),
+            binding: None,
+            span,
+        }
+    }
 }
 
 impl fmt::Display for IdentifierExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.name)
+        Printer::print(f, |p| p.visit_identifier(self))
     }
 }
@@ -1,27 +1,20 @@
-use std::hash::{Hash, Hasher};
-use std::{collections::HashMap, fmt};
+use std::fmt;
 
-use super::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-pub struct HashLiteral {
-    pub pairs: HashMap<Expression, Expression>,
-}
+use super::{printer::Printer, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
 
-// Had to implement Hash for this because HashMap doesn't. Doesn't matter what this is because
-// a HashLiteral isn't a valid expression as a key in a monkey hash.
-impl Hash for HashLiteral {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        panic!("hash not implemented for HashLiteral");
-    }
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct HashLiteral {
+    // pairs are kept in source order, so evaluating the literal is deterministic
+    pub pairs: Vec<(Expression, Expression)>,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for HashLiteral {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let pairs: Vec<String> = (&self.pairs)
-            .into_iter()
-            .map(|(k, v)| format!("{}:{}", k.to_string(), v.to_string()))
-            .collect();
-        write!(f, "{{{}}}", pairs.join(", "))
+        Printer::print(f, |p| p.visit_hash_literal(self))
     }
 }
@@ -1,21 +1,45 @@
 use std::fmt;
 
-use super::{BlockStatement, Expression};
+use serde::{Deserialize, Serialize};
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+use super::{printer::Printer, BlockStatement, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct IfExpression {
     pub condition: Expression,
     pub consequence: BlockStatement,
-    pub alternative: Option<BlockStatement>,
+    pub alternative: Option<Alternative>,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for IfExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "if {} {}", self.condition, self.consequence)?;
+        Printer::print(f, |p| p.visit_if_expression(self))
+    }
+}
+
+/// What follows the `else` of an `if`: a block, or the next `if` of an `else if` chain.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Alternative {
+    Block(BlockStatement),
+    If(Box<IfExpression>),
+}
 
-        if let Some(ref stmt) = self.alternative {
-            write!(f, " else {}", stmt)?;
-        }
-        Ok(())
+/// `condition ? consequence : alternative`, an `if` of two expressions.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TernaryExpression {
+    pub condition: Expression,
+    pub consequence: Expression,
+    pub alternative: Expression,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for TernaryExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_ternary_expression(self))
     }
 }
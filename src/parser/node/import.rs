@@ -0,0 +1,20 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::printer::Printer;
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+/// `import "path"`, which evaluates to a hash of the names the module at `path` exports.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct ImportExpression {
+    pub path: String,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for ImportExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_import_expression(self))
+    }
+}
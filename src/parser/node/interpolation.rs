@@ -0,0 +1,21 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{printer::Printer, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+/// A string literal with `${expression}` interpolations. The text between them is kept as
+/// string literals among the `parts`, which evaluate to the concatenation of their values.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct InterpolationExpression {
+    pub parts: Vec<Expression>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for InterpolationExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_interpolation_expression(self))
+    }
+}
@@ -1,15 +1,25 @@
 use std::fmt;
 
-use super::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use super::{printer::Printer, Expression, Pattern};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 pub struct LetStatement {
-    pub name: String,
+    /// What the value is bound to; the resolver fills in the `binding` of its names like for
+    /// parameters.
+    pub pattern: Pattern,
     pub value: Expression,
+    /// Written `export let`, which makes the binding part of what importing the module gives.
+    #[serde(default)]
+    pub exported: bool,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for LetStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "let {} = {};", self.name, self.value)
+        Printer::print(f, |p| p.visit_let_statement(self))
     }
 }
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::printer::Printer;
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub struct IntegerLiteral {
+    pub value: i64,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for IntegerLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_integer(self))
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub struct StringLiteral {
+    pub value: String,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for StringLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_string(self))
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub struct BooleanLiteral {
+    pub value: bool,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for BooleanLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_boolean(self))
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{printer::Printer, Expression, Pattern};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+/// `match (value) { pattern => expression, ... }`, which evaluates to the expression of the
+/// first arm matching the value. Like a `let`, an arm binds its names in the enclosing scope.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MatchExpression {
+    pub value: Expression,
+    pub arms: Vec<MatchArm>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for MatchExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_match_expression(self))
+    }
+}
+
+/// `pattern if guard => body`, the guard being optional.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+    #[serde(default)]
+    pub span: Span,
+}
@@ -1,10 +1,27 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
+use self::printer::Printer;
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
 pub use self::{
-    block_stmt::BlockStatement, call_expression::CallExpression,
-    expression_stmt::ExpressionStatement, function_literal::*, hash_literal::HashLiteral,
-    if_expression::IfExpression, infix_expression::InfixExpression, let_stmt::LetStatement,
-    prefix_expression::PrefixExpression, return_stmt::ReturnStatement,
+    block_stmt::BlockStatement,
+    call_expression::CallExpression,
+    expression_stmt::ExpressionStatement,
+    function_literal::*,
+    hash_literal::HashLiteral,
+    if_expression::{Alternative, IfExpression, TernaryExpression},
+    import::ImportExpression,
+    infix_expression::InfixExpression,
+    interpolation::InterpolationExpression,
+    let_stmt::LetStatement,
+    literal::{BooleanLiteral, IntegerLiteral, StringLiteral},
+    match_expression::{MatchArm, MatchExpression},
+    pattern::{ArrayPattern, HashPattern, Pattern},
+    prefix_expression::PrefixExpression,
+    quote::{MacroLiteral, QuoteExpression, UnquoteExpression},
+    return_stmt::ReturnStatement,
 };
 
 mod block_stmt;
@@ -13,146 +30,249 @@ mod expression_stmt;
 mod function_literal;
 mod hash_literal;
 mod if_expression;
+mod import;
 mod infix_expression;
+mod interpolation;
 mod let_stmt;
+mod literal;
+mod match_expression;
+mod pattern;
 mod prefix_expression;
+mod printer;
+mod quote;
 mod return_stmt;
 
-#[derive(Debug)]
+/// Operators are written to JSON as their source text, e.g. `"+"` or `"!="`.
+mod operator {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::lexer::{token::TokenKind, Lexer};
+
+    pub fn serialize<S>(operator: &TokenKind, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(operator)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<TokenKind, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let mut l = Lexer::new(&s);
+        match (l.next_token().kind, l.next_token().kind) {
+            (kind @ TokenKind::Punctuator(_), TokenKind::EOF) => Ok(kind),
+            _ => Err(de::Error::custom(format!("invalid operator: {}", s))),
+        }
+    }
+}
+
+/// Where the value of a resolved identifier lives at runtime.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Binding {
+    /// Looked up by name in the global environment, falling back to the builtins.
+    Global,
+    /// A slot of the function environment `depth` scopes up from the current one.
+    Local { depth: usize, slot: usize },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum Node {
     Program(Box<Program>),
     Statement(Box<Statement>),
     Expression(Box<Expression>),
 }
 
-impl fmt::Display for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Node {
+    pub fn span(&self) -> Span {
         match self {
-            Node::Program(p) => write!(f, "{}", p),
-            Node::Statement(stmt) => write!(f, "{}", stmt),
-            Node::Expression(expr) => write!(f, "{}", expr),
+            Node::Program(program) => program.span,
+            Node::Statement(stmt) => stmt.span(),
+            Node::Expression(exp) => exp.span(),
         }
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_node(self))
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 pub struct Program {
     pub body: Vec<Statement>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Program {
     pub fn new() -> Self {
-        Program { body: Vec::new() }
+        Program {
+            body: Vec::new(),
+            span: Span::default(),
+        }
     }
 }
 
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let statements: Vec<String> = (&self.body)
-            .into_iter()
-            .map(|stmt| stmt.to_string())
-            .collect();
-        write!(f, "{}", statements.join(""))
+        Printer::print(f, |p| p.visit_program(self))
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum Statement {
     Let(Box<LetStatement>),
     Return(Box<ReturnStatement>),
     Expression(Box<ExpressionStatement>),
 }
 
+impl Statement {
+    /// The source range of the statement, including its trailing semicolon.
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let(stmt) => stmt.span,
+            Statement::Return(stmt) => stmt.span,
+            Statement::Expression(stmt) => stmt.span,
+        }
+    }
+}
+
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Statement::Let(stmt) => format!("{}", stmt),
-                Statement::Return(ret) => format!("{}", ret),
-                Statement::Expression(exp) => format!("{}", exp),
-            }
-        )
+        Printer::print(f, |p| p.visit_statement(self))
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum Expression {
-    Integer(i64),
-    Identifier(String),
-    String(String),
-    Boolean(bool),
+    Integer(IntegerLiteral),
+    Identifier(IdentifierExpression),
+    String(StringLiteral),
+    Boolean(BooleanLiteral),
     Prefix(Box<PrefixExpression>),
     Infix(Box<InfixExpression>),
     If(Box<IfExpression>),
+    Ternary(Box<TernaryExpression>),
     Function(Box<FunctionLiteral>),
     Call(Box<CallExpression>),
     Array(Box<ArrayLiteral>),
     Index(Box<IndexExpression>),
+    Slice(Box<SliceExpression>),
     Hash(Box<HashLiteral>),
+    Quote(Box<QuoteExpression>),
+    Unquote(Box<UnquoteExpression>),
+    Macro(Box<MacroLiteral>),
+    Import(Box<ImportExpression>),
+    Interpolation(Box<InterpolationExpression>),
+    Match(Box<MatchExpression>),
+}
+
+impl Expression {
+    /// The source range of the expression. Nodes built outside the parser, like the literals
+    /// from `From<bool>` and `From<i64>`, have an empty span at offset 0.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Integer(lit) => lit.span,
+            Expression::Identifier(ident) => ident.span,
+            Expression::String(lit) => lit.span,
+            Expression::Boolean(lit) => lit.span,
+            Expression::Prefix(exp) => exp.span,
+            Expression::Infix(exp) => exp.span,
+            Expression::If(exp) => exp.span,
+            Expression::Ternary(exp) => exp.span,
+            Expression::Function(func) => func.span,
+            Expression::Call(exp) => exp.span,
+            Expression::Array(array) => array.span,
+            Expression::Index(exp) => exp.span,
+            Expression::Slice(exp) => exp.span,
+            Expression::Hash(hash) => hash.span,
+            Expression::Quote(exp) => exp.span,
+            Expression::Unquote(exp) => exp.span,
+            Expression::Macro(mac) => mac.span,
+            Expression::Import(exp) => exp.span,
+            Expression::Interpolation(exp) => exp.span,
+            Expression::Match(exp) => exp.span,
+        }
+    }
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Expression::Integer(value) => format!("{}", value),
-                Expression::Identifier(value) => format!("{}", value),
-                Expression::String(value) => format!("{}", value),
-                Expression::Boolean(value) => format!("{}", value),
-                Expression::Prefix(value) => format!("{}", value),
-                Expression::Infix(value) => format!("{}", value),
-                Expression::If(value) => format!("{}", value),
-                Expression::Function(value) => format!("{}", value),
-                Expression::Call(value) => format!("{}", value),
-                Expression::Array(a) => format!("{}", a),
-                Expression::Index(i) => format!("{}", i),
-                Expression::Hash(h) => format!("{}", h),
-            }
-        )
+        Printer::print(f, |p| p.visit_expression(self))
     }
 }
 
 impl From<bool> for Expression {
     #[inline]
-    fn from(n: bool) -> Self {
-        Self::Boolean(n)
+    fn from(value: bool) -> Self {
+        Self::Boolean(BooleanLiteral {
+            value,
+            span: Span::default(),
+        })
     }
 }
 
 impl From<i64> for Expression {
     #[inline]
-    fn from(n: i64) -> Self {
-        Self::Integer(n)
+    fn from(value: i64) -> Self {
+        Self::Integer(IntegerLiteral {
+            value,
+            span: Span::default(),
+        })
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct ArrayLiteral {
     pub elements: Vec<Expression>,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for ArrayLiteral {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let elements: Vec<String> = (&self.elements)
-            .into_iter()
-            .map(|e| e.to_string())
-            .collect();
-        write!(f, "[{}]", elements.join(", "))
+        Printer::print(f, |p| p.visit_array_literal(self))
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct IndexExpression {
     pub left: Expression,
     pub index: Expression,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for IndexExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}[{}])", self.left.to_string(), self.index.to_string())
+        Printer::print(f, |p| p.visit_index_expression(self))
+    }
+}
+
+/// `left[start:end]`, either bound of which may be left out.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct SliceExpression {
+    pub left: Expression,
+    pub start: Option<Expression>,
+    pub end: Option<Expression>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for SliceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_slice_expression(self))
     }
 }
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{printer::Printer, Expression, IdentifierExpression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+/// What a `let`, a function parameter or a `match` arm binds a value to: a name, or the elements
+/// of an array or the values of a hash, destructured into further patterns.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Pattern {
+    Identifier(IdentifierExpression),
+    /// An integer, string or boolean literal, which only matches a value equal to it.
+    Literal(Expression),
+    /// `_`, which matches anything and binds nothing.
+    Wildcard {
+        #[serde(default)]
+        span: Span,
+    },
+    Array(Box<ArrayPattern>),
+    Hash(Box<HashPattern>),
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Identifier(ident) => ident.span,
+            Pattern::Literal(lit) => lit.span(),
+            Pattern::Wildcard { span } => *span,
+            Pattern::Array(array) => array.span,
+            Pattern::Hash(hash) => hash.span,
+        }
+    }
+
+    /// The names the pattern binds, in source order.
+    pub fn identifiers(&self) -> Vec<&IdentifierExpression> {
+        let mut identifiers = Vec::new();
+        self.collect(&mut identifiers);
+        identifiers
+    }
+
+    fn collect<'a>(&'a self, identifiers: &mut Vec<&'a IdentifierExpression>) {
+        match self {
+            Pattern::Identifier(ident) => identifiers.push(ident),
+            Pattern::Literal(_) | Pattern::Wildcard { .. } => {}
+            Pattern::Array(array) => {
+                for el in &array.elements {
+                    el.collect(identifiers);
+                }
+                identifiers.extend(&array.rest);
+            }
+            Pattern::Hash(hash) => {
+                for (_, value) in &hash.pairs {
+                    value.collect(identifiers);
+                }
+            }
+        }
+    }
+
+    /// Like `identifiers`, with mutable access, e.g. to resolve them.
+    pub fn identifiers_mut(&mut self) -> Vec<&mut IdentifierExpression> {
+        let mut identifiers = Vec::new();
+        self.collect_mut(&mut identifiers);
+        identifiers
+    }
+
+    fn collect_mut<'a>(&'a mut self, identifiers: &mut Vec<&'a mut IdentifierExpression>) {
+        match self {
+            Pattern::Identifier(ident) => identifiers.push(ident),
+            Pattern::Literal(_) | Pattern::Wildcard { .. } => {}
+            Pattern::Array(array) => {
+                for el in &mut array.elements {
+                    el.collect_mut(identifiers);
+                }
+                identifiers.extend(&mut array.rest);
+            }
+            Pattern::Hash(hash) => {
+                for (_, value) in &mut hash.pairs {
+                    value.collect_mut(identifiers);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_pattern(self))
+    }
+}
+
+impl From<IdentifierExpression> for Pattern {
+    fn from(ident: IdentifierExpression) -> Self {
+        Pattern::Identifier(ident)
+    }
+}
+
+/// `[a, b, ...rest]`, which matches an array of exactly as many elements as it has patterns, or
+/// of at least as many when it ends with a rest binding the array of the remaining elements.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub struct ArrayPattern {
+    pub elements: Vec<Pattern>,
+    pub rest: Option<IdentifierExpression>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+/// `{"x": x, "y": y}`, which matches a hash having every key, whatever its other pairs.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub struct HashPattern {
+    pub pairs: Vec<(Expression, Pattern)>,
+    #[serde(default)]
+    pub span: Span,
+}
@@ -1,17 +1,25 @@
 use std::fmt;
 
-use crate::lexer::token::TokenKind;
+use serde::{Deserialize, Serialize};
 
-use super::Expression;
+use crate::{
+    lexer::token::{Span, TokenKind},
+    parser::visitor::Visitor,
+};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use super::{printer::Printer, Expression};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 pub struct PrefixExpression {
+    #[serde(with = "super::operator")]
     pub operator: TokenKind,
     pub right: Expression,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for PrefixExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}{})", self.operator, self.right)
+        Printer::print(f, |p| p.visit_prefix_expression(self))
     }
 }
@@ -0,0 +1,262 @@
+//! The `Display` impls of the AST nodes, written as one `Visitor`.
+
+use std::fmt;
+
+use super::{
+    Alternative, ArrayLiteral, BooleanLiteral, CallExpression, Expression, FunctionLiteral,
+    HashLiteral, IdentifierExpression, IfExpression, ImportExpression, IndexExpression,
+    InfixExpression, IntegerLiteral, InterpolationExpression, LetStatement, MacroLiteral, MatchArm,
+    MatchExpression, Pattern, PrefixExpression, QuoteExpression, ReturnStatement, SliceExpression,
+    StringLiteral, TernaryExpression, UnquoteExpression,
+};
+use crate::parser::visitor::Visitor;
+
+pub(super) struct Printer<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    result: fmt::Result,
+}
+
+impl<'a, 'b> Printer<'a, 'b> {
+    /// Prints whatever `visit` visits to `f`.
+    pub(super) fn print<F>(f: &'a mut fmt::Formatter<'b>, visit: F) -> fmt::Result
+    where
+        F: FnOnce(&mut Self),
+    {
+        let mut printer = Printer { f, result: Ok(()) };
+        visit(&mut printer);
+        printer.result
+    }
+
+    fn write(&mut self, args: fmt::Arguments<'_>) {
+        if self.result.is_ok() {
+            self.result = self.f.write_fmt(args);
+        }
+    }
+
+    fn list<T, F>(&mut self, items: &[T], separator: &str, mut visit: F)
+    where
+        F: FnMut(&mut Self, &T),
+    {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.write(format_args!("{}", separator));
+            }
+            visit(self, item);
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Printer<'_, '_> {
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        if stmt.exported {
+            self.write(format_args!("export "));
+        }
+        self.write(format_args!("let "));
+        self.visit_pattern(&stmt.pattern);
+        self.write(format_args!(" = "));
+        self.visit_expression(&stmt.value);
+        self.write(format_args!(";"));
+    }
+
+    fn visit_return_statement(&mut self, stmt: &'ast ReturnStatement) {
+        self.write(format_args!("return "));
+        self.visit_expression(&stmt.value);
+        self.write(format_args!(";"));
+    }
+
+    fn visit_integer(&mut self, lit: &'ast IntegerLiteral) {
+        self.write(format_args!("{}", lit.value));
+    }
+
+    fn visit_identifier(&mut self, ident: &'ast IdentifierExpression) {
+        self.write(format_args!("{}", ident.name));
+    }
+
+    fn visit_string(&mut self, lit: &'ast StringLiteral) {
+        self.write(format_args!("{}", lit.value));
+    }
+
+    fn visit_boolean(&mut self, lit: &'ast BooleanLiteral) {
+        self.write(format_args!("{}", lit.value));
+    }
+
+    fn visit_interpolation_expression(&mut self, exp: &'ast InterpolationExpression) {
+        self.write(format_args!("\""));
+        for part in &exp.parts {
+            match part {
+                Expression::String(lit) => {
+                    self.write(format_args!("{}", lit.value.replace("${", "\\${")))
+                }
+                part => {
+                    self.write(format_args!("${{"));
+                    self.visit_expression(part);
+                    self.write(format_args!("}}"));
+                }
+            }
+        }
+        self.write(format_args!("\""));
+    }
+
+    fn visit_match_expression(&mut self, exp: &'ast MatchExpression) {
+        self.write(format_args!("match "));
+        self.visit_expression(&exp.value);
+        self.write(format_args!(" {{"));
+        if !exp.arms.is_empty() {
+            self.write(format_args!(" "));
+            self.list(&exp.arms, ", ", |p, arm| p.visit_match_arm(arm));
+            self.write(format_args!(" "));
+        }
+        self.write(format_args!("}}"));
+    }
+
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm) {
+        self.visit_pattern(&arm.pattern);
+        if let Some(guard) = &arm.guard {
+            self.write(format_args!(" if "));
+            self.visit_expression(guard);
+        }
+        self.write(format_args!(" => "));
+        self.visit_expression(&arm.body);
+    }
+
+    fn visit_import_expression(&mut self, exp: &'ast ImportExpression) {
+        self.write(format_args!("import \"{}\"", exp.path));
+    }
+
+    fn visit_prefix_expression(&mut self, exp: &'ast PrefixExpression) {
+        self.write(format_args!("({}", exp.operator));
+        self.visit_expression(&exp.right);
+        self.write(format_args!(")"));
+    }
+
+    fn visit_infix_expression(&mut self, exp: &'ast InfixExpression) {
+        self.write(format_args!("("));
+        self.visit_expression(&exp.left);
+        self.write(format_args!(" {} ", exp.operator));
+        self.visit_expression(&exp.right);
+        self.write(format_args!(")"));
+    }
+
+    fn visit_if_expression(&mut self, exp: &'ast IfExpression) {
+        self.write(format_args!("if "));
+        self.visit_expression(&exp.condition);
+        self.write(format_args!(" "));
+        self.visit_block_statement(&exp.consequence);
+        if let Some(alt) = &exp.alternative {
+            self.write(format_args!(" else "));
+            match alt {
+                Alternative::Block(block) => self.visit_block_statement(block),
+                Alternative::If(exp) => self.visit_if_expression(exp),
+            }
+        }
+    }
+
+    fn visit_ternary_expression(&mut self, exp: &'ast TernaryExpression) {
+        self.write(format_args!("("));
+        self.visit_expression(&exp.condition);
+        self.write(format_args!(" ? "));
+        self.visit_expression(&exp.consequence);
+        self.write(format_args!(" : "));
+        self.visit_expression(&exp.alternative);
+        self.write(format_args!(")"));
+    }
+
+    fn visit_function_literal(&mut self, func: &'ast FunctionLiteral) {
+        self.write(format_args!("("));
+        self.list(&func.parameters, ", ", |p, param| p.visit_parameter(param));
+        self.write(format_args!(") "));
+        self.visit_block_statement(&func.body);
+    }
+
+    fn visit_pattern(&mut self, pat: &'ast Pattern) {
+        match pat {
+            Pattern::Identifier(ident) => self.visit_identifier(ident),
+            Pattern::Literal(lit) => self.visit_expression(lit),
+            Pattern::Wildcard { .. } => self.write(format_args!("_")),
+            Pattern::Array(array) => {
+                self.write(format_args!("["));
+                self.list(&array.elements, ", ", |p, el| p.visit_pattern(el));
+                if let Some(rest) = &array.rest {
+                    if !array.elements.is_empty() {
+                        self.write(format_args!(", "));
+                    }
+                    self.write(format_args!("...{}", rest.name));
+                }
+                self.write(format_args!("]"));
+            }
+            Pattern::Hash(hash) => {
+                self.write(format_args!("{{"));
+                self.list(&hash.pairs, ", ", |p, (key, value)| {
+                    p.visit_expression(key);
+                    p.write(format_args!(":"));
+                    p.visit_pattern(value);
+                });
+                self.write(format_args!("}}"));
+            }
+        }
+    }
+
+    fn visit_call_expression(&mut self, exp: &'ast CallExpression) {
+        self.visit_expression(&exp.function);
+        self.write(format_args!("("));
+        self.list(&exp.arguments, ", ", |p, arg| p.visit_expression(arg));
+        self.write(format_args!(")"));
+    }
+
+    fn visit_array_literal(&mut self, array: &'ast ArrayLiteral) {
+        self.write(format_args!("["));
+        self.list(&array.elements, ", ", |p, e| p.visit_expression(e));
+        self.write(format_args!("]"));
+    }
+
+    fn visit_index_expression(&mut self, exp: &'ast IndexExpression) {
+        self.write(format_args!("("));
+        self.visit_expression(&exp.left);
+        self.write(format_args!("["));
+        self.visit_expression(&exp.index);
+        self.write(format_args!("])"));
+    }
+
+    fn visit_slice_expression(&mut self, exp: &'ast SliceExpression) {
+        self.write(format_args!("("));
+        self.visit_expression(&exp.left);
+        self.write(format_args!("["));
+        if let Some(start) = &exp.start {
+            self.visit_expression(start);
+        }
+        self.write(format_args!(":"));
+        if let Some(end) = &exp.end {
+            self.visit_expression(end);
+        }
+        self.write(format_args!("])"));
+    }
+
+    fn visit_hash_literal(&mut self, hash: &'ast HashLiteral) {
+        self.write(format_args!("{{"));
+        self.list(&hash.pairs, ", ", |p, (key, value)| {
+            p.visit_expression(key);
+            p.write(format_args!(":"));
+            p.visit_expression(value);
+        });
+        self.write(format_args!("}}"));
+    }
+
+    fn visit_quote_expression(&mut self, exp: &'ast QuoteExpression) {
+        self.write(format_args!("quote("));
+        self.visit_expression(&exp.expression);
+        self.write(format_args!(")"));
+    }
+
+    fn visit_unquote_expression(&mut self, exp: &'ast UnquoteExpression) {
+        self.write(format_args!("unquote("));
+        self.visit_expression(&exp.expression);
+        self.write(format_args!(")"));
+    }
+
+    fn visit_macro_literal(&mut self, mac: &'ast MacroLiteral) {
+        self.write(format_args!("macro("));
+        self.list(&mac.parameters, ", ", |p, param| p.visit_parameter(param));
+        self.write(format_args!(") "));
+        self.visit_block_statement(&mac.body);
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{printer::Printer, BlockStatement, Expression, Pattern};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+/// `quote(expression)`, which evaluates to the expression itself rather than its value.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct QuoteExpression {
+    pub expression: Expression,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for QuoteExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_quote_expression(self))
+    }
+}
+
+/// `unquote(expression)` inside a quote, replaced by the value of `expression` when the quote is
+/// evaluated.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct UnquoteExpression {
+    pub expression: Expression,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for UnquoteExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_unquote_expression(self))
+    }
+}
+
+/// `macro(parameters) { body }`, a function from quoted arguments to the quoted expression a
+/// call expands to.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MacroLiteral {
+    pub parameters: Vec<Pattern>,
+    pub body: BlockStatement,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl fmt::Display for MacroLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::print(f, |p| p.visit_macro_literal(self))
+    }
+}
@@ -1,14 +1,19 @@
 use std::fmt;
 
-use super::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use super::{printer::Printer, Expression};
+use crate::{lexer::token::Span, parser::visitor::Visitor};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 pub struct ReturnStatement {
     pub value: Expression,
+    #[serde(default)]
+    pub span: Span,
 }
 
 impl fmt::Display for ReturnStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "return {};", self.value)
+        Printer::print(f, |p| p.visit_return_statement(self))
     }
 }
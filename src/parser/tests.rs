@@ -15,6 +15,30 @@ fn setup(input: &str, stmt_count: usize) -> Program {
     prog
 }
 
+fn string(value: &str) -> Expression {
+    Expression::String(node::StringLiteral {
+        value: value.to_string(),
+        span: Span::default(),
+    })
+}
+
+/// The JSON of `exp` without its spans, to compare trees built by hand with parsed ones.
+fn without_spans(exp: &Expression) -> serde_json::Value {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.remove("span");
+                map.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(exp).unwrap();
+    strip(&mut value);
+    value
+}
+
 #[test]
 fn test_return_statement() {
     let input = r#"
@@ -25,16 +49,16 @@ return "foo";
 "#;
     let prog = setup(input, 4);
     let tests = [
-        Expression::Identifier("bar".to_string()),
-        Expression::Integer(5),
-        Expression::Boolean(true),
-        Expression::String("foo".to_string()),
+        Expression::Identifier(node::IdentifierExpression::new("bar", Span::default())),
+        Expression::from(5),
+        Expression::from(true),
+        string("foo"),
     ];
     let mut it = prog.body.iter();
     for t in tests {
         match it.next().unwrap() {
             node::Statement::Return(ref l) => {
-                assert_eq!(l.value, t);
+                assert_eq!(without_spans(&l.value), without_spans(&t));
             }
             _ => panic!("invalid node"),
         }
@@ -50,17 +74,17 @@ let foo = "foo";
 "#;
     let prog = setup(input, 4);
     let tests = [
-        Expression::Identifier("bar".to_string()),
-        Expression::Integer(5),
-        Expression::Boolean(true),
-        Expression::String("foo".to_string()),
+        Expression::Identifier(node::IdentifierExpression::new("bar", Span::default())),
+        Expression::from(5),
+        Expression::from(true),
+        string("foo"),
     ];
     let mut it = prog.body.iter();
     for t in tests {
         match it.next().unwrap() {
             node::Statement::Let(ref l) => {
-                assert_eq!(l.name, "foo".to_string());
-                assert_eq!(l.value, t);
+                assert_eq!(l.pattern.to_string(), "foo");
+                assert_eq!(without_spans(&l.value), without_spans(&t));
             }
             _ => panic!("invalid node"),
         }
@@ -76,18 +100,20 @@ fn test_prefix_expression() {
     let tests = [
         Expression::Prefix(Box::new(node::PrefixExpression {
             operator: TokenKind::punctuator(Punctuator::Not),
-            right: Expression::Integer(5),
+            right: Expression::from(5),
+            span: Span::default(),
         })),
         Expression::Prefix(Box::new(node::PrefixExpression {
             operator: TokenKind::punctuator(Punctuator::Sub),
-            right: Expression::Integer(15),
+            right: Expression::from(15),
+            span: Span::default(),
         })),
     ];
     let mut it = prog.body.iter();
     for t in tests {
         match it.next().unwrap() {
             node::Statement::Expression(ref l) => {
-                assert_eq!(l.expression, t);
+                assert_eq!(without_spans(&l.expression), without_spans(&t));
             }
             _ => panic!("invalid node"),
         }
@@ -108,51 +134,59 @@ fn test_infix_expression() {
     let prog = setup(input, 8);
     let tests = [
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::Add),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::Sub),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::Mul),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::Div),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::GreaterThan),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::LessThan),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::Eq),
+            span: Span::default(),
         })),
         Expression::Infix(Box::new(node::InfixExpression {
-            left: Expression::Integer(5),
-            right: Expression::Integer(5),
+            left: Expression::from(5),
+            right: Expression::from(5),
             operator: TokenKind::punctuator(Punctuator::NotEq),
+            span: Span::default(),
         })),
     ];
     let mut it = prog.body.iter();
     for t in tests {
         match it.next().unwrap() {
             node::Statement::Expression(ref l) => {
-                assert_eq!(l.expression, t);
+                assert_eq!(without_spans(&l.expression), without_spans(&t));
             }
             _ => panic!("invalid node"),
         }
@@ -220,6 +254,17 @@ fn test_if_expression() {
     let tests = vec![
         ("if (x < y) { x }", "if (x < y) x"),
         ("if (x < y) { x } else { y }", "if (x < y) x else y"),
+        ("if x < y { x }", "if (x < y) x"),
+        ("if f(x) { x } else { y }", "if f(x) x else y"),
+        (
+            "if (a) { 1 } else if b { 2 } else if (c) { 3 } else { 4 }",
+            "if a 1 else if b 2 else if c 3 else 4",
+        ),
+        ("if a { 1 } else if b { 2 }", "if a 1 else if b 2"),
+        // a `{` right after a complete condition opens the consequence
+        (r#"if h["a"] { 1 }"#, "if (h[a]) 1"),
+        (r#"if {"a": 1}["a"] { 1 }"#, "if ({a:1}[a]) 1"),
+        ("if a ? b : c { 1 }", "if (a ? b : c) 1"),
     ];
     for t in tests {
         let l = Lexer::new(t.0);
@@ -227,6 +272,61 @@ fn test_if_expression() {
         let prog = p.parse_program().unwrap();
         assert_eq!(prog.to_string(), t.1);
     }
+    let prog = setup("if a { 1 } else if b { 2 } else { 3 }", 1);
+    match &prog.body[0] {
+        Statement::Expression(stmt) => match &stmt.expression {
+            Expression::If(exp) => match &exp.alternative {
+                Some(node::Alternative::If(alt)) => {
+                    assert_eq!(alt.span, Span::new(16, 37));
+                    assert!(matches!(alt.alternative, Some(node::Alternative::Block(_))));
+                }
+                alt => panic!("expected else if but got {:?}", alt),
+            },
+            exp => panic!("expected if expression but got {:?}", exp),
+        },
+        stmt => panic!("expected expression statement but got {:?}", stmt),
+    }
+    for input in [
+        "if { 1 }",
+        "if a 1",
+        "if a { 1 } else if { 2 }",
+        "if a { 1 } else 2",
+    ] {
+        assert!(
+            Parser::new(Lexer::new(input)).parse_program().is_err(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_ternary_expression() {
+    let tests = [
+        ("a ? b : c", "(a ? b : c)"),
+        ("a > 1 ? b + 1 : c * 2", "((a > 1) ? (b + 1) : (c * 2))"),
+        ("a ? b : c ? d : e", "(a ? b : (c ? d : e))"),
+        ("a ? b ? c : d : e", "(a ? (b ? c : d) : e)"),
+        ("f(a ? 1 : 2)", "f((a ? 1 : 2))"),
+        ("x[a ? 1 : 2]", "(x[(a ? 1 : 2)])"),
+        ("x[a ? 1 : 2:]", "(x[(a ? 1 : 2):])"),
+        (r#"{a ? "k" : "l": 1}"#, "{(a ? k : l):1}"),
+        (r#"{"a": c ? 1 : 2}"#, "{a:(c ? 1 : 2)}"),
+        (r#"{"a": c ? 1 : 2, "b": d}"#, "{a:(c ? 1 : 2), b:d}"),
+        ("x[:a ? 1 : 2]", "(x[:(a ? 1 : 2)])"),
+        ("x[a ? 1 : 2:b ? 3 : 4]", "(x[(a ? 1 : 2):(b ? 3 : 4)])"),
+    ];
+    for (input, expected) in tests.iter() {
+        assert_eq!(setup(input, 1).to_string(), *expected, "{}", input);
+    }
+    assert_eq!(setup("a ? b : c", 1).body[0].span(), Span::new(0, 9));
+    for input in ["a ? b", "a ? b c", "a ? : c", "a ? b : "] {
+        assert!(
+            Parser::new(Lexer::new(input)).parse_program().is_err(),
+            "{}",
+            input
+        );
+    }
 }
 
 #[test]
@@ -239,3 +339,418 @@ fn test_fn_literal() {
         assert_eq!(prog.to_string(), t.1);
     }
 }
+
+#[test]
+fn test_macros() {
+    let tests = vec![
+        ("quote(1 + unquote(a))", "quote((1 + unquote(a)))"),
+        (
+            "let m = macro(a, b) { quote(unquote(b) - unquote(a)) };",
+            "let m = macro(a, b) quote((unquote(b) - unquote(a)));",
+        ),
+    ];
+    for t in tests {
+        let l = Lexer::new(t.0);
+        let mut p = Parser::new(l);
+        let prog = p.parse_program().unwrap();
+        assert_eq!(prog.to_string(), t.1);
+    }
+    assert!(Parser::new(Lexer::new("quote 1")).parse_program().is_err());
+}
+
+#[test]
+fn test_json_round_trip() {
+    let input = r#"let add = fn(x, y) { return x + y; };
+if (!add(1, 2) == -3) { [1, "two"][0] } else { {"a": true}["a"] }
+if add { 1 } else if add(1, 2) ? false : true { 2 } else { 3 }
+"#;
+    let prog = setup(input, 3);
+    let json = serde_json::to_string(&prog).unwrap();
+    let decoded: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, prog);
+}
+
+#[test]
+fn test_json_schema() {
+    let prog = setup("let x = -a + 1;", 1);
+    let expected = serde_json::json!({
+        "body": [{
+            "type": "Let",
+            "value": {
+                "pattern": {
+                    "type": "Identifier",
+                    "value": {"name": "x", "span": {"start": 4, "end": 5}}
+                },
+                "value": {
+                    "type": "Infix",
+                    "value": {
+                        "operator": "+",
+                        "left": {
+                            "type": "Prefix",
+                            "value": {
+                                "operator": "-",
+                                "right": {
+                                    "type": "Identifier",
+                                    "value": {"name": "a", "span": {"start": 9, "end": 10}}
+                                },
+                                "span": {"start": 8, "end": 10}
+                            }
+                        },
+                        "right": {
+                            "type": "Integer",
+                            "value": {"value": 1, "span": {"start": 13, "end": 14}}
+                        },
+                        "span": {"start": 8, "end": 14}
+                    }
+                },
+                "exported": false,
+                "span": {"start": 0, "end": 15}
+            }
+        }],
+        "span": {"start": 0, "end": 15}
+    });
+    assert_eq!(serde_json::to_value(&prog).unwrap(), expected);
+}
+
+#[test]
+fn test_hash_literal_source_order() {
+    let tests = vec![
+        (r#"{"b": 1, "a": 2, "c": 3}"#, "{b:1, a:2, c:3}"),
+        ("{3: x, 1: y + 1}", "{3:x, 1:(y + 1)}"),
+        ("{}", "{}"),
+    ];
+    for t in tests {
+        let l = Lexer::new(t.0);
+        let mut p = Parser::new(l);
+        let prog = p.parse_program().unwrap();
+        assert_eq!(prog.to_string(), t.1);
+    }
+}
+
+#[test]
+fn test_visitor() {
+    use super::visitor::Visitor;
+
+    // counts the calls made outside of function literals
+    #[derive(Default)]
+    struct Calls {
+        count: usize,
+        params: Vec<String>,
+    }
+
+    impl<'ast> Visitor<'ast> for Calls {
+        fn visit_call_expression(&mut self, exp: &'ast node::CallExpression) {
+            self.count += 1;
+            visitor::walk_call_expression(self, exp);
+        }
+
+        fn visit_function_literal(&mut self, func: &'ast node::FunctionLiteral) {
+            for param in &func.parameters {
+                self.visit_parameter(param);
+            }
+        }
+
+        fn visit_parameter(&mut self, param: &'ast node::Pattern) {
+            for ident in param.identifiers() {
+                self.params.push(ident.name.clone());
+            }
+        }
+    }
+
+    let prog = setup("f(g(1), fn(x, [y, ...z]) { h() }); [i()][0]", 0);
+    let mut calls = Calls::default();
+    calls.visit_program(&prog);
+    assert_eq!(calls.count, 3);
+    assert_eq!(calls.params, ["x", "y", "z"]);
+
+    // the default walk does descend into function bodies
+    struct AllCalls(usize);
+    impl<'ast> Visitor<'ast> for AllCalls {
+        fn visit_call_expression(&mut self, exp: &'ast node::CallExpression) {
+            self.0 += 1;
+            visitor::walk_call_expression(self, exp);
+        }
+    }
+    let mut all = AllCalls(0);
+    all.visit_program(&prog);
+    assert_eq!(all.0, 4);
+}
+
+#[test]
+fn test_visitor_mut() {
+    use super::visitor::VisitorMut;
+
+    struct Rename<'a>(&'a str, &'a str);
+
+    impl VisitorMut for Rename<'_> {
+        fn visit_identifier_mut(&mut self, ident: &mut node::IdentifierExpression) {
+            if ident.name == self.0 {
+                ident.name = self.1.to_string();
+            }
+        }
+
+        fn visit_parameter_mut(&mut self, param: &mut node::Pattern) {
+            for ident in param.identifiers_mut() {
+                self.visit_identifier_mut(ident);
+            }
+        }
+
+        fn visit_integer_mut(&mut self, lit: &mut node::IntegerLiteral) {
+            lit.value *= 10;
+        }
+    }
+
+    let mut prog = setup("let f = fn(a, b) { a + b * 2 }; f(a, {a: [1]})", 0);
+    Rename("a", "x").visit_program_mut(&mut prog);
+    assert_eq!(
+        prog.to_string(),
+        "let f = (x, b) (x + (b * 20));f(x, {x:[10]})"
+    );
+}
+
+#[test]
+fn test_spans() {
+    use super::visitor::{self, Visitor};
+
+    // records the source text of every expression
+    struct Sources<'a>(&'a str, Vec<&'a str>);
+
+    impl<'a, 'ast> Visitor<'ast> for Sources<'a> {
+        fn visit_expression(&mut self, exp: &'ast Expression) {
+            let span = exp.span();
+            self.1.push(&self.0[span.start..span.end]);
+            visitor::walk_expression(self, exp);
+        }
+    }
+
+    let input = "let f = fn(x) { if (x > 1) { x } else { -x } };\nf([1, \"a\"][0]);";
+    let prog = setup(input, 2);
+    let spans: Vec<&str> = prog
+        .body
+        .iter()
+        .map(|stmt| &input[stmt.span().start..stmt.span().end])
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            "let f = fn(x) { if (x > 1) { x } else { -x } };",
+            "f([1, \"a\"][0]);"
+        ]
+    );
+
+    let mut sources = Sources(input, Vec::new());
+    sources.visit_program(&prog);
+    assert_eq!(
+        sources.1,
+        [
+            "fn(x) { if (x > 1) { x } else { -x } }",
+            "if (x > 1) { x } else { -x }",
+            "x > 1",
+            "x",
+            "1",
+            "x",
+            "-x",
+            "x",
+            "f([1, \"a\"][0])",
+            "f",
+            "[1, \"a\"][0]",
+            "[1, \"a\"]",
+            "1",
+            "\"a\"",
+            "0",
+        ]
+    );
+}
+
+#[test]
+fn test_modules() {
+    let input = r#"export let lib = import "lib.monkey";"#;
+    let prog = setup(input, 1);
+    assert_eq!(prog.to_string(), input);
+    match &prog.body[0] {
+        Statement::Let(stmt) => {
+            assert!(stmt.exported);
+            assert_eq!(stmt.span, Span::new(0, input.len()));
+        }
+        stmt => panic!("expected let statement but got {:?}", stmt),
+    }
+    assert!(Parser::new(Lexer::new("import lib"))
+        .parse_program()
+        .is_err());
+    assert!(Parser::new(Lexer::new("export 1")).parse_program().is_err());
+}
+
+#[test]
+fn test_interpolation() {
+    let input = r#""x = ${x + 1}, ${f("}")}!""#;
+    let prog = setup(input, 1);
+    assert_eq!(prog.to_string(), r#""x = ${(x + 1)}, ${f(})}!""#);
+    let exp = match &prog.body[0] {
+        Statement::Expression(stmt) => &stmt.expression,
+        stmt => panic!("expected expression statement but got {:?}", stmt),
+    };
+    match exp {
+        Expression::Interpolation(exp) => {
+            assert_eq!(exp.span, Span::new(0, input.len()));
+            let spans: Vec<&str> = exp
+                .parts
+                .iter()
+                .map(|part| &input[part.span().start..part.span().end])
+                .collect();
+            assert_eq!(spans, [input, "x + 1", input, r#"f("}")"#, input]);
+        }
+        exp => panic!("expected interpolation but got {:?}", exp),
+    }
+    assert!(Parser::new(Lexer::new(r#""${a b}""#))
+        .parse_program()
+        .is_err());
+    assert!(Parser::new(Lexer::new(r#""${}""#)).parse_program().is_err());
+
+    // escaped openings stay escaped when printed
+    let prog = setup(r#""\${a} ${b}""#, 1);
+    assert_eq!(prog.to_string(), r#""\${a} ${b}""#);
+
+    let input = r#"let s = "a ${f(1)" + 1;"#;
+    let errors = Parser::new(Lexer::new(input)).parse_program().unwrap_err();
+    assert_eq!(errors[0].message, "unterminated interpolation");
+    assert_eq!(&input[errors[0].span.start..errors[0].span.end], "${");
+}
+
+#[test]
+fn test_slice_expressions() {
+    let tests = [
+        ("a[1:2]", "(a[1:2])"),
+        ("a[:b + 1]", "(a[:(b + 1)])"),
+        ("a[-1:]", "(a[(-1):])"),
+        ("a[:]", "(a[:])"),
+        ("a[1][:2][3]", "(((a[1])[:2])[3])"),
+    ];
+    for (input, expected) in tests.iter() {
+        let prog = setup(input, 1);
+        assert_eq!(prog.to_string(), *expected);
+        assert!(matches!(
+            &prog.body[0],
+            Statement::Expression(stmt)
+                if stmt.expression.span() == Span::new(0, input.len())
+        ));
+    }
+    for input in ["a[1:2:3]", "a[1", "a[1:", "a[]"] {
+        assert!(Parser::new(Lexer::new(input)).parse_program().is_err());
+    }
+}
+
+#[test]
+fn test_destructuring() {
+    let tests = [
+        ("let [a, b, ...rest] = arr;", "let [a, b, ...rest] = arr;"),
+        ("let [...all] = arr;", "let [...all] = arr;"),
+        ("let [] = arr;", "let [] = arr;"),
+        (
+            r#"let {"x": x, "y": [y, z]} = p;"#,
+            "let {x:x, y:[y, z]} = p;",
+        ),
+        (r#"fn([a, b], {"k": v}) { a }"#, "([a, b], {k:v}) a"),
+    ];
+    for (input, expected) in tests.iter() {
+        assert_eq!(setup(input, 1).to_string(), *expected);
+    }
+    let prog = setup(r#"let {"x": [a, ...b]} = p;"#, 1);
+    match &prog.body[0] {
+        Statement::Let(stmt) => {
+            let names: Vec<(&str, Span)> = stmt
+                .pattern
+                .identifiers()
+                .into_iter()
+                .map(|ident| (ident.name.as_str(), ident.span))
+                .collect();
+            assert_eq!(names, [("a", Span::new(11, 12)), ("b", Span::new(17, 18))]);
+            assert_eq!(stmt.pattern.span(), Span::new(4, 20));
+        }
+        stmt => panic!("expected let statement but got {:?}", stmt),
+    }
+    for input in [
+        "let [...a, b] = c;",
+        "let [1] = c;",
+        "let {a} = c;",
+        "fn(...a) { a }",
+    ] {
+        assert!(
+            Parser::new(Lexer::new(input)).parse_program().is_err(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_match_expressions() {
+    let tests = [
+        (
+            r#"match (x) { 1 => "one", -1 => "minus one", _ => "other" }"#,
+            "match x { 1 => one, (-1) => minus one, _ => other }",
+        ),
+        (
+            "match (p) { [a, ...b] if a > 0 => b, {\"k\": true} => 1, n => n, }",
+            "match p { [a, ...b] if (a > 0) => b, {k:true} => 1, n => n }",
+        ),
+        ("match (x) {}", "match x {}"),
+        // like an `if` condition, the value needs no parentheses
+        ("match x { _ => 1 }", "match x { _ => 1 }"),
+        ("match (a) + b { _ => 1 }", "match (a + b) { _ => 1 }"),
+        (r#"match h["a"] { _ => 1 }"#, "match (h[a]) { _ => 1 }"),
+        (
+            r#"match {"a": 1} { {"a": v} => v }"#,
+            "match {a:1} { {a:v} => v }",
+        ),
+        ("match a ? b : c { _ => 1 }", "match (a ? b : c) { _ => 1 }"),
+    ];
+    for (input, expected) in tests.iter() {
+        assert_eq!(setup(input, 1).to_string(), *expected);
+    }
+    let prog = setup("match (x) { [a, _] => a }", 1);
+    match &prog.body[0] {
+        Statement::Expression(stmt) => match &stmt.expression {
+            Expression::Match(exp) => {
+                assert_eq!(exp.span, Span::new(0, 25));
+                assert_eq!(exp.arms[0].span, Span::new(12, 23));
+                let names: Vec<&str> = exp.arms[0]
+                    .pattern
+                    .identifiers()
+                    .into_iter()
+                    .map(|ident| ident.name.as_str())
+                    .collect();
+                assert_eq!(names, ["a"]);
+            }
+            exp => panic!("expected match expression but got {:?}", exp),
+        },
+        stmt => panic!("expected expression statement but got {:?}", stmt),
+    }
+    for input in [
+        "match { _ => 1 }",
+        "match x _ => 1",
+        "match (x) { _ 1 }",
+        "match (x) { a + 1 => 1 }",
+        "match (x) { 1 => 1 2 => 2 }",
+        "let [1] = c;",
+    ] {
+        assert!(
+            Parser::new(Lexer::new(input)).parse_program().is_err(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_error_messages() {
+    let tests = [
+        ("let = 1;", "invalid pattern ="),
+        ("1 + ;", "no prefix parse function for ; found"),
+        ("if x 1", "expected token: { got: 1"),
+        ("[1, 2", "expected token: ] got: end of file"),
+    ];
+    for (input, expected) in tests.iter() {
+        let errors = Parser::new(Lexer::new(input)).parse_program().unwrap_err();
+        assert_eq!(errors[0].message, *expected, "{}", input);
+    }
+}
@@ -0,0 +1,651 @@
+//! Traversal of the AST.
+//!
+//! `Visitor` walks a tree by shared reference and `VisitorMut` by mutable reference. Every
+//! method defaults to the matching `walk_*` function, which visits the children of the node, so
+//! an implementation only overrides the nodes it is interested in and calls the `walk_*`
+//! function itself to keep descending.
+//!
+//! ```
+//! use monkey_rs::parser::{self, node::{IdentifierExpression, Node}, visitor::Visitor};
+//!
+//! struct Identifiers(Vec<String>);
+//!
+//! impl<'ast> Visitor<'ast> for Identifiers {
+//!     fn visit_identifier(&mut self, ident: &'ast IdentifierExpression) {
+//!         self.0.push(ident.name.clone());
+//!     }
+//! }
+//!
+//! let node = parser::parse("let a = b + c(d);").unwrap();
+//! let mut names = Identifiers(Vec::new());
+//! names.visit_node(&node);
+//! assert_eq!(names.0, ["b", "c", "d"]);
+//! ```
+
+use super::node::{
+    Alternative, ArrayLiteral, BlockStatement, BooleanLiteral, CallExpression, Expression,
+    ExpressionStatement, FunctionLiteral, HashLiteral, IdentifierExpression, IfExpression,
+    ImportExpression, IndexExpression, InfixExpression, IntegerLiteral, InterpolationExpression,
+    LetStatement, MacroLiteral, MatchArm, MatchExpression, Node, Pattern, PrefixExpression,
+    Program, QuoteExpression, ReturnStatement, SliceExpression, Statement, StringLiteral,
+    TernaryExpression, UnquoteExpression,
+};
+
+pub trait Visitor<'ast> {
+    fn visit_node(&mut self, node: &'ast Node) {
+        walk_node(self, node)
+    }
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program)
+    }
+    fn visit_statement(&mut self, stmt: &'ast Statement) {
+        walk_statement(self, stmt)
+    }
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        walk_let_statement(self, stmt)
+    }
+    fn visit_return_statement(&mut self, stmt: &'ast ReturnStatement) {
+        walk_return_statement(self, stmt)
+    }
+    fn visit_expression_statement(&mut self, stmt: &'ast ExpressionStatement) {
+        walk_expression_statement(self, stmt)
+    }
+    fn visit_block_statement(&mut self, block: &'ast BlockStatement) {
+        walk_block_statement(self, block)
+    }
+    fn visit_expression(&mut self, exp: &'ast Expression) {
+        walk_expression(self, exp)
+    }
+    fn visit_integer(&mut self, _lit: &'ast IntegerLiteral) {}
+    fn visit_identifier(&mut self, _ident: &'ast IdentifierExpression) {}
+    fn visit_string(&mut self, _lit: &'ast StringLiteral) {}
+    fn visit_boolean(&mut self, _lit: &'ast BooleanLiteral) {}
+    fn visit_import_expression(&mut self, _exp: &'ast ImportExpression) {}
+    fn visit_interpolation_expression(&mut self, exp: &'ast InterpolationExpression) {
+        walk_interpolation_expression(self, exp)
+    }
+    fn visit_match_expression(&mut self, exp: &'ast MatchExpression) {
+        walk_match_expression(self, exp)
+    }
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm) {
+        walk_match_arm(self, arm)
+    }
+    fn visit_prefix_expression(&mut self, exp: &'ast PrefixExpression) {
+        walk_prefix_expression(self, exp)
+    }
+    fn visit_infix_expression(&mut self, exp: &'ast InfixExpression) {
+        walk_infix_expression(self, exp)
+    }
+    fn visit_if_expression(&mut self, exp: &'ast IfExpression) {
+        walk_if_expression(self, exp)
+    }
+    fn visit_ternary_expression(&mut self, exp: &'ast TernaryExpression) {
+        walk_ternary_expression(self, exp)
+    }
+    fn visit_function_literal(&mut self, func: &'ast FunctionLiteral) {
+        walk_function_literal(self, func)
+    }
+    /// Visits a parameter of a function literal, whose names are bound rather than read.
+    fn visit_parameter(&mut self, param: &'ast Pattern) {
+        self.visit_pattern(param)
+    }
+    /// Visits the pattern of a `let`, a parameter or a `match` arm. Only its literals and the
+    /// keys of hash patterns are visited as expressions, the names it binds are left to the
+    /// implementation.
+    fn visit_pattern(&mut self, pat: &'ast Pattern) {
+        walk_pattern(self, pat)
+    }
+    fn visit_call_expression(&mut self, exp: &'ast CallExpression) {
+        walk_call_expression(self, exp)
+    }
+    fn visit_array_literal(&mut self, array: &'ast ArrayLiteral) {
+        walk_array_literal(self, array)
+    }
+    fn visit_index_expression(&mut self, exp: &'ast IndexExpression) {
+        walk_index_expression(self, exp)
+    }
+    fn visit_slice_expression(&mut self, exp: &'ast SliceExpression) {
+        walk_slice_expression(self, exp)
+    }
+    fn visit_hash_literal(&mut self, hash: &'ast HashLiteral) {
+        walk_hash_literal(self, hash)
+    }
+    fn visit_quote_expression(&mut self, exp: &'ast QuoteExpression) {
+        walk_quote_expression(self, exp)
+    }
+    fn visit_unquote_expression(&mut self, exp: &'ast UnquoteExpression) {
+        walk_unquote_expression(self, exp)
+    }
+    fn visit_macro_literal(&mut self, mac: &'ast MacroLiteral) {
+        walk_macro_literal(self, mac)
+    }
+}
+
+pub fn walk_node<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, node: &'ast Node) {
+    match node {
+        Node::Program(p) => v.visit_program(p),
+        Node::Statement(stmt) => v.visit_statement(stmt),
+        Node::Expression(exp) => v.visit_expression(exp),
+    }
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, program: &'ast Program) {
+    for stmt in &program.body {
+        v.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, stmt: &'ast Statement) {
+    match stmt {
+        Statement::Let(stmt) => v.visit_let_statement(stmt),
+        Statement::Return(stmt) => v.visit_return_statement(stmt),
+        Statement::Expression(stmt) => v.visit_expression_statement(stmt),
+    }
+}
+
+pub fn walk_let_statement<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, stmt: &'ast LetStatement) {
+    v.visit_expression(&stmt.value);
+    v.visit_pattern(&stmt.pattern);
+}
+
+pub fn walk_pattern<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, pat: &'ast Pattern) {
+    match pat {
+        Pattern::Identifier(_) | Pattern::Wildcard { .. } => {}
+        Pattern::Literal(lit) => v.visit_expression(lit),
+        Pattern::Array(array) => {
+            for el in &array.elements {
+                v.visit_pattern(el);
+            }
+        }
+        Pattern::Hash(hash) => {
+            for (key, value) in &hash.pairs {
+                v.visit_expression(key);
+                v.visit_pattern(value);
+            }
+        }
+    }
+}
+
+pub fn walk_return_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    stmt: &'ast ReturnStatement,
+) {
+    v.visit_expression(&stmt.value);
+}
+
+pub fn walk_expression_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    stmt: &'ast ExpressionStatement,
+) {
+    v.visit_expression(&stmt.expression);
+}
+
+pub fn walk_block_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    block: &'ast BlockStatement,
+) {
+    for stmt in &block.statements {
+        v.visit_statement(stmt);
+    }
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, exp: &'ast Expression) {
+    match exp {
+        Expression::Integer(lit) => v.visit_integer(lit),
+        Expression::Identifier(ident) => v.visit_identifier(ident),
+        Expression::String(lit) => v.visit_string(lit),
+        Expression::Boolean(lit) => v.visit_boolean(lit),
+        Expression::Prefix(exp) => v.visit_prefix_expression(exp),
+        Expression::Infix(exp) => v.visit_infix_expression(exp),
+        Expression::If(exp) => v.visit_if_expression(exp),
+        Expression::Ternary(exp) => v.visit_ternary_expression(exp),
+        Expression::Function(func) => v.visit_function_literal(func),
+        Expression::Call(exp) => v.visit_call_expression(exp),
+        Expression::Array(array) => v.visit_array_literal(array),
+        Expression::Index(exp) => v.visit_index_expression(exp),
+        Expression::Slice(exp) => v.visit_slice_expression(exp),
+        Expression::Hash(hash) => v.visit_hash_literal(hash),
+        Expression::Quote(exp) => v.visit_quote_expression(exp),
+        Expression::Unquote(exp) => v.visit_unquote_expression(exp),
+        Expression::Macro(mac) => v.visit_macro_literal(mac),
+        Expression::Import(exp) => v.visit_import_expression(exp),
+        Expression::Interpolation(exp) => v.visit_interpolation_expression(exp),
+        Expression::Match(exp) => v.visit_match_expression(exp),
+    }
+}
+
+pub fn walk_prefix_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast PrefixExpression,
+) {
+    v.visit_expression(&exp.right);
+}
+
+pub fn walk_infix_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast InfixExpression,
+) {
+    v.visit_expression(&exp.left);
+    v.visit_expression(&exp.right);
+}
+
+pub fn walk_if_expression<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, exp: &'ast IfExpression) {
+    v.visit_expression(&exp.condition);
+    v.visit_block_statement(&exp.consequence);
+    match &exp.alternative {
+        Some(Alternative::Block(alt)) => v.visit_block_statement(alt),
+        Some(Alternative::If(alt)) => v.visit_if_expression(alt),
+        None => {}
+    }
+}
+
+pub fn walk_ternary_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast TernaryExpression,
+) {
+    v.visit_expression(&exp.condition);
+    v.visit_expression(&exp.consequence);
+    v.visit_expression(&exp.alternative);
+}
+
+pub fn walk_function_literal<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    func: &'ast FunctionLiteral,
+) {
+    for param in &func.parameters {
+        v.visit_parameter(param);
+    }
+    v.visit_block_statement(&func.body);
+}
+
+pub fn walk_call_expression<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, exp: &'ast CallExpression) {
+    v.visit_expression(&exp.function);
+    for arg in &exp.arguments {
+        v.visit_expression(arg);
+    }
+}
+
+pub fn walk_array_literal<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, array: &'ast ArrayLiteral) {
+    for e in &array.elements {
+        v.visit_expression(e);
+    }
+}
+
+pub fn walk_interpolation_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast InterpolationExpression,
+) {
+    for part in &exp.parts {
+        v.visit_expression(part);
+    }
+}
+
+pub fn walk_match_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast MatchExpression,
+) {
+    v.visit_expression(&exp.value);
+    for arm in &exp.arms {
+        v.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, arm: &'ast MatchArm) {
+    v.visit_pattern(&arm.pattern);
+    if let Some(guard) = &arm.guard {
+        v.visit_expression(guard);
+    }
+    v.visit_expression(&arm.body);
+}
+
+pub fn walk_index_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast IndexExpression,
+) {
+    v.visit_expression(&exp.left);
+    v.visit_expression(&exp.index);
+}
+
+pub fn walk_slice_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast SliceExpression,
+) {
+    v.visit_expression(&exp.left);
+    for bound in exp.start.iter().chain(&exp.end) {
+        v.visit_expression(bound);
+    }
+}
+
+pub fn walk_hash_literal<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, hash: &'ast HashLiteral) {
+    for (key, value) in &hash.pairs {
+        v.visit_expression(key);
+        v.visit_expression(value);
+    }
+}
+
+pub fn walk_quote_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast QuoteExpression,
+) {
+    v.visit_expression(&exp.expression);
+}
+
+/// Visits only the `unquote`s of a quote, the parts of it evaluated where the quote is, leaving
+/// out the ones of nested quotes.
+pub fn walk_unquoted<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, exp: &'ast QuoteExpression) {
+    struct Unquoted<'v, V: ?Sized>(&'v mut V);
+
+    impl<'ast, V: Visitor<'ast> + ?Sized> Visitor<'ast> for Unquoted<'_, V> {
+        fn visit_unquote_expression(&mut self, exp: &'ast UnquoteExpression) {
+            self.0.visit_unquote_expression(exp);
+        }
+        fn visit_quote_expression(&mut self, _exp: &'ast QuoteExpression) {}
+    }
+
+    Unquoted(v).visit_expression(&exp.expression);
+}
+
+pub fn walk_unquote_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    exp: &'ast UnquoteExpression,
+) {
+    v.visit_expression(&exp.expression);
+}
+
+pub fn walk_macro_literal<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, mac: &'ast MacroLiteral) {
+    for param in &mac.parameters {
+        v.visit_parameter(param);
+    }
+    v.visit_block_statement(&mac.body);
+}
+
+/// Like `Visitor`, but with mutable access to the nodes, e.g. to rewrite them in place.
+pub trait VisitorMut {
+    fn visit_node_mut(&mut self, node: &mut Node) {
+        walk_node_mut(self, node)
+    }
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+    fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+        walk_statement_mut(self, stmt)
+    }
+    fn visit_let_statement_mut(&mut self, stmt: &mut LetStatement) {
+        walk_let_statement_mut(self, stmt)
+    }
+    fn visit_return_statement_mut(&mut self, stmt: &mut ReturnStatement) {
+        walk_return_statement_mut(self, stmt)
+    }
+    fn visit_expression_statement_mut(&mut self, stmt: &mut ExpressionStatement) {
+        walk_expression_statement_mut(self, stmt)
+    }
+    fn visit_block_statement_mut(&mut self, block: &mut BlockStatement) {
+        walk_block_statement_mut(self, block)
+    }
+    fn visit_expression_mut(&mut self, exp: &mut Expression) {
+        walk_expression_mut(self, exp)
+    }
+    fn visit_integer_mut(&mut self, _lit: &mut IntegerLiteral) {}
+    fn visit_identifier_mut(&mut self, _ident: &mut IdentifierExpression) {}
+    fn visit_string_mut(&mut self, _lit: &mut StringLiteral) {}
+    fn visit_boolean_mut(&mut self, _lit: &mut BooleanLiteral) {}
+    fn visit_import_expression_mut(&mut self, _exp: &mut ImportExpression) {}
+    fn visit_interpolation_expression_mut(&mut self, exp: &mut InterpolationExpression) {
+        walk_interpolation_expression_mut(self, exp)
+    }
+    fn visit_match_expression_mut(&mut self, exp: &mut MatchExpression) {
+        walk_match_expression_mut(self, exp)
+    }
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        walk_match_arm_mut(self, arm)
+    }
+    fn visit_prefix_expression_mut(&mut self, exp: &mut PrefixExpression) {
+        walk_prefix_expression_mut(self, exp)
+    }
+    fn visit_infix_expression_mut(&mut self, exp: &mut InfixExpression) {
+        walk_infix_expression_mut(self, exp)
+    }
+    fn visit_if_expression_mut(&mut self, exp: &mut IfExpression) {
+        walk_if_expression_mut(self, exp)
+    }
+    fn visit_ternary_expression_mut(&mut self, exp: &mut TernaryExpression) {
+        walk_ternary_expression_mut(self, exp)
+    }
+    fn visit_function_literal_mut(&mut self, func: &mut FunctionLiteral) {
+        walk_function_literal_mut(self, func)
+    }
+    fn visit_parameter_mut(&mut self, param: &mut Pattern) {
+        self.visit_pattern_mut(param)
+    }
+    fn visit_pattern_mut(&mut self, pat: &mut Pattern) {
+        walk_pattern_mut(self, pat)
+    }
+    fn visit_call_expression_mut(&mut self, exp: &mut CallExpression) {
+        walk_call_expression_mut(self, exp)
+    }
+    fn visit_array_literal_mut(&mut self, array: &mut ArrayLiteral) {
+        walk_array_literal_mut(self, array)
+    }
+    fn visit_index_expression_mut(&mut self, exp: &mut IndexExpression) {
+        walk_index_expression_mut(self, exp)
+    }
+    fn visit_slice_expression_mut(&mut self, exp: &mut SliceExpression) {
+        walk_slice_expression_mut(self, exp)
+    }
+    fn visit_hash_literal_mut(&mut self, hash: &mut HashLiteral) {
+        walk_hash_literal_mut(self, hash)
+    }
+    fn visit_quote_expression_mut(&mut self, exp: &mut QuoteExpression) {
+        walk_quote_expression_mut(self, exp)
+    }
+    fn visit_unquote_expression_mut(&mut self, exp: &mut UnquoteExpression) {
+        walk_unquote_expression_mut(self, exp)
+    }
+    fn visit_macro_literal_mut(&mut self, mac: &mut MacroLiteral) {
+        walk_macro_literal_mut(self, mac)
+    }
+}
+
+pub fn walk_node_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Node) {
+    match node {
+        Node::Program(p) => v.visit_program_mut(p),
+        Node::Statement(stmt) => v.visit_statement_mut(stmt),
+        Node::Expression(exp) => v.visit_expression_mut(exp),
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(v: &mut V, program: &mut Program) {
+    for stmt in &mut program.body {
+        v.visit_statement_mut(stmt);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::Let(stmt) => v.visit_let_statement_mut(stmt),
+        Statement::Return(stmt) => v.visit_return_statement_mut(stmt),
+        Statement::Expression(stmt) => v.visit_expression_statement_mut(stmt),
+    }
+}
+
+pub fn walk_let_statement_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut LetStatement) {
+    v.visit_expression_mut(&mut stmt.value);
+    v.visit_pattern_mut(&mut stmt.pattern);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut + ?Sized>(v: &mut V, pat: &mut Pattern) {
+    match pat {
+        Pattern::Identifier(_) | Pattern::Wildcard { .. } => {}
+        Pattern::Literal(lit) => v.visit_expression_mut(lit),
+        Pattern::Array(array) => {
+            for el in &mut array.elements {
+                v.visit_pattern_mut(el);
+            }
+        }
+        Pattern::Hash(hash) => {
+            for (key, value) in &mut hash.pairs {
+                v.visit_expression_mut(key);
+                v.visit_pattern_mut(value);
+            }
+        }
+    }
+}
+
+pub fn walk_return_statement_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut ReturnStatement) {
+    v.visit_expression_mut(&mut stmt.value);
+}
+
+pub fn walk_expression_statement_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    stmt: &mut ExpressionStatement,
+) {
+    v.visit_expression_mut(&mut stmt.expression);
+}
+
+pub fn walk_block_statement_mut<V: VisitorMut + ?Sized>(v: &mut V, block: &mut BlockStatement) {
+    for stmt in &mut block.statements {
+        v.visit_statement_mut(stmt);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut Expression) {
+    match exp {
+        Expression::Integer(lit) => v.visit_integer_mut(lit),
+        Expression::Identifier(ident) => v.visit_identifier_mut(ident),
+        Expression::String(lit) => v.visit_string_mut(lit),
+        Expression::Boolean(lit) => v.visit_boolean_mut(lit),
+        Expression::Prefix(exp) => v.visit_prefix_expression_mut(exp),
+        Expression::Infix(exp) => v.visit_infix_expression_mut(exp),
+        Expression::If(exp) => v.visit_if_expression_mut(exp),
+        Expression::Ternary(exp) => v.visit_ternary_expression_mut(exp),
+        Expression::Function(func) => v.visit_function_literal_mut(func),
+        Expression::Call(exp) => v.visit_call_expression_mut(exp),
+        Expression::Array(array) => v.visit_array_literal_mut(array),
+        Expression::Index(exp) => v.visit_index_expression_mut(exp),
+        Expression::Slice(exp) => v.visit_slice_expression_mut(exp),
+        Expression::Hash(hash) => v.visit_hash_literal_mut(hash),
+        Expression::Quote(exp) => v.visit_quote_expression_mut(exp),
+        Expression::Unquote(exp) => v.visit_unquote_expression_mut(exp),
+        Expression::Macro(mac) => v.visit_macro_literal_mut(mac),
+        Expression::Import(exp) => v.visit_import_expression_mut(exp),
+        Expression::Interpolation(exp) => v.visit_interpolation_expression_mut(exp),
+        Expression::Match(exp) => v.visit_match_expression_mut(exp),
+    }
+}
+
+pub fn walk_prefix_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut PrefixExpression) {
+    v.visit_expression_mut(&mut exp.right);
+}
+
+pub fn walk_infix_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut InfixExpression) {
+    v.visit_expression_mut(&mut exp.left);
+    v.visit_expression_mut(&mut exp.right);
+}
+
+pub fn walk_if_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut IfExpression) {
+    v.visit_expression_mut(&mut exp.condition);
+    v.visit_block_statement_mut(&mut exp.consequence);
+    match &mut exp.alternative {
+        Some(Alternative::Block(alt)) => v.visit_block_statement_mut(alt),
+        Some(Alternative::If(alt)) => v.visit_if_expression_mut(alt),
+        None => {}
+    }
+}
+
+pub fn walk_ternary_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut TernaryExpression) {
+    v.visit_expression_mut(&mut exp.condition);
+    v.visit_expression_mut(&mut exp.consequence);
+    v.visit_expression_mut(&mut exp.alternative);
+}
+
+pub fn walk_function_literal_mut<V: VisitorMut + ?Sized>(v: &mut V, func: &mut FunctionLiteral) {
+    for param in &mut func.parameters {
+        v.visit_parameter_mut(param);
+    }
+    v.visit_block_statement_mut(&mut func.body);
+}
+
+pub fn walk_call_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut CallExpression) {
+    v.visit_expression_mut(&mut exp.function);
+    for arg in &mut exp.arguments {
+        v.visit_expression_mut(arg);
+    }
+}
+
+pub fn walk_array_literal_mut<V: VisitorMut + ?Sized>(v: &mut V, array: &mut ArrayLiteral) {
+    for e in &mut array.elements {
+        v.visit_expression_mut(e);
+    }
+}
+
+pub fn walk_interpolation_expression_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    exp: &mut InterpolationExpression,
+) {
+    for part in &mut exp.parts {
+        v.visit_expression_mut(part);
+    }
+}
+
+pub fn walk_match_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut MatchExpression) {
+    v.visit_expression_mut(&mut exp.value);
+    for arm in &mut exp.arms {
+        v.visit_match_arm_mut(arm);
+    }
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut + ?Sized>(v: &mut V, arm: &mut MatchArm) {
+    v.visit_pattern_mut(&mut arm.pattern);
+    if let Some(guard) = &mut arm.guard {
+        v.visit_expression_mut(guard);
+    }
+    v.visit_expression_mut(&mut arm.body);
+}
+
+pub fn walk_index_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut IndexExpression) {
+    v.visit_expression_mut(&mut exp.left);
+    v.visit_expression_mut(&mut exp.index);
+}
+
+pub fn walk_slice_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut SliceExpression) {
+    v.visit_expression_mut(&mut exp.left);
+    for bound in exp.start.iter_mut().chain(&mut exp.end) {
+        v.visit_expression_mut(bound);
+    }
+}
+
+pub fn walk_hash_literal_mut<V: VisitorMut + ?Sized>(v: &mut V, hash: &mut HashLiteral) {
+    for (key, value) in &mut hash.pairs {
+        v.visit_expression_mut(key);
+        v.visit_expression_mut(value);
+    }
+}
+
+pub fn walk_quote_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut QuoteExpression) {
+    v.visit_expression_mut(&mut exp.expression);
+}
+
+/// Like `walk_unquoted`, with mutable access.
+pub fn walk_unquoted_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut QuoteExpression) {
+    struct Unquoted<'v, V: ?Sized>(&'v mut V);
+
+    impl<V: VisitorMut + ?Sized> VisitorMut for Unquoted<'_, V> {
+        fn visit_unquote_expression_mut(&mut self, exp: &mut UnquoteExpression) {
+            self.0.visit_unquote_expression_mut(exp);
+        }
+        fn visit_quote_expression_mut(&mut self, _exp: &mut QuoteExpression) {}
+    }
+
+    Unquoted(v).visit_expression_mut(&mut exp.expression);
+}
+
+pub fn walk_unquote_expression_mut<V: VisitorMut + ?Sized>(v: &mut V, exp: &mut UnquoteExpression) {
+    v.visit_expression_mut(&mut exp.expression);
+}
+
+pub fn walk_macro_literal_mut<V: VisitorMut + ?Sized>(v: &mut V, mac: &mut MacroLiteral) {
+    for param in &mut mac.parameters {
+        v.visit_parameter_mut(param);
+    }
+    v.visit_block_statement_mut(&mut mac.body);
+}
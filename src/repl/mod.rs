@@ -0,0 +1,57 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::{
+    evaluator::{Context, Output},
+    interpreter::{Interpreter, InterpreterError},
+};
+
+/// Forwards writes to a writer shared between the REPL and the interpreter's output.
+struct SharedWriter<W>(Rc<RefCell<W>>);
+
+impl<W: io::Write> io::Write for SharedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+pub fn start<R: io::BufRead, W: io::Write + 'static>(mut reader: R, writer: W) -> io::Result<()> {
+    let writer = Rc::new(RefCell::new(writer));
+    let ctx = Context::new();
+    ctx.set_output(Output::Writer(Box::new(SharedWriter(Rc::clone(&writer)))));
+    let mut interp = Interpreter::with_context(ctx);
+    let mut writer = SharedWriter(writer);
+    loop {
+        writer.write_all(b"> ")?;
+        writer.flush()?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        if line.trim_end().is_empty() {
+            writeln!(writer, "bye")?;
+            return Ok(());
+        }
+        match interp.eval_str(&line) {
+            Ok(obj) => {
+                writeln!(writer, "{}", obj)?;
+            }
+            Err(InterpreterError::Parse(errors)) => {
+                for err in errors {
+                    writeln!(writer, "parse errors:\n{}", err)?;
+                }
+            }
+            Err(err) => {
+                writeln!(writer, "error: {}", err)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,13 @@
+use super::*;
+
+#[test]
+fn test_repl_output() {
+    let input = "let a = 2;\nputs(a * 3)\nfoo\n\n";
+    let out = Rc::new(RefCell::new(Vec::new()));
+    start(input.as_bytes(), SharedWriter(Rc::clone(&out))).unwrap();
+
+    assert_eq!(
+        String::from_utf8(out.borrow().clone()).unwrap(),
+        "> 2\n> 6\nnull\n> error: identifier not found: foo\n> bye\n"
+    );
+}
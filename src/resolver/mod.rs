@@ -0,0 +1,319 @@
+//! Static scope resolution.
+//!
+//! The resolver binds every identifier of a program to the place its value lives at runtime, so
+//! the evaluator can read locals by slot instead of searching the environments by name, and
+//! reports undefined and shadowed names before anything runs.
+//!
+//! A name no scope binds is still resolved as a global: the host or a later evaluation may
+//! define it before it is read, and reading it while it is missing fails at runtime.
+//!
+//! Like the evaluator, function bodies and `match` arms open a scope: a `let` inside an `if` block
+//! belongs to the enclosing function or arm. The names bound by the parameters, or by the pattern
+//! of an arm, take the first slots of a scope, followed by one slot per distinct name bound with
+//! `let`. Top-level bindings stay globals looked up by name, since the host and earlier
+//! evaluations can define them too.
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    lexer::token::Span,
+    object::Builtin,
+    parser::{
+        node::{
+            Binding, FunctionLiteral, IdentifierExpression, LetStatement, MacroLiteral, MatchArm,
+            Node, Pattern, Program, QuoteExpression, Statement,
+        },
+        visitor::{
+            walk_function_literal_mut, walk_let_statement, walk_let_statement_mut,
+            walk_macro_literal_mut, walk_pattern_mut, walk_unquoted_mut, Visitor, VisitorMut,
+        },
+    },
+};
+
+pub(crate) mod symbols;
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    /// The name is not bound anywhere in sight, so reading it fails unless it is defined as a
+    /// global by then.
+    Undefined,
+    /// A parameter or `let` hides a binding of an enclosing scope.
+    Shadowed,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub name: String,
+    /// Where the offending identifier, parameter or `let` name appears.
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DiagnosticKind::Undefined => write!(f, "identifier not found: {}", self.name),
+            DiagnosticKind::Shadowed => write!(f, "{} shadows an outer binding", self.name),
+        }
+    }
+}
+
+struct Scope {
+    // every name bound in the scope, including the ones bound further down
+    slots: HashMap<String, usize>,
+    // the names bound so far while walking the scope
+    declared: HashSet<String>,
+    // set for the scope of a `match` arm, which runs as soon as it is entered
+    arm: bool,
+}
+
+pub struct Resolver {
+    globals: HashSet<String>,
+    scopes: Vec<Scope>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves a program that only uses its own bindings and the builtins.
+pub fn resolve(program: &mut Program) -> Vec<Diagnostic> {
+    Resolver::new().resolve_program(program)
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::with_globals(Vec::new())
+    }
+
+    /// Creates a resolver that knows `globals` are defined, e.g. by the host or an earlier
+    /// evaluation in the same environment.
+    pub fn with_globals<I: IntoIterator<Item = String>>(globals: I) -> Self {
+        Resolver {
+            globals: globals.into_iter().collect(),
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn resolve_program(mut self, program: &mut Program) -> Vec<Diagnostic> {
+        self.enter_scope(&[], scope_bindings(&program.body), false);
+        self.visit_program_mut(program);
+        self.diagnostics
+    }
+
+    pub fn resolve_node(mut self, node: &mut Node) -> Vec<Diagnostic> {
+        let mut bindings = Bindings(Vec::new());
+        bindings.visit_node(node);
+        self.enter_scope(&[], bindings.0, false);
+        self.visit_node_mut(node);
+        self.diagnostics
+    }
+
+    fn enter_scope(
+        &mut self,
+        parameters: &[IdentifierExpression],
+        names: Vec<IdentifierExpression>,
+        arm: bool,
+    ) {
+        let mut slots = HashMap::new();
+        // a repeated parameter binds its last argument, as it would in a store keyed by name
+        for (i, param) in parameters.iter().enumerate() {
+            if !slots.contains_key(&param.name) {
+                self.check_shadowing(param);
+            }
+            slots.insert(param.name.clone(), i);
+        }
+        let mut next = parameters.len();
+        for ident in names {
+            if let Entry::Vacant(entry) = slots.entry(ident.name.clone()) {
+                entry.insert(next);
+                next += 1;
+                self.check_shadowing(&ident);
+            }
+        }
+        self.scopes.push(Scope {
+            slots,
+            declared: parameters.iter().map(|p| p.name.clone()).collect(),
+            arm,
+        });
+    }
+
+    fn check_shadowing(&mut self, ident: &IdentifierExpression) {
+        let outer = self
+            .scopes
+            .iter()
+            .any(|s| s.slots.contains_key(&ident.name));
+        if !self.scopes.is_empty() && (outer || self.globals.contains(&ident.name)) {
+            self.report(DiagnosticKind::Shadowed, ident);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        let innermost = self.scopes.len() - 1;
+        let mut in_function = false;
+        for (i, scope) in self.scopes.iter().enumerate().rev() {
+            // a function may be called after the enclosing scope has bound more names, so
+            // from inside one every binding of the enclosing scopes is visible, while an arm
+            // runs as soon as it is reached and only sees what is bound so far
+            let visible = if in_function {
+                scope.slots.contains_key(name)
+            } else {
+                scope.declared.contains(name)
+            };
+            in_function |= !scope.arm;
+            if !visible {
+                continue;
+            }
+            return Some(match i {
+                0 => Binding::Global,
+                _ => Binding::Local {
+                    depth: innermost - i,
+                    slot: scope.slots[name],
+                },
+            });
+        }
+        if self.globals.contains(name) || Builtin::lookup(name).is_some() {
+            return Some(Binding::Global);
+        }
+        None
+    }
+
+    fn declare(&mut self, name: &str) -> Binding {
+        let depth = self.scopes.len() - 1;
+        let scope = self.scopes.last_mut().unwrap();
+        scope.declared.insert(name.to_string());
+        match depth {
+            0 => Binding::Global,
+            _ => Binding::Local {
+                depth: 0,
+                slot: scope.slots[name],
+            },
+        }
+    }
+
+    fn report(&mut self, kind: DiagnosticKind, ident: &IdentifierExpression) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            name: ident.name.clone(),
+            span: ident.span,
+        });
+    }
+}
+
+impl VisitorMut for Resolver {
+    fn visit_let_statement_mut(&mut self, stmt: &mut LetStatement) {
+        walk_let_statement_mut(self, stmt);
+        for ident in stmt.pattern.identifiers_mut() {
+            ident.binding = Some(self.declare(&ident.name));
+        }
+    }
+
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        self.visit_pattern_mut(&mut arm.pattern);
+        let names = arm
+            .pattern
+            .identifiers()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.enter_scope(&names, arm_bindings(arm), true);
+        for ident in arm.pattern.identifiers_mut() {
+            ident.binding = self.lookup(&ident.name);
+        }
+        if let Some(guard) = &mut arm.guard {
+            self.visit_expression_mut(guard);
+        }
+        self.visit_expression_mut(&mut arm.body);
+        self.scopes.pop();
+    }
+
+    fn visit_identifier_mut(&mut self, ident: &mut IdentifierExpression) {
+        ident.binding = self.lookup(&ident.name);
+        if ident.binding.is_none() {
+            self.report(DiagnosticKind::Undefined, ident);
+            ident.binding = Some(Binding::Global);
+        }
+    }
+
+    fn visit_function_literal_mut(&mut self, func: &mut FunctionLiteral) {
+        let parameters = parameter_names(&func.parameters);
+        self.enter_scope(&parameters, scope_bindings(&func.body.statements), false);
+        walk_function_literal_mut(self, func);
+        self.scopes.pop();
+    }
+
+    fn visit_macro_literal_mut(&mut self, mac: &mut MacroLiteral) {
+        let parameters = parameter_names(&mac.parameters);
+        self.enter_scope(&parameters, scope_bindings(&mac.body.statements), false);
+        walk_macro_literal_mut(self, mac);
+        self.scopes.pop();
+    }
+
+    fn visit_parameter_mut(&mut self, param: &mut Pattern) {
+        walk_pattern_mut(self, param);
+        for ident in param.identifiers_mut() {
+            ident.binding = self.lookup(&ident.name);
+        }
+    }
+
+    // quoted code is resolved where it ends up, only its unquotes are evaluated here
+    fn visit_quote_expression_mut(&mut self, exp: &mut QuoteExpression) {
+        walk_unquoted_mut(self, exp);
+    }
+}
+
+/// Collects the names bound by `let` in a scope, without descending into nested functions or
+/// `match` arms.
+struct Bindings(Vec<IdentifierExpression>);
+
+impl<'ast> Visitor<'ast> for Bindings {
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        walk_let_statement(self, stmt);
+        self.0
+            .extend(stmt.pattern.identifiers().into_iter().cloned());
+    }
+
+    fn visit_match_arm(&mut self, _arm: &'ast MatchArm) {}
+
+    fn visit_function_literal(&mut self, _func: &'ast FunctionLiteral) {}
+
+    fn visit_macro_literal(&mut self, _mac: &'ast MacroLiteral) {}
+}
+
+/// The names bound by `parameters`, in order of appearance.
+pub(crate) fn parameter_names(parameters: &[Pattern]) -> Vec<IdentifierExpression> {
+    parameters
+        .iter()
+        .flat_map(Pattern::identifiers)
+        .cloned()
+        .collect()
+}
+
+/// The names bound by `let` in the scope made of `stmts`, in order of appearance. The names
+/// bound inside `match` arms belong to the arms, see [`arm_bindings`].
+pub(crate) fn scope_bindings(stmts: &[Statement]) -> Vec<IdentifierExpression> {
+    let mut bindings = Bindings(Vec::new());
+    for stmt in stmts {
+        bindings.visit_statement(stmt);
+    }
+    bindings.0
+}
+
+/// The names bound by `let` in the guard and body of `arm`, in order of appearance.
+pub(crate) fn arm_bindings(arm: &MatchArm) -> Vec<IdentifierExpression> {
+    let mut bindings = Bindings(Vec::new());
+    if let Some(guard) = &arm.guard {
+        bindings.visit_expression(guard);
+    }
+    bindings.visit_expression(&arm.body);
+    bindings.0
+}
@@ -0,0 +1,295 @@
+//! Which binding every identifier of a program reads, for tools that point back at the source.
+//!
+//! Names are looked up the way the resolver binds them: a function body sees every binding of
+//! the enclosing scopes, the scope being walked and the `match` arms it runs only the ones made
+//! so far. When a name is bound more than once, a read refers to the latest binding made before
+//! it, or, from inside a function, to the first binding of the enclosing scope if none was made
+//! yet.
+
+use std::collections::HashMap;
+
+use super::{arm_bindings, parameter_names, scope_bindings};
+use crate::{
+    lexer::token::Span,
+    object::Builtin,
+    parser::{
+        node::{
+            Expression, FunctionLiteral, IdentifierExpression, LetStatement, MacroLiteral,
+            MatchArm, Pattern, Program, QuoteExpression,
+        },
+        visitor::{walk_block_statement, walk_let_statement, walk_unquoted, Visitor},
+    },
+};
+
+/// What a binding holds, as far as can be told without running the program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ValueKind {
+    Integer,
+    String,
+    Boolean,
+    Array,
+    Hash,
+    /// A function literal, with the names of its parameters.
+    Function(Vec<String>),
+    Unknown,
+}
+
+impl ValueKind {
+    fn of(exp: &Expression, symbols: &Symbols) -> ValueKind {
+        match exp {
+            Expression::Integer(_) => ValueKind::Integer,
+            Expression::String(_) => ValueKind::String,
+            Expression::Boolean(_) => ValueKind::Boolean,
+            Expression::Array(_) => ValueKind::Array,
+            Expression::Hash(_) => ValueKind::Hash,
+            Expression::Function(func) => {
+                ValueKind::Function(func.parameters.iter().map(|p| p.to_string()).collect())
+            }
+            Expression::Identifier(ident) => match symbols.reference(ident.span) {
+                Some(Target::Definition(id)) => symbols.definitions[id].value.clone(),
+                _ => ValueKind::Unknown,
+            },
+            _ => ValueKind::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Integer => "integer",
+            ValueKind::String => "string",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Array => "array",
+            ValueKind::Hash => "hash",
+            ValueKind::Function(_) => "function",
+            ValueKind::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum DefinitionKind {
+    Let,
+    Parameter,
+    /// A name bound by the pattern of a `match` arm.
+    MatchBinding,
+}
+
+#[derive(Debug)]
+pub(crate) struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// The name where it is bound.
+    pub span: Span,
+    /// The function literal or `match` arm the binding is visible in, or the whole source for
+    /// globals.
+    pub scope: Span,
+    pub value: ValueKind,
+    /// Declared with `export let`, so read by the modules importing it.
+    pub exported: bool,
+}
+
+/// What an identifier refers to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Target {
+    Definition(usize),
+    Builtin(Builtin),
+    Undefined,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Symbols {
+    pub definitions: Vec<Definition>,
+    /// Every identifier of the program, definitions included, with what it refers to, in
+    /// source order.
+    pub references: Vec<(Span, Target)>,
+}
+
+impl Symbols {
+    pub fn new(program: &Program) -> Self {
+        let mut symbols = Symbols::default();
+        let mut binder = Binder {
+            symbols: &mut symbols,
+            scopes: Vec::new(),
+        };
+        let globals = Span::new(0, usize::MAX);
+        binder.enter_scope(
+            globals,
+            &[],
+            DefinitionKind::Parameter,
+            scope_bindings(&program.body),
+        );
+        binder.visit_program(program);
+        symbols.references.sort_by_key(|(span, _)| span.start);
+        symbols
+    }
+
+    /// What the identifier spanning `span` refers to.
+    pub fn reference(&self, span: Span) -> Option<Target> {
+        self.references
+            .iter()
+            .find(|(s, _)| *s == span)
+            .map(|(_, target)| *target)
+    }
+
+    /// The identifier under `offset`, counting the offset just past its end.
+    pub fn reference_at(&self, offset: usize) -> Option<(Span, Target)> {
+        self.references
+            .iter()
+            .find(|(span, _)| span.start <= offset && offset <= span.end)
+            .copied()
+    }
+
+    /// How many identifiers read the definition `id`.
+    pub fn reads(&self, id: usize) -> usize {
+        let span = self.definitions[id].span;
+        self.references
+            .iter()
+            .filter(|(s, target)| *target == Target::Definition(id) && *s != span)
+            .count()
+    }
+}
+
+struct Scope {
+    // the latest binding of each name made so far while walking the scope
+    declared: HashMap<String, usize>,
+    // the first binding of each name anywhere in the scope
+    first: HashMap<String, usize>,
+    // the binding made by each `let` of the scope, by the span of its name
+    lets: HashMap<Span, usize>,
+    // set for the scope of a `match` arm, which runs as soon as it is entered
+    arm: bool,
+}
+
+struct Binder<'a> {
+    symbols: &'a mut Symbols,
+    scopes: Vec<Scope>,
+}
+
+impl Binder<'_> {
+    fn define(&mut self, ident: &IdentifierExpression, kind: DefinitionKind, scope: Span) -> usize {
+        let id = self.symbols.definitions.len();
+        self.symbols.definitions.push(Definition {
+            name: ident.name.clone(),
+            kind,
+            span: ident.span,
+            scope,
+            value: ValueKind::Unknown,
+            exported: false,
+        });
+        self.symbols
+            .references
+            .push((ident.span, Target::Definition(id)));
+        id
+    }
+
+    fn enter_scope(
+        &mut self,
+        span: Span,
+        parameters: &[IdentifierExpression],
+        kind: DefinitionKind,
+        lets: Vec<IdentifierExpression>,
+    ) {
+        let mut scope = Scope {
+            declared: HashMap::new(),
+            first: HashMap::new(),
+            lets: HashMap::new(),
+            arm: kind == DefinitionKind::MatchBinding,
+        };
+        for param in parameters {
+            let id = self.define(param, kind, span);
+            scope.declared.insert(param.name.clone(), id);
+            scope.first.entry(param.name.clone()).or_insert(id);
+        }
+        for ident in &lets {
+            let id = self.define(ident, DefinitionKind::Let, span);
+            scope.first.entry(ident.name.clone()).or_insert(id);
+            scope.lets.insert(ident.span, id);
+        }
+        self.scopes.push(scope);
+    }
+
+    fn lookup(&self, name: &str) -> Target {
+        let mut in_function = false;
+        for scope in self.scopes.iter().rev() {
+            let id = match scope.declared.get(name) {
+                Some(id) => Some(id),
+                // a function may run after the enclosing scope bound the name
+                None if in_function => scope.first.get(name),
+                None => None,
+            };
+            in_function |= !scope.arm;
+            if let Some(&id) = id {
+                return Target::Definition(id);
+            }
+        }
+        match Builtin::ALL.iter().find(|b| b.name() == name) {
+            Some(b) => Target::Builtin(*b),
+            None => Target::Undefined,
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Binder<'_> {
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        walk_let_statement(self, stmt);
+        // only a plain name is known to hold the value itself
+        let value = match stmt.pattern {
+            Pattern::Identifier(_) => ValueKind::of(&stmt.value, self.symbols),
+            _ => ValueKind::Unknown,
+        };
+        for ident in stmt.pattern.identifiers() {
+            let scope = self.scopes.last_mut().unwrap();
+            if let Some(&id) = scope.lets.get(&ident.span) {
+                scope.declared.insert(ident.name.clone(), id);
+                self.symbols.definitions[id].value = value.clone();
+                self.symbols.definitions[id].exported = stmt.exported;
+            }
+        }
+    }
+
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm) {
+        self.visit_pattern(&arm.pattern);
+        let names = arm
+            .pattern
+            .identifiers()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.enter_scope(
+            arm.span,
+            &names,
+            DefinitionKind::MatchBinding,
+            arm_bindings(arm),
+        );
+        if let Some(guard) = &arm.guard {
+            self.visit_expression(guard);
+        }
+        self.visit_expression(&arm.body);
+        self.scopes.pop();
+    }
+
+    fn visit_identifier(&mut self, ident: &'ast IdentifierExpression) {
+        let target = self.lookup(&ident.name);
+        self.symbols.references.push((ident.span, target));
+    }
+
+    fn visit_function_literal(&mut self, func: &'ast FunctionLiteral) {
+        let lets = scope_bindings(&func.body.statements);
+        let parameters = parameter_names(&func.parameters);
+        self.enter_scope(func.span, &parameters, DefinitionKind::Parameter, lets);
+        walk_block_statement(self, &func.body);
+        self.scopes.pop();
+    }
+
+    fn visit_macro_literal(&mut self, mac: &'ast MacroLiteral) {
+        let lets = scope_bindings(&mac.body.statements);
+        let parameters = parameter_names(&mac.parameters);
+        self.enter_scope(mac.span, &parameters, DefinitionKind::Parameter, lets);
+        walk_block_statement(self, &mac.body);
+        self.scopes.pop();
+    }
+
+    fn visit_quote_expression(&mut self, exp: &'ast QuoteExpression) {
+        walk_unquoted(self, exp);
+    }
+}
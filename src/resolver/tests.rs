@@ -0,0 +1,210 @@
+use super::*;
+use crate::{
+    lexer::{token::Span, Lexer},
+    parser::node::Expression,
+    parser::Parser,
+};
+
+fn setup(input: &str) -> (Program, Vec<Diagnostic>) {
+    let mut program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+    let diagnostics = resolve(&mut program);
+    (program, diagnostics)
+}
+
+fn function_body(exp: &Expression) -> &[Statement] {
+    match exp {
+        Expression::Function(f) => &f.body.statements,
+        _ => panic!("expected function literal but got {}", exp),
+    }
+}
+
+fn binding(stmt: &Statement) -> Option<Binding> {
+    match stmt {
+        Statement::Expression(exp) => match &exp.expression {
+            Expression::Identifier(ident) => ident.binding,
+            Expression::Infix(infix) => match &infix.right {
+                Expression::Identifier(ident) => ident.binding,
+                exp => panic!("expected identifier but got {}", exp),
+            },
+            exp => panic!("expected identifier but got {}", exp),
+        },
+        Statement::Return(ret) => match &ret.value {
+            Expression::Identifier(ident) => ident.binding,
+            exp => panic!("expected identifier but got {}", exp),
+        },
+        Statement::Let(stmt) => stmt.pattern.identifiers()[0].binding,
+    }
+}
+
+#[test]
+fn test_slots() {
+    let input = "let g = 1;
+let f = fn(a, b) {
+    let c = a;
+    if (c) { let d = b; }
+    fn(e) { c + e; d; g; len };
+};";
+    let (program, diagnostics) = setup(input);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    assert_eq!(binding(&program.body[0]), Some(Binding::Global));
+    let f = match &program.body[1] {
+        Statement::Let(stmt) => &stmt.value,
+        stmt => panic!("expected let statement but got {}", stmt),
+    };
+    let body = function_body(f);
+    assert_eq!(
+        binding(&body[0]),
+        Some(Binding::Local { depth: 0, slot: 2 })
+    );
+
+    let inner = match &body[2] {
+        Statement::Expression(exp) => function_body(&exp.expression),
+        stmt => panic!("expected expression statement but got {}", stmt),
+    };
+    // `c + e`
+    assert_eq!(
+        binding(&inner[0]),
+        Some(Binding::Local { depth: 0, slot: 0 })
+    );
+    let tests = [
+        Some(Binding::Local { depth: 1, slot: 3 }),
+        Some(Binding::Global),
+        Some(Binding::Global),
+    ];
+    for (stmt, expected) in inner[1..].iter().zip(tests.iter()) {
+        assert_eq!(&binding(stmt), expected);
+    }
+}
+
+#[test]
+fn test_rebinding_reuses_slot() {
+    let (program, _) = setup("fn(x) { let y = x; let y = y + 1; let x = y; x }");
+    let body = match &program.body[0] {
+        Statement::Expression(exp) => function_body(&exp.expression),
+        stmt => panic!("expected expression statement but got {}", stmt),
+    };
+    let tests = [
+        Some(Binding::Local { depth: 0, slot: 1 }),
+        Some(Binding::Local { depth: 0, slot: 1 }),
+        Some(Binding::Local { depth: 0, slot: 0 }),
+        Some(Binding::Local { depth: 0, slot: 0 }),
+    ];
+    for (stmt, expected) in body.iter().zip(tests.iter()) {
+        assert_eq!(&binding(stmt), expected);
+    }
+}
+
+#[test]
+fn test_match_arm_slots() {
+    let (program, diagnostics) = setup("fn(a) { let b = a; match (a) { [c, d] => b + d } }");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let body = match &program.body[0] {
+        Statement::Expression(exp) => function_body(&exp.expression),
+        stmt => panic!("expected expression statement but got {}", stmt),
+    };
+    let arm = match &body[1] {
+        Statement::Expression(exp) => match &exp.expression {
+            Expression::Match(exp) => &exp.arms[0],
+            exp => panic!("expected match expression but got {}", exp),
+        },
+        stmt => panic!("expected expression statement but got {}", stmt),
+    };
+    // the arm binds its pattern in its own scope, leaving the function's slots alone
+    let names: Vec<Option<Binding>> = arm
+        .pattern
+        .identifiers()
+        .iter()
+        .map(|ident| ident.binding)
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            Some(Binding::Local { depth: 0, slot: 0 }),
+            Some(Binding::Local { depth: 0, slot: 1 }),
+        ]
+    );
+    let (left, right) = match &arm.body {
+        Expression::Infix(infix) => (&infix.left, &infix.right),
+        exp => panic!("expected infix expression but got {}", exp),
+    };
+    let tests = [
+        (left, Binding::Local { depth: 1, slot: 1 }),
+        (right, Binding::Local { depth: 0, slot: 1 }),
+    ];
+    for (exp, expected) in tests.iter() {
+        match exp {
+            Expression::Identifier(ident) => assert_eq!(ident.binding, Some(*expected)),
+            exp => panic!("expected identifier but got {}", exp),
+        }
+    }
+}
+
+#[test]
+fn test_diagnostics() {
+    let tests = [
+        ("foobar", vec![(DiagnosticKind::Undefined, "foobar")]),
+        ("let a = a;", vec![(DiagnosticKind::Undefined, "a")]),
+        ("a; let a = 1;", vec![(DiagnosticKind::Undefined, "a")]),
+        // functions see bindings made after them
+        ("let f = fn() { g() }; let g = fn() { f() };", vec![]),
+        ("let f = fn() { f() };", vec![]),
+        (
+            "let x = 1; fn(x) { let y = 2; fn() { let y = x; y } }",
+            vec![
+                (DiagnosticKind::Shadowed, "x"),
+                (DiagnosticKind::Shadowed, "y"),
+            ],
+        ),
+        ("fn(a) { b }", vec![(DiagnosticKind::Undefined, "b")]),
+        // the names bound by a match arm are only visible in its guard and body
+        (
+            "match (1) { a => a }; a",
+            vec![(DiagnosticKind::Undefined, "a")],
+        ),
+        (
+            "match ([1]) { [a] if a > 5 => 0, _ => a }",
+            vec![(DiagnosticKind::Undefined, "a")],
+        ),
+        (
+            "let x = 1; fn(y) { match (2) { x => x, y => y } }",
+            vec![
+                (DiagnosticKind::Shadowed, "x"),
+                (DiagnosticKind::Shadowed, "y"),
+            ],
+        ),
+        // an arm runs right away, unlike a function
+        (
+            "match (1) { a => b }; let b = 1;",
+            vec![(DiagnosticKind::Undefined, "b")],
+        ),
+        ("len; puts", vec![]),
+    ];
+
+    for (input, expected) in tests.iter() {
+        let (_, diagnostics) = setup(input);
+        let diagnostics: Vec<(DiagnosticKind, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.kind, d.name.as_str()))
+            .collect();
+        assert_eq!(&diagnostics, expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_known_globals() {
+    let mut program = Parser::new(Lexer::new("fn() { let host = 1; host + other }"))
+        .parse_program()
+        .unwrap();
+    let diagnostics = Resolver::with_globals(vec!["host".to_string(), "other".to_string()])
+        .resolve_program(&mut program);
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic {
+            kind: DiagnosticKind::Shadowed,
+            name: "host".to_string(),
+            span: Span::new(11, 15),
+        }]
+    );
+    assert_eq!(diagnostics[0].to_string(), "host shadows an outer binding");
+}